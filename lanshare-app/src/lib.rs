@@ -1 +1,8 @@
-pub mod use_cases;
\ No newline at end of file
+pub mod offers;
+pub mod peer_resolver;
+pub mod rate_limit;
+pub mod receive_policy;
+pub mod retry;
+pub mod time;
+pub mod transfers;
+pub mod use_cases;
@@ -0,0 +1,208 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Sender},
+    },
+    time::Duration,
+};
+
+use lanshare_domain::{
+    error::DomainError,
+    models::{FileManifest, IncomingOffer, Peer},
+};
+
+use crate::time::unix_now;
+
+/// Incoming transfers held until the user accepts or rejects them. The
+/// sender's connection stays open meanwhile, so offers only live in memory.
+/// An accepted file is not asked about again when its sender reconnects to
+/// resume it.
+#[derive(Clone, Default)]
+pub struct IncomingOffers {
+    pending: Arc<Mutex<HashMap<String, PendingOffer>>>,
+    accepted: Arc<Mutex<HashSet<(IpAddr, String)>>>,
+}
+
+struct PendingOffer {
+    offer: IncomingOffer,
+    decision: Sender<bool>,
+}
+
+impl IncomingOffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Blocks until the offer is decided; an offer nobody answers within
+    /// `timeout` is rejected.
+    pub fn wait(
+        &self,
+        manifest: &FileManifest,
+        sender: SocketAddr,
+        peer: Option<&Peer>,
+        timeout: Duration,
+    ) -> Result<(), DomainError> {
+        let resumed = (sender.ip(), manifest.file_id.clone());
+        if self
+            .accepted
+            .lock()
+            .is_ok_and(|accepted| accepted.contains(&resumed))
+        {
+            return Ok(());
+        }
+
+        let offer = new_offer(&manifest.name, manifest.size, sender, peer, timeout);
+        if self.ask(offer, timeout)? {
+            if let Ok(mut accepted) = self.accepted.lock() {
+                accepted.insert(resumed);
+            }
+            Ok(())
+        } else {
+            Err(DomainError::TransferRejected)
+        }
+    }
+
+    /// Like `wait`, for a stream; streams cannot be resumed, so accepting
+    /// one is not remembered.
+    pub fn wait_for_stream(
+        &self,
+        name: &str,
+        sender: SocketAddr,
+        peer: Option<&Peer>,
+        timeout: Duration,
+    ) -> Result<(), DomainError> {
+        let mut offer = new_offer(name, 0, sender, peer, timeout);
+        offer.streamed = true;
+        if self.ask(offer, timeout)? {
+            Ok(())
+        } else {
+            Err(DomainError::TransferRejected)
+        }
+    }
+
+    fn ask(&self, offer: IncomingOffer, timeout: Duration) -> Result<bool, DomainError> {
+        let offer_id = offer.offer_id.clone();
+        let (tx, rx) = mpsc::channel();
+        self.pending
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?
+            .insert(
+                offer_id.clone(),
+                PendingOffer {
+                    offer,
+                    decision: tx,
+                },
+            );
+
+        let accepted = rx.recv_timeout(timeout).unwrap_or(false);
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&offer_id);
+        }
+        Ok(accepted)
+    }
+
+    pub fn decide(&self, offer_id: &str, accept: bool) -> Result<(), DomainError> {
+        let pending = self
+            .pending
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        let pending = pending
+            .get(offer_id)
+            .ok_or_else(|| DomainError::NotFound(format!("offer {}", offer_id)))?;
+        pending
+            .decision
+            .send(accept)
+            .map_err(|_| DomainError::NotFound(format!("offer {}", offer_id)))
+    }
+
+    /// Answers every open offer with a rejection, e.g. on shutdown.
+    pub fn reject_all(&self) {
+        if let Ok(pending) = self.pending.lock() {
+            for offer in pending.values() {
+                let _ = offer.decision.send(false);
+            }
+        }
+    }
+
+    pub fn pending(&self) -> Vec<IncomingOffer> {
+        let mut offers: Vec<IncomingOffer> = self
+            .pending
+            .lock()
+            .map(|pending| {
+                pending
+                    .values()
+                    .map(|pending| pending.offer.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        offers.sort_by_key(|offer| offer.offered_at);
+        offers
+    }
+}
+
+fn new_offer(
+    name: &str,
+    size: u64,
+    sender: SocketAddr,
+    peer: Option<&Peer>,
+    timeout: Duration,
+) -> IncomingOffer {
+    let offered_at = unix_now();
+    IncomingOffer {
+        offer_id: uuid::Uuid::new_v4().simple().to_string(),
+        sender,
+        peer: peer.map(|peer| peer.display_name().to_string()),
+        name: name.to_string(),
+        size,
+        streamed: false,
+        offered_at,
+        expires_at: offered_at + timeout.as_secs(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn manifest() -> FileManifest {
+        FileManifest {
+            file_id: "abc".to_string(),
+            name: "photo.jpg".to_string(),
+            size: 42,
+            sha256: [0; 32],
+        }
+    }
+
+    #[test]
+    fn offers_wait_for_a_decision_or_time_out() {
+        let offers = IncomingOffers::new();
+        let sender = "192.168.1.20:4000".parse().unwrap();
+
+        let waiting = offers.clone();
+        let handle =
+            thread::spawn(move || waiting.wait(&manifest(), sender, None, Duration::from_secs(10)));
+        let offer_id = loop {
+            if let Some(offer) = offers.pending().pop() {
+                break offer.offer_id;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        offers.decide(&offer_id, true).unwrap();
+        assert!(handle.join().unwrap().is_ok());
+        assert!(offers.pending().is_empty());
+        assert!(offers.decide(&offer_id, false).is_err());
+        // Reconnecting to resume the accepted file does not ask again.
+        assert!(
+            offers
+                .wait(&manifest(), sender, None, Duration::from_millis(20))
+                .is_ok()
+        );
+
+        let other_sender = "192.168.1.21:4000".parse().unwrap();
+        let result = offers.wait(&manifest(), other_sender, None, Duration::from_millis(20));
+        assert!(matches!(result, Err(DomainError::TransferRejected)));
+    }
+}
@@ -0,0 +1,228 @@
+use std::net::SocketAddr;
+
+use lanshare_domain::{error::DomainError, models::Peer};
+
+const MIN_DEVICE_ID_PREFIX: usize = 4;
+const MAX_TYPO_DISTANCE: usize = 2;
+
+/// A matcher, and whether a single hit may be picked without asking.
+type Tier<'a> = (bool, &'a dyn Fn(&Peer) -> bool);
+
+/// Resolves a user supplied peer reference (alias, display name, device ID
+/// prefix, hostname or `ip:port`) against the currently known peers.
+///
+/// Matching runs in tiers from exact to fuzzy; the first tier with any hit
+/// decides, so an exact alias always wins over a similar-looking name. Only
+/// exact names and device ID prefixes pick a peer on their own; fuzzy hits
+/// come back as candidates, since the peer meant may just be offline.
+pub fn resolve_peer(query: &str, peers: &[Peer]) -> Result<Peer, DomainError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(DomainError::NotFound("Empty peer name".to_string()));
+    }
+
+    if let Ok(address) = query.parse::<SocketAddr>() {
+        return Ok(peers
+            .iter()
+            .find(|peer| peer.address == address)
+            .cloned()
+            .unwrap_or_else(|| Peer::new(query.to_string(), address, 0)));
+    }
+
+    let tiers: [Tier; 4] = [
+        (true, &|peer| matches_exactly(peer, query)),
+        (true, &|peer| {
+            query.len() >= MIN_DEVICE_ID_PREFIX
+                && peer
+                    .metadata
+                    .device_id
+                    .to_lowercase()
+                    .starts_with(&query.to_lowercase())
+        }),
+        (false, &|peer| {
+            names(peer).any(|name| name.to_lowercase().contains(&query.to_lowercase()))
+        }),
+        (false, &|peer| {
+            names(peer).any(|name| {
+                edit_distance(&name.to_lowercase(), &query.to_lowercase()) <= MAX_TYPO_DISTANCE
+            })
+        }),
+    ];
+
+    for (decisive, tier) in tiers {
+        let candidates: Vec<&Peer> = peers.iter().filter(|peer| tier(peer)).collect();
+        match candidates.as_slice() {
+            [] => continue,
+            [single] if decisive => return Ok((*single).clone()),
+            candidates => {
+                return Err(DomainError::AmbiguousPeer(
+                    candidates.iter().map(|peer| describe(peer)).collect(),
+                ));
+            }
+        }
+    }
+
+    Err(DomainError::NotFound(format!(
+        "No peer matches '{}'",
+        query
+    )))
+}
+
+/// True when the query names this peer without any fuzzy matching.
+pub fn matches_exactly(peer: &Peer, query: &str) -> bool {
+    let query = query.trim();
+    peer.address.to_string() == query
+        || peer.name == query
+        || (!peer.metadata.device_id.is_empty() && peer.metadata.device_id == query)
+        || names(peer).any(|name| name.eq_ignore_ascii_case(query))
+}
+
+pub fn hostname(peer: &Peer) -> &str {
+    peer.name.split("._").next().unwrap_or(&peer.name)
+}
+
+fn names(peer: &Peer) -> impl Iterator<Item = &str> {
+    [
+        peer.alias.as_deref(),
+        Some(peer.metadata.display_name.as_str()),
+        Some(hostname(peer)),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|name| !name.is_empty())
+}
+
+fn describe(peer: &Peer) -> String {
+    let device_id = &peer.metadata.device_id;
+    if device_id.is_empty() {
+        format!("{} ({})", peer.display_name(), peer.address)
+    } else {
+        let short_id: String = device_id.chars().take(8).collect();
+        format!(
+            "{} ({}, id {})",
+            peer.display_name(),
+            peer.address,
+            short_id
+        )
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(fullname: &str, display_name: &str, device_id: &str, address: &str) -> Peer {
+        let mut peer = Peer::new(fullname.to_string(), address.parse().unwrap(), 0);
+        peer.metadata.display_name = display_name.to_string();
+        peer.metadata.device_id = device_id.to_string();
+        peer
+    }
+
+    fn peers() -> Vec<Peer> {
+        let mut build = peer(
+            "build-01._lanshare._tcp.local.",
+            "Build Server",
+            "5f0c1a2b-0000",
+            "10.0.0.5:8080",
+        );
+        build.alias = Some("ci".to_string());
+        vec![
+            build,
+            peer(
+                "alice-laptop._lanshare._tcp.local.",
+                "Alice",
+                "9a8b7c6d-1111",
+                "10.0.0.7:8080",
+            ),
+            peer(
+                "alice-desktop._lanshare._tcp.local.",
+                "Alice Desktop",
+                "9a8b0000-2222",
+                "10.0.0.8:8080",
+            ),
+        ]
+    }
+
+    #[test]
+    fn resolves_alias_display_name_hostname_and_device_id() {
+        let peers = peers();
+        assert_eq!(
+            resolve_peer("ci", &peers).unwrap().metadata.display_name,
+            "Build Server"
+        );
+        assert_eq!(
+            resolve_peer("build server", &peers)
+                .unwrap()
+                .metadata
+                .device_id,
+            "5f0c1a2b-0000"
+        );
+        assert_eq!(
+            resolve_peer("build-01", &peers).unwrap().metadata.device_id,
+            "5f0c1a2b-0000"
+        );
+        assert_eq!(
+            resolve_peer("9a8b7", &peers).unwrap().metadata.display_name,
+            "Alice"
+        );
+    }
+
+    #[test]
+    fn exact_match_wins_over_fuzzy_candidates() {
+        assert_eq!(
+            resolve_peer("Alice", &peers()).unwrap().address.to_string(),
+            "10.0.0.7:8080"
+        );
+    }
+
+    #[test]
+    fn ambiguous_queries_list_candidates() {
+        match resolve_peer("alice-", &peers()) {
+            Err(DomainError::AmbiguousPeer(candidates)) => assert_eq!(candidates.len(), 2),
+            other => panic!("expected ambiguity, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn raw_addresses_resolve_even_when_unknown() {
+        let resolved = resolve_peer("10.2.0.15:8080", &peers()).unwrap();
+        assert_eq!(resolved.address.to_string(), "10.2.0.15:8080");
+    }
+
+    #[test]
+    fn small_typos_are_suggested_but_not_picked() {
+        match resolve_peer("bild-01", &peers()) {
+            Err(DomainError::AmbiguousPeer(candidates)) => {
+                assert_eq!(candidates, vec!["ci (10.0.0.5:8080, id 5f0c1a2b)"])
+            }
+            other => panic!("expected a suggestion, got {:?}", other),
+        }
+        assert!(matches!(
+            resolve_peer("nobody", &peers()),
+            Err(DomainError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn an_offline_peer_is_not_swapped_for_an_online_neighbour() {
+        // build-02 is offline; only build-01 is one typo away.
+        match resolve_peer("build-02", &peers()) {
+            Err(DomainError::AmbiguousPeer(candidates)) => assert_eq!(candidates.len(), 1),
+            other => panic!("expected a suggestion, got {:?}", other),
+        }
+    }
+}
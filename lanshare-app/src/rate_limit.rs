@@ -0,0 +1,98 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Token bucket shared by all transfers in one direction. A rate of 0 means
+/// unlimited; the rate can be changed while transfers are running.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    state: Arc<Mutex<BucketState>>,
+}
+
+#[derive(Default)]
+struct BucketState {
+    bytes_per_sec: u64,
+    available: f64,
+    last_refill: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let limiter = Self::default();
+        limiter.set_rate(bytes_per_sec);
+        limiter
+    }
+
+    pub fn set_rate(&self, bytes_per_sec: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.bytes_per_sec = bytes_per_sec;
+            state.available = state.available.min(bytes_per_sec as f64);
+        }
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.state
+            .lock()
+            .map(|state| state.bytes_per_sec)
+            .unwrap_or(0)
+    }
+
+    /// Blocks until `bytes` may be transferred under the current rate.
+    pub fn acquire(&self, bytes: usize) {
+        let wait = match self.state.lock() {
+            Ok(mut state) => state.reserve(bytes as f64, Instant::now()),
+            Err(_) => None,
+        };
+        if let Some(wait) = wait {
+            thread::sleep(wait);
+        }
+    }
+}
+
+impl BucketState {
+    fn reserve(&mut self, bytes: f64, now: Instant) -> Option<Duration> {
+        if self.bytes_per_sec == 0 {
+            self.last_refill = None;
+            return None;
+        }
+        let rate = self.bytes_per_sec as f64;
+        if let Some(last) = self.last_refill {
+            let refill = now.duration_since(last).as_secs_f64() * rate;
+            self.available = (self.available + refill).min(rate);
+        }
+        self.last_refill = Some(now);
+
+        // Going negative lets chunks larger than one second's budget through
+        // while still charging later callers for them.
+        self.available -= bytes;
+        (self.available < 0.0).then(|| Duration::from_secs_f64(-self.available / rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservations_beyond_the_budget_wait_proportionally() {
+        let mut state = BucketState {
+            bytes_per_sec: 1000,
+            ..BucketState::default()
+        };
+        let start = Instant::now();
+
+        assert_eq!(
+            state.reserve(500.0, start),
+            Some(Duration::from_millis(500))
+        );
+        let wait = state
+            .reserve(500.0, start + Duration::from_millis(500))
+            .unwrap();
+        assert_eq!(wait.as_millis(), 500);
+
+        state.bytes_per_sec = 0;
+        assert_eq!(state.reserve(1_000_000.0, start), None);
+    }
+}
@@ -0,0 +1,59 @@
+use std::{net::IpAddr, time::Duration};
+
+use lanshare_domain::{
+    error::DomainError,
+    models::{FileManifest, Peer},
+};
+
+use crate::peer_resolver::matches_exactly;
+
+/// Decides which incoming transfers are refused outright and whether the
+/// rest are accepted without asking.
+#[derive(Debug, Clone)]
+pub struct ReceivePolicy {
+    pub auto_accept: bool,
+    /// How long a transfer that needs asking waits for an answer.
+    pub offer_timeout: Duration,
+    /// Sender IPs or peer references (alias, name, device ID); empty allows
+    /// every sender.
+    pub accept_from: Vec<String>,
+    /// Largest accepted file in bytes; 0 means no limit.
+    pub max_file_size: u64,
+}
+
+impl Default for ReceivePolicy {
+    fn default() -> Self {
+        Self {
+            auto_accept: true,
+            offer_timeout: Duration::from_secs(120),
+            accept_from: Vec::new(),
+            max_file_size: 0,
+        }
+    }
+}
+
+impl ReceivePolicy {
+    pub fn check(
+        &self,
+        manifest: &FileManifest,
+        address: IpAddr,
+        sender: Option<&Peer>,
+    ) -> Result<(), DomainError> {
+        if self.max_file_size > 0 && manifest.size > self.max_file_size {
+            return Err(DomainError::TransferRejected);
+        }
+        if self.accept_from.is_empty() {
+            return Ok(());
+        }
+
+        let allowed = self.accept_from.iter().any(|rule| {
+            rule.parse::<IpAddr>().is_ok_and(|ip| ip == address)
+                || sender.is_some_and(|peer| matches_exactly(peer, rule))
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(DomainError::TransferRejected)
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, RwLock,
+        mpsc::{self, Receiver, Sender},
+    },
+    time::{Duration, Instant},
+};
+
+use lanshare_domain::models::{TransferState, TransferStatus};
+
+/// Subscribers see every state change, but progress alone at most this
+/// often per transfer.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Default)]
+pub struct TransferTracker {
+    transfers: Arc<RwLock<HashMap<String, TransferStatus>>>,
+    subscribers: Arc<Mutex<Vec<Sender<TransferStatus>>>>,
+    last_published: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl TransferTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&self, peer: &str, file_path: &str) -> String {
+        let transfer_id = uuid::Uuid::new_v4().to_string();
+        let status = TransferStatus {
+            transfer_id: transfer_id.clone(),
+            peer: peer.to_string(),
+            file_path: file_path.to_string(),
+            state: TransferState::Queued,
+            bytes_sent: 0,
+            total_bytes: 0,
+            attempts: Vec::new(),
+            streamed: false,
+        };
+        if let Ok(mut guard) = self.transfers.write() {
+            guard.insert(transfer_id.clone(), status.clone());
+        }
+        self.publish(status);
+        transfer_id
+    }
+
+    pub fn update<F: FnOnce(&mut TransferStatus)>(&self, transfer_id: &str, f: F) {
+        let (updated, state_changed) = {
+            let Ok(mut guard) = self.transfers.write() else {
+                return;
+            };
+            let Some(status) = guard.get_mut(transfer_id) else {
+                return;
+            };
+            let previous = status.state;
+            f(status);
+            (status.clone(), status.state != previous)
+        };
+
+        let due = self.last_published.lock().is_ok_and(|last| {
+            last.get(transfer_id)
+                .is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL)
+        });
+        if state_changed || due {
+            self.publish(updated);
+        }
+    }
+
+    /// Streams transfer changes from now on; dropping the receiver
+    /// unsubscribes.
+    pub fn subscribe(&self) -> Receiver<TransferStatus> {
+        let (tx, rx) = mpsc::channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    fn publish(&self, status: TransferStatus) {
+        if let Ok(mut last) = self.last_published.lock() {
+            last.insert(status.transfer_id.clone(), Instant::now());
+        }
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|tx| tx.send(status.clone()).is_ok());
+        }
+    }
+
+    pub fn get(&self, transfer_id: &str) -> Option<TransferStatus> {
+        self.transfers
+            .read()
+            .ok()
+            .and_then(|guard| guard.get(transfer_id).cloned())
+    }
+
+    pub fn list(&self) -> Vec<TransferStatus> {
+        self.transfers
+            .read()
+            .map(|guard| guard.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribers_see_state_changes_but_not_every_progress_step() {
+        let tracker = TransferTracker::new();
+        let events = tracker.subscribe();
+        let id = tracker.register("peer", "/tmp/file");
+        for sent in 1..=100 {
+            tracker.update(&id, |status| status.bytes_sent = sent);
+        }
+        tracker.update(&id, |status| status.state = TransferState::Completed);
+
+        let received: Vec<TransferStatus> = events.try_iter().collect();
+        assert_eq!(received.first().unwrap().state, TransferState::Queued);
+        assert_eq!(received.last().unwrap().state, TransferState::Completed);
+        assert!(received.len() < 10);
+    }
+}
@@ -1,2 +1,3 @@
+pub mod outbox;
+pub mod receive_file;
 pub mod send_file;
-pub mod receive_file;
\ No newline at end of file
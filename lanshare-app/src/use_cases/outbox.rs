@@ -0,0 +1,116 @@
+use std::{
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    time::Duration,
+};
+
+use lanshare_domain::{
+    error::DomainError,
+    models::{OutboxEntry, OutboxEvent},
+    ports::OutboxPort,
+};
+
+use crate::time::unix_now;
+
+pub struct OutboxUseCase<O: OutboxPort> {
+    outbox: O,
+    subscribers: Mutex<Vec<Sender<OutboxEvent>>>,
+}
+
+impl<O: OutboxPort> OutboxUseCase<O> {
+    pub fn new(outbox: O) -> Self {
+        Self {
+            outbox,
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Streams entries leaving the outbox from now on; dropping the receiver
+    /// unsubscribes.
+    pub fn subscribe(&self) -> Receiver<OutboxEvent> {
+        let (tx, rx) = mpsc::channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    fn publish(&self, event: OutboxEvent) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+        }
+    }
+
+    pub fn delivered(&self, entry: OutboxEntry) {
+        self.publish(OutboxEvent::Delivered { entry });
+    }
+
+    pub fn dropped(&self, entry: OutboxEntry, reason: impl Into<String>) {
+        self.publish(OutboxEvent::Dropped {
+            entry,
+            reason: reason.into(),
+        });
+    }
+
+    pub fn queue(
+        &self,
+        peer: &str,
+        file_path: &str,
+        expires_in: Duration,
+    ) -> Result<OutboxEntry, DomainError> {
+        let now = unix_now();
+        let entry = OutboxEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            peer: peer.to_string(),
+            file_path: file_path.to_string(),
+            queued_at: now,
+            expires_at: now + expires_in.as_secs(),
+        };
+        self.outbox.enqueue(&entry)?;
+        Ok(entry)
+    }
+
+    /// Puts an entry back for the next delivery attempt, unless it expired
+    /// meanwhile.
+    pub fn requeue(&self, entry: &OutboxEntry) -> Result<(), DomainError> {
+        if entry.is_expired(unix_now()) {
+            self.publish(OutboxEvent::Expired {
+                entry: entry.clone(),
+            });
+            return Ok(());
+        }
+        self.outbox.enqueue(entry)
+    }
+
+    pub fn remove(&self, entry_id: &str) -> Result<(), DomainError> {
+        self.outbox.remove(entry_id)
+    }
+
+    pub fn pending(&self) -> Result<Vec<OutboxEntry>, DomainError> {
+        self.outbox.pending()
+    }
+
+    /// Removes and returns the live entries addressed to a peer the caller
+    /// is about to deliver to.
+    pub fn take_for_peer<F: Fn(&str) -> bool>(
+        &self,
+        matches_peer: F,
+    ) -> Result<Vec<OutboxEntry>, DomainError> {
+        let now = unix_now();
+        self.outbox
+            .take(&|entry| !entry.is_expired(now) && matches_peer(&entry.peer))
+    }
+
+    pub fn purge_expired(&self) -> Result<Vec<OutboxEntry>, DomainError> {
+        let now = unix_now();
+        let expired = self.outbox.take(&|entry| entry.is_expired(now))?;
+        for entry in &expired {
+            self.publish(OutboxEvent::Expired {
+                entry: entry.clone(),
+            });
+        }
+        Ok(expired)
+    }
+}
@@ -1,28 +1,213 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Instant,
+};
+
 use lanshare_domain::{
     error::DomainError,
-    models::{FileBlock, FileManifest},
-    ports::StoragePort,
+    models::{Direction, FileBlock, FileManifest, Peer, PeerStatus, TransferOutcome},
+    ports::{MetricsPort, NoopMetrics, StoragePort},
 };
 
+use crate::{offers::IncomingOffers, rate_limit::RateLimiter, receive_policy::ReceivePolicy};
+
+type PeerLookup = Box<dyn Fn(&SocketAddr) -> Option<Peer> + Send + Sync>;
+
 pub struct ReceiveFileUseCase<S: StoragePort> {
     storage: S,
+    policy: RwLock<ReceivePolicy>,
+    rate_limiter: RateLimiter,
+    peer_lookup: Option<PeerLookup>,
+    offers: IncomingOffers,
+    active: Arc<AtomicUsize>,
+    metrics: Arc<dyn MetricsPort>,
+}
+
+/// Held while an incoming connection is being served.
+pub struct ActiveTransfer {
+    active: Arc<AtomicUsize>,
+}
+
+impl Drop for ActiveTransfer {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl<S: StoragePort> ReceiveFileUseCase<S> {
     pub fn new(storage: S) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            policy: RwLock::new(ReceivePolicy::default()),
+            rate_limiter: RateLimiter::default(),
+            peer_lookup: None,
+            offers: IncomingOffers::new(),
+            active: Arc::new(AtomicUsize::new(0)),
+            metrics: Arc::new(NoopMetrics),
+        }
+    }
+
+    /// Maps a sender address to a known peer so accept rules can name peers.
+    pub fn with_peer_lookup<F>(mut self, lookup: F) -> Self
+    where
+        F: Fn(&SocketAddr) -> Option<Peer> + Send + Sync + 'static,
+    {
+        self.peer_lookup = Some(Box::new(lookup));
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsPort>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn metrics(&self) -> &dyn MetricsPort {
+        self.metrics.as_ref()
+    }
+
+    pub fn set_policy(&self, policy: ReceivePolicy) {
+        if let Ok(mut guard) = self.policy.write() {
+            *guard = policy;
+        }
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Transfers waiting for the user while auto-accept is off.
+    pub fn offers(&self) -> &IncomingOffers {
+        &self.offers
+    }
+
+    pub fn begin_transfer(&self) -> ActiveTransfer {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        ActiveTransfer {
+            active: self.active.clone(),
+        }
+    }
+
+    /// Busy while a transfer is coming in or an offer waits for the user.
+    pub fn status(&self) -> PeerStatus {
+        if self.active.load(Ordering::SeqCst) > 0 || !self.offers.pending().is_empty() {
+            PeerStatus::Busy
+        } else {
+            PeerStatus::Accepting
+        }
     }
 
-    pub fn accept_transfer(&self, manifest: &FileManifest) -> Result<u64, DomainError> {
+    pub fn accept_transfer(
+        &self,
+        manifest: &FileManifest,
+        sender: SocketAddr,
+    ) -> Result<u64, DomainError> {
+        let peer = self.peer_lookup.as_ref().and_then(|lookup| lookup(&sender));
+        let policy = self
+            .policy
+            .read()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?
+            .clone();
+        policy.check(manifest, sender.ip(), peer.as_ref())?;
+        if !policy.auto_accept {
+            self.offers
+                .wait(manifest, sender, peer.as_ref(), policy.offer_timeout)?;
+        }
+
         self.storage.prepare_for_receive(manifest)?;
         self.storage.get_written_bytes(&manifest.file_id)
     }
 
+    /// Like `accept_transfer` for a stream, which has no size to check up
+    /// front and starts over in a new partial file. Returns its file ID.
+    pub fn accept_stream(&self, name: &str, sender: SocketAddr) -> Result<String, DomainError> {
+        let peer = self.peer_lookup.as_ref().and_then(|lookup| lookup(&sender));
+        let policy = self
+            .policy
+            .read()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?
+            .clone();
+        let manifest = FileManifest {
+            file_id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            size: 0,
+            sha256: [0; 32],
+        };
+        policy.check(&manifest, sender.ip(), peer.as_ref())?;
+        if !policy.auto_accept {
+            self.offers
+                .wait_for_stream(name, sender, peer.as_ref(), policy.offer_timeout)?;
+        }
+
+        self.storage.prepare_for_receive(&manifest)?;
+        Ok(manifest.file_id)
+    }
+
+    /// Fails once a stream has grown past the largest accepted file.
+    pub fn check_stream_size(&self, size: u64) -> Result<(), DomainError> {
+        let max_file_size = self
+            .policy
+            .read()
+            .map(|policy| policy.max_file_size)
+            .unwrap_or(0);
+        if max_file_size > 0 && size > max_file_size {
+            return Err(DomainError::TransferRejected);
+        }
+        Ok(())
+    }
+
     pub fn process_chunk(&self, block: &FileBlock) -> Result<(), DomainError> {
-        self.storage.write_block(block)
+        self.rate_limiter.acquire(block.data.len());
+        let started = Instant::now();
+        self.storage.write_block(block)?;
+        self.metrics
+            .record_chunk_latency(Direction::Inbound, started.elapsed());
+        self.metrics
+            .record_bytes(Direction::Inbound, block.data.len() as u64);
+        Ok(())
+    }
+
+    pub fn written_bytes(&self, file_id: &str) -> Result<u64, DomainError> {
+        self.storage.get_written_bytes(file_id)
     }
 
     pub fn finish_transfer(&self, file_id: &str) -> Result<(), DomainError> {
         self.storage.complete_transfer(file_id)
     }
+
+    /// Verifies a stream against the size and checksum its sender computed
+    /// while sending it.
+    pub fn finish_stream(
+        &self,
+        file_id: &str,
+        size: u64,
+        sha256: &[u8; 32],
+    ) -> Result<(), DomainError> {
+        if self.storage.get_written_bytes(file_id)? != size {
+            return Err(DomainError::IntegrityError);
+        }
+        self.storage.seal_stream(file_id, size, sha256)?;
+        self.storage.complete_transfer(file_id)
+    }
+
+    /// Drops a partial file, e.g. one that failed its checksum and would
+    /// otherwise be resumed as is.
+    pub fn discard_transfer(&self, file_id: &str) -> Result<(), DomainError> {
+        self.storage.cancel_transfer(file_id)
+    }
+
+    /// Counts how an incoming transfer ended, once per connection.
+    pub fn report_outcome(&self, result: &Result<(), DomainError>) {
+        let outcome = match result {
+            Ok(()) => TransferOutcome::Completed,
+            Err(DomainError::TransferRejected) => TransferOutcome::Rejected,
+            Err(DomainError::IntegrityError) => TransferOutcome::IntegrityFailed,
+            Err(DomainError::Interrupted) => TransferOutcome::Interrupted,
+            Err(_) => TransferOutcome::Failed,
+        };
+        self.metrics.record_transfer(Direction::Inbound, outcome);
+    }
 }
@@ -1,58 +1,587 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
 use lanshare_domain::{
     error::DomainError,
-    models::Peer,
-    ports::{NetworkPort, StoragePort},
+    models::{Direction, FileManifest, Peer, TransferAttempt, TransferOutcome, TransferState},
+    ports::{DiscoveryPort, MetricsPort, NetworkConnection, NetworkPort, NoopMetrics, StoragePort},
 };
 use lanshare_proto::{
-    codec::encode_message,
-    messages::{DataChunkPayload, LanShareMessage, TransferRequestPayload},
+    codec::{decode_message, encode_message},
+    messages::{
+        DataChunkPayload, INTEGRITY_FAILED, LanShareMessage, StreamEndPayload,
+        StreamRequestPayload, TransferRequestPayload,
+    },
+};
+use sha2::{Digest, Sha256};
+use tracing::{Span, debug, field::Empty, info, info_span, warn};
+
+use crate::{
+    rate_limit::RateLimiter, retry::RetryPolicy, time::unix_now, transfers::TransferTracker,
 };
 
-pub struct SendFileUseCase<S: StoragePort, N: NetworkPort> {
+pub const DEFAULT_CHUNK_SIZE: usize = 8192;
+
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy)]
+enum StopRequest {
+    Pause,
+    Cancel,
+}
+
+pub struct SendFileUseCase<S: StoragePort, N: NetworkPort, D: DiscoveryPort> {
     storage: S,
     network: N,
+    discovery: D,
+    tracker: TransferTracker,
+    retry_policy: RetryPolicy,
+    chunk_size: usize,
+    rate_limiter: RateLimiter,
+    interrupted: AtomicBool,
+    stop_requests: Mutex<HashMap<String, StopRequest>>,
+    metrics: Arc<dyn MetricsPort>,
 }
 
-impl<S: StoragePort, N: NetworkPort> SendFileUseCase<S, N> {
-    pub fn new(storage: S, network: N) -> Self {
-        Self { storage, network }
+impl<S: StoragePort, N: NetworkPort, D: DiscoveryPort> SendFileUseCase<S, N, D> {
+    pub fn new(storage: S, network: N, discovery: D, tracker: TransferTracker) -> Self {
+        Self {
+            storage,
+            network,
+            discovery,
+            tracker,
+            retry_policy: RetryPolicy::default(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            rate_limiter: RateLimiter::default(),
+            interrupted: AtomicBool::new(false),
+            stop_requests: Mutex::new(HashMap::new()),
+            metrics: Arc::new(NoopMetrics),
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsPort>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Stops running transfers after their current chunk and refuses new
+    /// attempts; the receiver keeps what it got so a later send resumes.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops a transfer after its current chunk; `resume` continues it from
+    /// the receiver's partial file.
+    pub fn pause(&self, transfer_id: &str) -> Result<(), DomainError> {
+        if self
+            .tracker
+            .get(transfer_id)
+            .is_some_and(|status| status.streamed)
+        {
+            return Err(DomainError::Conflict(
+                "streams cannot be paused".to_string(),
+            ));
+        }
+        self.request_stop(transfer_id, StopRequest::Pause)
+    }
+
+    pub fn cancel(&self, transfer_id: &str) -> Result<(), DomainError> {
+        // A paused transfer has no running thread left to notice a request.
+        if self.state_of(transfer_id)? == TransferState::Paused {
+            self.tracker.update(transfer_id, |status| {
+                status.state = TransferState::Cancelled
+            });
+            self.metrics
+                .record_transfer(Direction::Outbound, TransferOutcome::Cancelled);
+            return Ok(());
+        }
+        self.request_stop(transfer_id, StopRequest::Cancel)
+    }
+
+    /// Marks a paused transfer as queued again and returns the peer and file
+    /// to `run` it with.
+    pub fn resume(&self, transfer_id: &str) -> Result<(Peer, String), DomainError> {
+        let status = self
+            .tracker
+            .get(transfer_id)
+            .ok_or_else(|| DomainError::NotFound(format!("transfer {}", transfer_id)))?;
+        if status.state != TransferState::Paused {
+            return Err(DomainError::Conflict(format!(
+                "transfer is {}, only paused transfers can be resumed",
+                status.state.as_str()
+            )));
+        }
+        let peer = self
+            .resolve_peer(&status.peer)
+            .ok_or_else(|| DomainError::Conflict(format!("peer {} is offline", status.peer)))?;
+        self.tracker
+            .update(transfer_id, |status| status.state = TransferState::Queued);
+        Ok((peer, status.file_path))
+    }
+
+    fn state_of(&self, transfer_id: &str) -> Result<TransferState, DomainError> {
+        self.tracker
+            .get(transfer_id)
+            .map(|status| status.state)
+            .ok_or_else(|| DomainError::NotFound(format!("transfer {}", transfer_id)))
+    }
+
+    fn request_stop(&self, transfer_id: &str, request: StopRequest) -> Result<(), DomainError> {
+        let state = self.state_of(transfer_id)?;
+        if state.is_finished() || state == TransferState::Paused {
+            return Err(DomainError::Conflict(format!(
+                "transfer is already {}",
+                state.as_str()
+            )));
+        }
+        self.stop_requests
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?
+            .insert(transfer_id.to_string(), request);
+        Ok(())
+    }
+
+    fn check_interrupted(&self, transfer_id: &str) -> Result<(), DomainError> {
+        if self.interrupted.load(Ordering::Relaxed) {
+            return Err(DomainError::Interrupted);
+        }
+        let request = self
+            .stop_requests
+            .lock()
+            .ok()
+            .and_then(|requests| requests.get(transfer_id).copied());
+        match request {
+            Some(StopRequest::Pause) => Err(DomainError::Paused),
+            Some(StopRequest::Cancel) => Err(DomainError::Cancelled),
+            None => Ok(()),
+        }
+    }
+
+    pub fn tracker(&self) -> &TransferTracker {
+        &self.tracker
+    }
+
+    pub fn enqueue(&self, peer: &Peer, file_path: &str) -> String {
+        let transfer_id = self.tracker.register(&peer.name, file_path);
+        if self.is_stream(file_path) {
+            self.tracker
+                .update(&transfer_id, |status| status.streamed = true);
+        }
+        transfer_id
+    }
+
+    /// Whether `file_path` is a pipe or device that is sent as a stream.
+    pub fn is_stream(&self, file_path: &str) -> bool {
+        self.storage.is_stream(file_path).unwrap_or(false)
     }
 
     pub fn execute(&self, peer: &Peer, file_path: &str) -> Result<(), DomainError> {
+        let transfer_id = self.enqueue(peer, file_path);
+        self.run(&transfer_id, peer, file_path)
+    }
+
+    pub fn run(&self, transfer_id: &str, peer: &Peer, file_path: &str) -> Result<(), DomainError> {
+        // `sha256` is what the receiver logs too, so both sides of a transfer
+        // can be matched up.
+        let span = info_span!(
+            "transfer",
+            transfer_id,
+            peer = %peer.display_name(),
+            file = file_path,
+            sha256 = Empty,
+        );
+        let _entered = span.enter();
+
+        let result = self.run_with_retries(transfer_id, peer, file_path);
+        if let Ok(mut requests) = self.stop_requests.lock() {
+            requests.remove(transfer_id);
+        }
+        match &result {
+            Ok(()) => info!("Transfer completed"),
+            Err(DomainError::Interrupted) => info!("Transfer interrupted by shutdown"),
+            Err(DomainError::Paused) => info!("Transfer paused"),
+            Err(DomainError::Cancelled) => info!("Transfer cancelled"),
+            Err(e) => warn!(error = ?e, "Transfer failed"),
+        }
+        let (final_state, outcome) = match result {
+            Ok(()) => (TransferState::Completed, Some(TransferOutcome::Completed)),
+            Err(DomainError::Interrupted) => (
+                TransferState::Interrupted,
+                Some(TransferOutcome::Interrupted),
+            ),
+            Err(DomainError::Paused) => (TransferState::Paused, None),
+            Err(DomainError::Cancelled) => {
+                (TransferState::Cancelled, Some(TransferOutcome::Cancelled))
+            }
+            Err(DomainError::TransferRejected) => {
+                (TransferState::Failed, Some(TransferOutcome::Rejected))
+            }
+            Err(_) => (TransferState::Failed, Some(TransferOutcome::Failed)),
+        };
+        if let Some(outcome) = outcome {
+            self.metrics.record_transfer(Direction::Outbound, outcome);
+        }
+        self.tracker
+            .update(transfer_id, |status| status.state = final_state);
+        result
+    }
+
+    fn run_with_retries(
+        &self,
+        transfer_id: &str,
+        peer: &Peer,
+        file_path: &str,
+    ) -> Result<(), DomainError> {
+        if self.is_stream(file_path) {
+            // What was read from a stream cannot be sent again, so stopping
+            // it for a shutdown ends it for good.
+            return match self.run_stream(transfer_id, peer, file_path) {
+                Err(DomainError::Interrupted) => Err(DomainError::Cancelled),
+                result => result,
+            };
+        }
+
         let manifest = self.storage.create_file_manifest(file_path)?;
+        Span::current().record("sha256", to_hex(&manifest.sha256).as_str());
+        self.tracker
+            .update(transfer_id, |status| status.total_bytes = manifest.size);
+
+        let deadline = Instant::now() + self.retry_policy.deadline;
+        let mut peer = peer.clone();
+        let mut attempt_number = 0;
+
+        loop {
+            self.check_interrupted(transfer_id)?;
+            attempt_number += 1;
+            debug!(attempt = attempt_number, address = %peer.address, "Connecting");
+            self.tracker.update(transfer_id, |status| {
+                status.state = TransferState::InProgress;
+                status.attempts.push(TransferAttempt {
+                    number: attempt_number,
+                    started_at: unix_now(),
+                    address: peer.address,
+                    resume_offset: 0,
+                    error: None,
+                });
+            });
+
+            let error = match self.attempt(transfer_id, &peer, file_path, &manifest) {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            // A stop requested while blocked on the peer wins over whatever
+            // the attempt ran into.
+            self.check_interrupted(transfer_id)?;
+            self.tracker.update(transfer_id, |status| {
+                if let Some(attempt) = status.attempts.last_mut() {
+                    attempt.error = Some(format!("{:?}", error));
+                }
+            });
+
+            // The receiver drops a file that fails its checksum, so the
+            // retry starts over from the first byte.
+            if !error.is_transient() && !matches!(error, DomainError::IntegrityError) {
+                return Err(error);
+            }
+
+            let delay = self.retry_policy.backoff(attempt_number);
+            if Instant::now() + delay >= deadline {
+                return Err(DomainError::DeadlineExceeded);
+            }
+            warn!(
+                attempt = attempt_number,
+                error = ?error,
+                retry_in_ms = delay.as_millis() as u64,
+                "Transfer attempt failed, retrying"
+            );
+
+            self.tracker
+                .update(transfer_id, |status| status.state = TransferState::Retrying);
+            self.sleep_unless_interrupted(transfer_id, delay)?;
+
+            if let Some(resolved) = self.resolve_peer(&peer.name) {
+                peer = resolved;
+            }
+        }
+    }
+
+    fn sleep_unless_interrupted(
+        &self,
+        transfer_id: &str,
+        delay: Duration,
+    ) -> Result<(), DomainError> {
+        let wake_at = Instant::now() + delay;
+        loop {
+            self.check_interrupted(transfer_id)?;
+            let now = Instant::now();
+            if now >= wake_at {
+                return Ok(());
+            }
+            thread::sleep(INTERRUPT_POLL_INTERVAL.min(wake_at - now));
+        }
+    }
+
+    fn resolve_peer(&self, name: &str) -> Option<Peer> {
+        self.discovery
+            .discover_peers()
+            .ok()?
+            .into_iter()
+            .find(|candidate| candidate.name == name)
+    }
+
+    /// Sends a pipe or device in a single attempt that hashes it on the way
+    /// and ends with its size and checksum.
+    fn run_stream(
+        &self,
+        transfer_id: &str,
+        peer: &Peer,
+        file_path: &str,
+    ) -> Result<(), DomainError> {
+        self.check_interrupted(transfer_id)?;
+        let source = self.storage.open_stream(file_path)?;
+        let name = Path::new(file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "unknown.bin".to_string());
+        self.tracker.update(transfer_id, |status| {
+            status.state = TransferState::InProgress;
+            status.attempts.push(TransferAttempt {
+                number: 1,
+                started_at: unix_now(),
+                address: peer.address,
+                resume_offset: 0,
+                error: None,
+            });
+        });
+
+        let result = self.network.connect(peer).and_then(|mut connection| {
+            self.metrics.connection_opened(Direction::Outbound);
+            let result = self.stream_over(connection.as_mut(), transfer_id, &name, source);
+            self.metrics.connection_closed(Direction::Outbound);
+            result
+        });
+        if let Err(error) = &result {
+            self.check_interrupted(transfer_id)?;
+            self.tracker.update(transfer_id, |status| {
+                if let Some(attempt) = status.attempts.last_mut() {
+                    attempt.error = Some(format!("{:?}", error));
+                }
+            });
+        }
+        result
+    }
+
+    fn stream_over(
+        &self,
+        connection: &mut dyn NetworkConnection,
+        transfer_id: &str,
+        name: &str,
+        mut source: Box<dyn Read + Send>,
+    ) -> Result<(), DomainError> {
+        let request_msg = LanShareMessage::StreamRequest(StreamRequestPayload {
+            name: name.to_string(),
+        });
+        let mut buffer = Vec::new();
+        encode_message(&mut buffer, &request_msg).map_err(|_e| DomainError::ProtocolError)?;
+        connection.send(&buffer)?;
+
+        match decode_message(&mut ConnectionReader(connection)) {
+            Ok(LanShareMessage::TransferResponse(response)) if response.accepted => {}
+            Ok(LanShareMessage::TransferResponse(_)) => return Err(DomainError::TransferRejected),
+            Ok(_) => return Err(DomainError::ProtocolError),
+            Err(e) => return Err(DomainError::ConnectionFailed(format!("{:?}", e))),
+        }
+        info!("Stream accepted");
+
+        let mut hasher = Sha256::new();
+        let mut offset = 0;
+        let mut data = vec![0; self.chunk_size];
+        loop {
+            // A cancel is only noticed once the source has more to read.
+            self.check_interrupted(transfer_id)?;
+            let read = match source.read(&mut data) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            hasher.update(&data[..read]);
+            self.rate_limiter.acquire(read);
+            let chunk_msg = LanShareMessage::DataChunk(DataChunkPayload {
+                offset,
+                data: data[..read].to_vec(),
+            });
+
+            buffer.clear();
+            encode_message(&mut buffer, &chunk_msg).map_err(|_e| DomainError::ProtocolError)?;
+            let started = Instant::now();
+            connection.send(&buffer)?;
+            self.metrics
+                .record_chunk_latency(Direction::Outbound, started.elapsed());
+            self.metrics.record_bytes(Direction::Outbound, read as u64);
+            offset += read as u64;
+
+            self.tracker
+                .update(transfer_id, |status| status.bytes_sent = offset);
+        }
+
+        let sha256: [u8; 32] = hasher.finalize().into();
+        Span::current().record("sha256", to_hex(&sha256).as_str());
+        let end_msg = LanShareMessage::StreamEnd(StreamEndPayload {
+            size: offset,
+            sha256,
+        });
+        buffer.clear();
+        encode_message(&mut buffer, &end_msg).map_err(|_e| DomainError::ProtocolError)?;
+        connection.send(&buffer)?;
+        self.tracker
+            .update(transfer_id, |status| status.total_bytes = offset);
+        info!(size = offset, "Stream ended");
+        await_completion(connection)
+    }
+
+    fn attempt(
+        &self,
+        transfer_id: &str,
+        peer: &Peer,
+        file_path: &str,
+        manifest: &FileManifest,
+    ) -> Result<(), DomainError> {
         let mut connection = self.network.connect(peer)?;
+        self.metrics.connection_opened(Direction::Outbound);
+        let result = self.send_over(connection.as_mut(), transfer_id, file_path, manifest);
+        self.metrics.connection_closed(Direction::Outbound);
+        result
+    }
 
-        let request_payload = TransferRequestPayload {
+    fn send_over(
+        &self,
+        connection: &mut dyn NetworkConnection,
+        transfer_id: &str,
+        file_path: &str,
+        manifest: &FileManifest,
+    ) -> Result<(), DomainError> {
+        let request_msg = LanShareMessage::TransferRequest(TransferRequestPayload {
             name: manifest.name.clone(),
             size: manifest.size,
             sha256: manifest.sha256,
-        };
-
-        let request_msg = LanShareMessage::TransferRequest(request_payload);
+        });
 
         let mut request_buffer = Vec::new();
         encode_message(&mut request_buffer, &request_msg)
             .map_err(|_e| DomainError::ProtocolError)?;
         connection.send(&request_buffer)?;
 
-        let chunk_size: usize = 8192;
-        let mut offset: u64 = 0;
-        let mut chunk_buffer = Vec::with_capacity(chunk_size + 128);
+        let mut offset = match decode_message(&mut ConnectionReader(connection)) {
+            Ok(LanShareMessage::TransferResponse(response)) if response.accepted => {
+                response.resume_offset
+            }
+            Ok(LanShareMessage::TransferResponse(_)) => return Err(DomainError::TransferRejected),
+            Ok(_) => return Err(DomainError::ProtocolError),
+            Err(e) => return Err(DomainError::ConnectionFailed(format!("{:?}", e))),
+        };
+
+        info!(
+            size = manifest.size,
+            resume_offset = offset,
+            "Transfer accepted"
+        );
+        self.tracker.update(transfer_id, |status| {
+            status.bytes_sent = offset;
+            if let Some(attempt) = status.attempts.last_mut() {
+                attempt.resume_offset = offset;
+            }
+        });
+
+        let mut chunk_buffer = Vec::with_capacity(self.chunk_size + 128);
         while offset < manifest.size {
+            self.check_interrupted(transfer_id)?;
             chunk_buffer.clear();
-            let block = self.storage.read_block(file_path, offset, chunk_size)?;
-            let chunk_payload = DataChunkPayload {
+            let block = self
+                .storage
+                .read_block(file_path, offset, self.chunk_size)?;
+            if block.data.is_empty() {
+                return Err(DomainError::IoError(format!(
+                    "Unexpected end of file at offset {}",
+                    offset
+                )));
+            }
+            let block_len = block.data.len() as u64;
+            self.rate_limiter.acquire(block.data.len());
+            let chunk_msg = LanShareMessage::DataChunk(DataChunkPayload {
                 offset: block.offset,
                 data: block.data,
-            };
-            let chunk_msg = LanShareMessage::DataChunk(chunk_payload);
+            });
 
             encode_message(&mut chunk_buffer, &chunk_msg)
                 .map_err(|_e| DomainError::ProtocolError)?;
-            connection.send(&request_buffer)?;
-            offset += chunk_size as u64;
+            let started = Instant::now();
+            connection.send(&chunk_buffer)?;
+            self.metrics
+                .record_chunk_latency(Direction::Outbound, started.elapsed());
+            self.metrics.record_bytes(Direction::Outbound, block_len);
+            offset += block_len;
+
+            self.tracker
+                .update(transfer_id, |status| status.bytes_sent = offset);
         }
 
-        Ok(())
+        await_completion(connection)
+    }
+}
+
+/// Waits for the receiver to confirm it verified and stored the file; a
+/// written chunk has only reached the local send buffer.
+fn await_completion(connection: &mut dyn NetworkConnection) -> Result<(), DomainError> {
+    match decode_message(&mut ConnectionReader(connection)) {
+        Ok(LanShareMessage::TransferComplete) => Ok(()),
+        Ok(LanShareMessage::Error(err)) if err.message == INTEGRITY_FAILED => {
+            Err(DomainError::IntegrityError)
+        }
+        Ok(LanShareMessage::Error(err)) => Err(DomainError::IoError(err.message)),
+        Ok(_) => Err(DomainError::ProtocolError),
+        Err(e) => Err(DomainError::ConnectionFailed(format!(
+            "No confirmation from the receiver: {:?}",
+            e
+        ))),
+    }
+}
+
+fn to_hex(sha256: &[u8; 32]) -> String {
+    sha256.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+struct ConnectionReader<'a>(&'a mut dyn NetworkConnection);
+
+impl Read for ConnectionReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0
+            .receive(buf)
+            .map_err(|e| io::Error::other(format!("{:?}", e)))
     }
 }
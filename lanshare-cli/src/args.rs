@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
+
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  Success
+  1  The daemon could not carry out the command
+  2  Invalid usage or arguments, including ambiguous peer names
+  3  Invalid configuration
+  4  The daemon is not running or could not be reached
+  5  No such peer, file, transfer or offer
+  6  This user may not control the daemon";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "lanshare-cli",
+    version,
+    about = "Control the LanShare daemon",
+    after_long_help = EXIT_STATUS_HELP,
+    arg_required_else_help = true
+)]
+pub struct Cli {
+    /// Print results as JSON; commands without a result print nothing
+    #[arg(long, global = true, conflicts_with = "quiet")]
+    pub json: bool,
+    /// Print only IDs, one per line
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Send a file, queueing it until the peer comes online if it is offline
+    Send {
+        /// File or named pipe to send; `-` streams standard input until it ends
+        file: PathBuf,
+        /// Alias, display name, hostname, device ID prefix or ip:port
+        peer: String,
+        /// How long to keep the file queued while the peer is offline
+        #[arg(long, value_name = "SECONDS")]
+        expires_in: Option<u64>,
+        /// Show progress until the transfer finishes
+        #[arg(short, long)]
+        watch: bool,
+        /// File name the receiver stores standard input under
+        #[arg(long, default_value = "stdin", value_parser = file_name)]
+        name: String,
+    },
+    /// Show running transfers, queued files and offers waiting for an answer
+    Status {
+        /// Keep showing live progress of active transfers
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Cancel a transfer or drop a queued file
+    Cancel {
+        /// Transfer or outbox entry ID
+        id: String,
+    },
+    /// Pause a running transfer
+    Pause { transfer_id: String },
+    /// Resume a paused transfer where the receiver left off
+    Resume { transfer_id: String },
+    /// List transfers finished since the daemon started
+    History,
+    /// Accept an incoming transfer (when receive.auto_accept is off)
+    Accept { offer_id: String },
+    /// Reject an incoming transfer
+    Reject { offer_id: String },
+    /// List peers on the network or edit the peer book
+    Peers {
+        #[command(subcommand)]
+        action: Option<PeersCommand>,
+    },
+    /// Same as `peers list`
+    #[command(hide = true)]
+    List,
+    /// Show or reload the daemon's configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Start or stop the daemon
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonCommand,
+    },
+    /// Same as `daemon stop`
+    #[command(hide = true)]
+    Shutdown,
+    /// Print the daemon's metrics in Prometheus format
+    Metrics,
+    /// Print the OpenRPC description of the IPC protocol
+    Schema,
+    /// Show the web drop address, PIN and shared files
+    Webdrop {
+        #[command(subcommand)]
+        action: Option<WebDropCommand>,
+    },
+    /// Browse peers, transfers and offers in an interactive terminal UI
+    Tui,
+    /// Print a shell completion script
+    Completions { shell: Shell },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PeersCommand {
+    /// List peers on the network (the default)
+    List,
+    /// Add a static peer to the peer book
+    Add {
+        #[arg(value_name = "IP:PORT")]
+        address: String,
+        #[arg(long)]
+        alias: Option<String>,
+        /// May be given several times
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+    /// Remove a peer book entry by address or alias
+    Remove { peer: String },
+    /// Set or clear the alias of a peer book entry
+    Alias { peer: String, alias: Option<String> },
+    /// Replace the tags of a peer book entry
+    Tag { peer: String, tags: Vec<String> },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Show the daemon's effective configuration
+    Show,
+    /// Make the daemon re-read its configuration
+    Reload,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DaemonCommand {
+    /// Start lanshare-rs in the background and wait until it answers
+    Start,
+    /// Stop the daemon, checkpointing running transfers
+    Stop,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WebDropCommand {
+    /// Offer a file for download on the web drop page
+    Share { file: PathBuf },
+    /// Stop offering a shared file
+    Unshare { share_id: String },
+}
+
+fn file_name(value: &str) -> Result<String, String> {
+    if value.is_empty() || value == "." || value == ".." || value.contains('/') {
+        return Err("must be a plain file name".to_string());
+    }
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn command_line_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+}
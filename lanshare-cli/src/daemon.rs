@@ -0,0 +1,71 @@
+use std::{
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use lanshare_client::{Client, ClientError};
+
+use crate::CliError;
+
+const DAEMON_BINARY: &str = "lanshare-rs";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Starts the daemon detached from this terminal and waits until its IPC
+/// socket answers. Under systemd, `systemctl --user start lanshare` is the
+/// better choice; this is for machines without a unit.
+pub fn start(socket_path: &Path) -> Result<(), CliError> {
+    if Client::connect(socket_path).is_ok() {
+        println!("Daemon is already running.");
+        return Ok(());
+    }
+
+    let binary = daemon_binary();
+    let mut child = Command::new(&binary)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Its own process group, so Ctrl-C in this terminal does not reach it.
+        .process_group(0)
+        .spawn()
+        .map_err(|e| CliError::Start(format!("{}: {}", binary.display(), e)))?;
+
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        match Client::connect(socket_path) {
+            Ok(_) => {
+                println!("Daemon started (pid {}).", child.id());
+                return Ok(());
+            }
+            Err(ClientError::Connect(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(CliError::Start(format!(
+                "{} exited with {}; run it in a terminal to see why",
+                binary.display(),
+                status
+            )));
+        }
+        if Instant::now() >= deadline {
+            return Err(CliError::Start(format!(
+                "no answer on {} after {} seconds",
+                socket_path.display(),
+                STARTUP_TIMEOUT.as_secs()
+            )));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Prefers the daemon installed next to this binary over the one on `PATH`.
+fn daemon_binary() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(DAEMON_BINARY)))
+        .filter(|candidate| candidate.is_file())
+        .unwrap_or_else(|| PathBuf::from(DAEMON_BINARY))
+}
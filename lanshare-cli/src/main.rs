@@ -1,159 +1,390 @@
-use std::io::{BufRead, BufReader, Write};
-use std::os::unix::net::UnixStream;
-use std::process::exit;
+mod args;
+mod daemon;
+mod output;
+mod stream;
+mod tui;
+mod watch;
 
-const SOCKET_PATH: &str = "/tmp/lanshare.sock";
+use std::{io, path::Path, process::exit};
+
+use clap::{CommandFactory, Parser};
+use lanshare_app::peer_resolver::resolve_peer;
+use lanshare_client::{AddPeerParams, Client, ClientError, SendFileParams, SendFileResponse};
+use lanshare_config::{Config, ConfigError, Overrides};
+use lanshare_domain::{
+    error::DomainError,
+    models::{Peer, TransferState},
+};
+
+use crate::{
+    args::{Cli, Command, ConfigCommand, DaemonCommand, PeersCommand, WebDropCommand},
+    output::{
+        OutputMode, metric_samples, peer_ids, print_history, print_json, print_peers,
+        print_reload_report, print_send_response, print_status, print_web_drop, send_response_ids,
+        status_ids, transfer_ids,
+    },
+};
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_CONFIG: i32 = 3;
+const EXIT_UNREACHABLE: i32 = 4;
+const EXIT_NOT_FOUND: i32 = 5;
+const EXIT_PERMISSION_DENIED: i32 = 6;
 
 #[derive(Debug)]
-enum Command {
-    List,
-    Send { file_path: String, peer: String },
+enum CliError {
+    AmbiguousPeer(String, Vec<String>),
+    Config(ConfigError),
+    Daemon(ClientError),
+    Start(String),
+    Stdin(io::Error),
+    Terminal(io::Error),
+    /// A watched transfer ended without completing.
+    Transfer(TransferState, Option<String>),
 }
 
-impl Command {
-    fn from_args(args: &[String]) -> Result<Self, CliError> {
-        let verb = args.get(1).ok_or(CliError::NoCommand)?;
-
-        match verb.as_str() {
-            "list" => Ok(Command::List),
-            "send" => {
-                let file_path = args
-                    .get(2)
-                    .ok_or(CliError::MissingArgument("file_path"))?
-                    .clone();
-                let peer = args
-                    .get(3)
-                    .ok_or(CliError::MissingArgument("peer"))?
-                    .clone();
-                Ok(Command::Send { file_path, peer })
-            }
-            unknown => Err(CliError::UnknownCommand(unknown.to_string())),
+impl CliError {
+    /// Exit status, as listed in `lanshare-cli --help`.
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::AmbiguousPeer(..) => EXIT_USAGE,
+            CliError::Config(_) => EXIT_CONFIG,
+            CliError::Start(_)
+            | CliError::Stdin(_)
+            | CliError::Terminal(_)
+            | CliError::Transfer(..) => EXIT_FAILURE,
+            CliError::Daemon(ClientError::Connect(_)) => EXIT_UNREACHABLE,
+            CliError::Daemon(e) => match e.kind() {
+                Some("INVALID_ARGUMENT" | "AMBIGUOUS_PEER") => EXIT_USAGE,
+                Some("INVALID_CONFIG") => EXIT_CONFIG,
+                Some("PEER_NOT_FOUND" | "FILE_NOT_FOUND" | "TRANSFER_NOT_FOUND") => EXIT_NOT_FOUND,
+                Some("PERMISSION_DENIED") => EXIT_PERMISSION_DENIED,
+                _ => EXIT_FAILURE,
+            },
         }
     }
 
-    fn to_request(&self) -> serde_json::Value {
+    /// Stable name of the error for `--json` output.
+    fn code(&self) -> String {
         match self {
-            Command::List => serde_json::json!({
-                "command": "list_peers",
-                "id": 1
-            }),
-            Command::Send { file_path, peer } => serde_json::json!({
-                "command": "send_file",
-                "id": 2,
-                "path": file_path,
-                "peer": peer
-            }),
+            CliError::AmbiguousPeer(..) => "AMBIGUOUS_PEER".to_string(),
+            CliError::Config(_) => "INVALID_CONFIG".to_string(),
+            CliError::Start(_) => "DAEMON_START_FAILED".to_string(),
+            CliError::Stdin(_) => "STDIN_ERROR".to_string(),
+            CliError::Terminal(_) => "TERMINAL_ERROR".to_string(),
+            CliError::Transfer(state, _) => format!("TRANSFER_{}", state.as_str().to_uppercase()),
+            CliError::Daemon(ClientError::Connect(_)) => "DAEMON_UNREACHABLE".to_string(),
+            CliError::Daemon(e) => e.kind().unwrap_or("DAEMON_ERROR").to_string(),
         }
     }
 }
 
-#[derive(Debug)]
-enum CliError {
-    NoCommand,
-    UnknownCommand(String),
-    MissingArgument(&'static str),
-    DaemonConnect(std::io::Error),
-    DaemonWrite(std::io::Error),
-    DaemonRead(std::io::Error),
-    DaemonHungUp,
+impl From<ClientError> for CliError {
+    fn from(e: ClientError) -> Self {
+        CliError::Daemon(e)
+    }
 }
 
 impl std::fmt::Display for CliError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CliError::NoCommand => write!(f, "No command provided."),
-            CliError::UnknownCommand(cmd) => write!(f, "Unknown command: '{}'", cmd),
-            CliError::MissingArgument(arg) => write!(f, "Missing required argument: <{}>", arg),
-            CliError::DaemonConnect(e) => write!(
-                f,
-                "Could not connect to LanShare daemon. Is it running?\n  ({})",
-                e
-            ),
-            CliError::DaemonWrite(e) => write!(f, "Failed to send request to daemon: {}", e),
-            CliError::DaemonRead(e) => write!(f, "Failed to read response from daemon: {}", e),
-            CliError::DaemonHungUp => write!(f, "Daemon closed the connection unexpectedly."),
+            CliError::AmbiguousPeer(query, candidates) => {
+                match candidates.len() {
+                    1 => write!(f, "'{}' names no peer exactly. Did you mean:", query)?,
+                    _ => write!(f, "'{}' matches several peers:", query)?,
+                }
+                for candidate in candidates {
+                    write!(f, "\n  {}", candidate)?;
+                }
+                write!(
+                    f,
+                    "\nUse an alias, device ID prefix or ip:port to pick one."
+                )
+            }
+            CliError::Config(e) => write!(f, "Configuration error: {}", e),
+            CliError::Daemon(e) => write!(f, "{}", e),
+            CliError::Start(message) => write!(f, "Could not start the daemon: {}", message),
+            CliError::Stdin(e) => write!(f, "Cannot send standard input: {}", e),
+            CliError::Terminal(e) => write!(f, "Terminal error: {}", e),
+            CliError::Transfer(state, error) => {
+                write!(f, "Transfer {}", state.as_str())?;
+                match error {
+                    Some(error) => write!(f, ": {}", error),
+                    None => Ok(()),
+                }
+            }
         }
     }
 }
 
-struct DaemonClient {
-    stream: UnixStream,
-}
+/// Maps the user's peer reference onto an identifier the daemon resolves
+/// unambiguously. Unknown names are passed through so the daemon can queue
+/// the file until such a peer shows up.
+fn resolve_send_target(client: &mut Client, query: &str) -> Result<String, CliError> {
+    let peers = client.list_peers()?;
 
-impl DaemonClient {
-    fn connect() -> Result<Self, CliError> {
-        let stream = UnixStream::connect(SOCKET_PATH).map_err(CliError::DaemonConnect)?;
-        Ok(Self { stream })
-    }
-
-    fn send_request(&mut self, payload: &serde_json::Value) -> Result<(), CliError> {
-        let mut line = payload.to_string();
-        line.push('\n');
-        self.stream
-            .write_all(line.as_bytes())
-            .map_err(CliError::DaemonWrite)
-    }
-
-    fn read_response(&mut self) -> Result<String, CliError> {
-        let mut reader = BufReader::new(&self.stream);
-        let mut buf = String::new();
-        match reader.read_line(&mut buf) {
-            Ok(0) => Err(CliError::DaemonHungUp),
-            Ok(_) => Ok(buf),
-            Err(e) => Err(CliError::DaemonRead(e)),
+    match resolve_peer(query, &peers) {
+        Ok(peer) => Ok(send_target(&peer)),
+        Err(DomainError::AmbiguousPeer(candidates)) => {
+            Err(CliError::AmbiguousPeer(query.to_string(), candidates))
         }
+        Err(_) => Ok(query.to_string()),
     }
+}
 
-    fn round_trip(&mut self, payload: &serde_json::Value) -> Result<String, CliError> {
-        self.send_request(payload)?;
-        self.read_response()
+/// The device ID survives address changes; peers without one go by address.
+fn send_target(peer: &Peer) -> String {
+    if peer.metadata.device_id.is_empty() {
+        peer.address.to_string()
+    } else {
+        peer.metadata.device_id.clone()
     }
 }
 
-fn print_response(raw: &str) {
-    match serde_json::from_str::<serde_json::Value>(raw) {
-        Ok(parsed) => println!("{}", serde_json::to_string_pretty(&parsed).unwrap()),
-        Err(_) => println!("Response: {}", raw.trim()),
+/// Prints the daemon's effective configuration, or the one this machine's
+/// config file and environment would produce when the daemon is not running.
+fn show_config(config: &Config, mode: OutputMode) -> Result<(), CliError> {
+    let (config, running) = match Client::connect(&config.daemon.socket_path) {
+        Ok(mut client) => (client.get_config()?, true),
+        Err(ClientError::Connect(_)) => (config.clone(), false),
+        Err(e) => return Err(e.into()),
+    };
+    if mode == OutputMode::Json {
+        print_json(&config);
+        return Ok(());
+    }
+    if !running {
+        println!("# Daemon is not running; configuration from file and environment:");
     }
+    print!("{}", config.to_toml());
+    Ok(())
 }
 
-fn print_usage() {
-    eprintln!("Usage: lanshare-cli <command> [options]");
-    eprintln!();
-    eprintln!("Commands:");
-    eprintln!("  list                         List peers on the network");
-    eprintln!("  send <file_path> <peer>      Send a file to the specified peer");
+/// Paths are sent to the daemon, which may run in another directory.
+fn absolute(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
 }
 
-fn print_error(err: &CliError) {
-    eprintln!("Error: {}", err);
+fn print_error(err: &CliError, mode: OutputMode) {
+    if mode == OutputMode::Json {
+        let error = serde_json::json!({
+            "error": { "code": err.code(), "message": err.to_string() }
+        });
+        eprintln!("{}", error);
+    } else {
+        eprintln!("Error: {}", err);
+    }
 }
 
-fn run() -> Result<(), CliError> {
-    let args: Vec<String> = std::env::args().collect();
-
-    let command = Command::from_args(&args)?;
-    let request = command.to_request();
+fn run(cli: Cli, mode: OutputMode) -> Result<(), CliError> {
+    let command = match cli.command {
+        Command::Completions { shell } => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "lanshare-cli",
+                &mut io::stdout(),
+            );
+            return Ok(());
+        }
+        command => command,
+    };
+    let config = Config::load(&Overrides::default()).map_err(CliError::Config)?;
+    let socket_path = config.daemon.socket_path.as_path();
 
-    let mut client = DaemonClient::connect()?;
-    let response = client.round_trip(&request)?;
+    match command {
+        Command::Config {
+            action: ConfigCommand::Show,
+        } => return show_config(&config, mode),
+        Command::Daemon {
+            action: DaemonCommand::Start,
+        } => return daemon::start(socket_path),
+        Command::Tui => return tui::run(socket_path),
+        _ => {}
+    }
+    let mut client = Client::connect(socket_path)?;
 
-    print_response(&response);
+    match command {
+        Command::Send {
+            file,
+            peer,
+            expires_in,
+            watch,
+            name,
+        } => {
+            let target = resolve_send_target(&mut client, &peer)?;
+            // Standard input reaches the daemon through a named pipe, which
+            // stays open until the transfer has ended.
+            let stdin = if file.as_os_str() == "-" {
+                Some(stream::StdinPipe::create(socket_path, &name).map_err(CliError::Stdin)?)
+            } else {
+                None
+            };
+            let params = SendFileParams {
+                path: match &stdin {
+                    Some((pipe, _)) => absolute(pipe.path()),
+                    None => absolute(&file),
+                },
+                peer: target,
+                expires_in,
+            };
+            let response = client.send_file(&params)?;
+            let watched = match &response {
+                SendFileResponse::Started { transfer_id } if watch || stdin.is_some() => {
+                    Some(transfer_id.clone())
+                }
+                _ => None,
+            };
+            // With --json --watch the progress lines are the output.
+            if !(watch && watched.is_some() && mode == OutputMode::Json) {
+                mode.print(&response, send_response_ids, |response| {
+                    print_send_response(response, &params.path, &peer)
+                });
+            }
+            if let Some(transfer_id) = watched {
+                let _pipe = stdin.map(|(pipe, writer)| {
+                    stream::pump(writer);
+                    pipe
+                });
+                let mode = if watch { mode } else { OutputMode::Quiet };
+                return watch::watch(socket_path, mode, Some(&transfer_id));
+            }
+        }
+        Command::Status { watch: true } => return watch::watch(socket_path, mode, None),
+        Command::Status { watch: false } => {
+            mode.print(&client.get_status()?, status_ids, print_status)
+        }
+        Command::Cancel { id } => {
+            client.cancel_transfer(&id)?;
+            mode.done(format!("Cancelled {}", id));
+        }
+        Command::Pause { transfer_id } => {
+            client.pause_transfer(&transfer_id)?;
+            mode.done(format!("Pausing {}", transfer_id));
+        }
+        Command::Resume { transfer_id } => {
+            client.resume_transfer(&transfer_id)?;
+            mode.done(format!("Resumed {}", transfer_id));
+        }
+        Command::History => mode.print(
+            &client.get_history()?,
+            |t| transfer_ids(t),
+            |t| print_history(t),
+        ),
+        Command::Accept { offer_id } => {
+            client.accept_offer(&offer_id)?;
+            mode.done(format!("Accepted {}", offer_id));
+        }
+        Command::Reject { offer_id } => {
+            client.reject_offer(&offer_id)?;
+            mode.done(format!("Rejected {}", offer_id));
+        }
+        Command::List
+        | Command::Peers {
+            action: None | Some(PeersCommand::List),
+        } => mode.print(&client.list_peers()?, |p| peer_ids(p), |p| print_peers(p)),
+        Command::Peers {
+            action: Some(action),
+        } => run_peers(&mut client, action, mode)?,
+        Command::Config {
+            action: ConfigCommand::Reload,
+        } => mode.print(
+            &client.reload_config()?,
+            |_| Vec::new(),
+            print_reload_report,
+        ),
+        Command::Daemon {
+            action: DaemonCommand::Stop,
+        }
+        | Command::Shutdown => {
+            client.shutdown()?;
+            mode.done("Daemon is shutting down.");
+        }
+        Command::Metrics => {
+            let text = client.metrics()?;
+            mode.print(
+                &metric_samples(&text),
+                |_| Vec::new(),
+                |_| print!("{}", text),
+            );
+        }
+        Command::Schema => print_json(&client.discover()?),
+        Command::Webdrop { action: None } => {
+            let status = client.web_drop_status()?;
+            let ids = |status: &lanshare_client::WebDropStatus| {
+                status
+                    .shares
+                    .iter()
+                    .map(|shared| shared.id.clone())
+                    .collect()
+            };
+            mode.print(&status, ids, print_web_drop)
+        }
+        Command::Webdrop {
+            action: Some(WebDropCommand::Share { file }),
+        } => {
+            let shared = client.share_file(&absolute(&file))?;
+            mode.print(
+                &shared,
+                |shared| vec![shared.id.clone()],
+                |shared| println!("Shared {} as {}", shared.name, shared.id),
+            );
+        }
+        Command::Webdrop {
+            action: Some(WebDropCommand::Unshare { share_id }),
+        } => {
+            client.unshare_file(&share_id)?;
+            mode.done(format!("Stopped sharing {}", share_id));
+        }
+        Command::Completions { .. }
+        | Command::Tui
+        | Command::Config {
+            action: ConfigCommand::Show,
+        }
+        | Command::Daemon {
+            action: DaemonCommand::Start,
+        } => unreachable!("handled without a daemon connection"),
+    }
     Ok(())
 }
 
-fn main() {
-    if let Err(e) = run() {
-        print_error(&e);
-
-        if matches!(
-            e,
-            CliError::NoCommand | CliError::UnknownCommand(_) | CliError::MissingArgument(_)
-        ) {
-            eprintln!();
-            print_usage();
+fn run_peers(client: &mut Client, action: PeersCommand, mode: OutputMode) -> Result<(), CliError> {
+    let peer = match action {
+        PeersCommand::List => {
+            mode.print(&client.list_peers()?, |p| peer_ids(p), |p| print_peers(p));
+            return Ok(());
+        }
+        PeersCommand::Remove { peer } => {
+            client.remove_peer(&peer)?;
+            mode.done(format!("Removed {}", peer));
+            return Ok(());
         }
+        PeersCommand::Add {
+            address,
+            alias,
+            tags,
+        } => client.add_peer(&AddPeerParams {
+            address,
+            alias,
+            tags,
+        })?,
+        PeersCommand::Alias { peer, alias } => client.alias_peer(&peer, alias.as_deref())?,
+        PeersCommand::Tag { peer, tags } => client.tag_peer(&peer, tags)?,
+    };
+    let peers = [peer];
+    mode.print(&peers, |p| peer_ids(p), |p| print_peers(p));
+    Ok(())
+}
 
-        exit(1);
+fn main() {
+    let cli = Cli::parse();
+    let mode = OutputMode::from_flags(cli.json, cli.quiet);
+    if let Err(e) = run(cli, mode) {
+        print_error(&e, mode);
+        exit(e.exit_code());
     }
 }
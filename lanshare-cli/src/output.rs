@@ -0,0 +1,443 @@
+use std::{collections::BTreeMap, path::Path};
+
+use lanshare_app::time::unix_now;
+use lanshare_client::{SendFileResponse, StatusResponse, WebDropStatus};
+use lanshare_config::ReloadReport;
+use lanshare_domain::models::{IncomingOffer, Peer, TransferState, TransferStatus};
+use serde::Serialize;
+
+/// How command results are printed. Errors always go to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    Human,
+    /// The daemon's result as JSON; commands without one print nothing.
+    Json,
+    /// Only the IDs a script would pass to the next command.
+    Quiet,
+}
+
+impl OutputMode {
+    pub fn from_flags(json: bool, quiet: bool) -> Self {
+        if json {
+            OutputMode::Json
+        } else if quiet {
+            OutputMode::Quiet
+        } else {
+            OutputMode::Human
+        }
+    }
+
+    /// Prints `value` as JSON, the IDs from `ids`, or runs `human`.
+    pub fn print<T: Serialize>(
+        self,
+        value: &T,
+        ids: impl FnOnce(&T) -> Vec<String>,
+        human: impl FnOnce(&T),
+    ) {
+        match self {
+            OutputMode::Human => human(value),
+            OutputMode::Json => print_json(value),
+            OutputMode::Quiet => {
+                for id in ids(value) {
+                    println!("{}", id);
+                }
+            }
+        }
+    }
+
+    /// A confirmation for commands without a result.
+    pub fn done(self, message: impl std::fmt::Display) {
+        if self == OutputMode::Human {
+            println!("{}", message);
+        }
+    }
+}
+
+pub fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+/// Left-aligned columns sized to their widest cell.
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Self {
+        Self {
+            headers: headers.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    pub fn print(&self) {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.len()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let headers: Vec<String> = self.headers.iter().map(|h| h.to_string()).collect();
+        for row in std::iter::once(&headers).chain(&self.rows) {
+            let mut line = String::new();
+            for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+                if i + 1 == row.len() {
+                    line.push_str(cell);
+                } else {
+                    let padding = width - cell.chars().count();
+                    line.push_str(cell);
+                    line.push_str(&" ".repeat(padding + 2));
+                }
+            }
+            println!("{}", line.trim_end());
+        }
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+pub fn format_rate(bytes_per_sec: f64) -> String {
+    format!("{}/s", format_size(bytes_per_sec as u64))
+}
+
+pub fn format_duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+pub fn percent(transfer: &TransferStatus) -> u64 {
+    match transfer.total_bytes {
+        0 => 0,
+        total => transfer.bytes_sent.min(total) * 100 / total,
+    }
+}
+
+/// Streams only learn their size once they end.
+pub fn is_streaming(transfer: &TransferStatus) -> bool {
+    transfer.streamed && !transfer.state.is_finished()
+}
+
+pub fn transfer_size(transfer: &TransferStatus) -> u64 {
+    if transfer.streamed {
+        transfer.total_bytes.max(transfer.bytes_sent)
+    } else {
+        transfer.total_bytes
+    }
+}
+
+pub fn offer_size(offer: &IncomingOffer) -> String {
+    if offer.streamed {
+        "stream".to_string()
+    } else {
+        format_size(offer.size)
+    }
+}
+
+pub fn progress_bar(percent: u64, width: usize) -> String {
+    let filled = (percent.min(100) as usize * width) / 100;
+    format!("{}{}", "#".repeat(filled), "-".repeat(width - filled))
+}
+
+pub fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Average rate of the current attempt, from what the daemon reports.
+pub fn attempt_rate(transfer: &TransferStatus) -> Option<f64> {
+    if transfer.state != TransferState::InProgress {
+        return None;
+    }
+    let attempt = transfer.attempts.last()?;
+    let elapsed = unix_now().checked_sub(attempt.started_at)?;
+    if elapsed == 0 {
+        return None;
+    }
+    let sent = transfer.bytes_sent.saturating_sub(attempt.resume_offset);
+    Some(sent as f64 / elapsed as f64)
+}
+
+fn or_dash(value: &str) -> &str {
+    if value.is_empty() { "-" } else { value }
+}
+
+pub fn print_peers(peers: &[Peer]) {
+    if peers.is_empty() {
+        println!("No peers discovered.");
+        return;
+    }
+    let mut table = Table::new(&["NAME", "ADDRESS", "DEVICE", "PLATFORM", "STATUS", "TAGS"]);
+    for peer in peers {
+        let device: String = peer.metadata.device_id.chars().take(12).collect();
+        table.row(vec![
+            peer.display_name().to_string(),
+            peer.address.to_string(),
+            or_dash(&device).to_string(),
+            or_dash(&peer.metadata.platform).to_string(),
+            peer.metadata.status.as_str().to_string(),
+            peer.tags.join(","),
+        ]);
+    }
+    table.print();
+}
+
+pub fn peer_ids(peers: &[Peer]) -> Vec<String> {
+    peers.iter().map(|peer| peer.address.to_string()).collect()
+}
+
+pub fn print_send_response(response: &SendFileResponse, file: &str, peer: &str) {
+    match response {
+        SendFileResponse::Started { transfer_id } => {
+            println!("Sending {} to {} ({})", file_name(file), peer, transfer_id)
+        }
+        SendFileResponse::Queued {
+            outbox_id,
+            expires_at,
+        } => println!(
+            "{} is offline; {} waits for it for {} ({})",
+            peer,
+            file_name(file),
+            format_duration(expires_at.saturating_sub(unix_now())),
+            outbox_id
+        ),
+    }
+}
+
+pub fn send_response_ids(response: &SendFileResponse) -> Vec<String> {
+    match response {
+        SendFileResponse::Started { transfer_id } => vec![transfer_id.clone()],
+        SendFileResponse::Queued { outbox_id, .. } => vec![outbox_id.clone()],
+    }
+}
+
+pub fn print_status(status: &StatusResponse) {
+    if status.transfers.is_empty() && status.outbox.is_empty() && status.offers.is_empty() {
+        println!("Nothing is being transferred.");
+        return;
+    }
+    let now = unix_now();
+    let mut sections = Vec::new();
+
+    if !status.transfers.is_empty() {
+        let mut table = Table::new(&[
+            "TRANSFER", "FILE", "PEER", "STATE", "PROGRESS", "SIZE", "SPEED",
+        ]);
+        for transfer in &status.transfers {
+            table.row(vec![
+                transfer.transfer_id.clone(),
+                file_name(&transfer.file_path),
+                transfer.peer.clone(),
+                transfer.state.as_str().to_string(),
+                if is_streaming(transfer) {
+                    "streaming".to_string()
+                } else {
+                    format!("{}%", percent(transfer))
+                },
+                format_size(transfer_size(transfer)),
+                attempt_rate(transfer)
+                    .map(format_rate)
+                    .unwrap_or_else(|| "-".to_string()),
+            ]);
+        }
+        sections.push(table);
+    }
+    if !status.outbox.is_empty() {
+        let mut table = Table::new(&["QUEUED", "FILE", "PEER", "GIVES UP IN"]);
+        for entry in &status.outbox {
+            table.row(vec![
+                entry.id.clone(),
+                file_name(&entry.file_path),
+                entry.peer.clone(),
+                format_duration(entry.expires_at.saturating_sub(now)),
+            ]);
+        }
+        sections.push(table);
+    }
+    if !status.offers.is_empty() {
+        let mut table = Table::new(&["OFFER", "FILE", "SIZE", "FROM", "EXPIRES IN"]);
+        for offer in &status.offers {
+            let sender = match &offer.peer {
+                Some(peer) => format!("{} ({})", peer, offer.sender.ip()),
+                None => offer.sender.ip().to_string(),
+            };
+            table.row(vec![
+                offer.offer_id.clone(),
+                offer.name.clone(),
+                offer_size(offer),
+                sender,
+                format_duration(offer.expires_at.saturating_sub(now)),
+            ]);
+        }
+        sections.push(table);
+    }
+
+    for (i, table) in sections.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        table.print();
+    }
+    if !status.offers.is_empty() {
+        println!("\nAnswer offers with `lanshare-cli accept <offer>` or `reject <offer>`.");
+    }
+}
+
+pub fn status_ids(status: &StatusResponse) -> Vec<String> {
+    let transfers = status.transfers.iter().map(|t| t.transfer_id.clone());
+    let outbox = status.outbox.iter().map(|entry| entry.id.clone());
+    let offers = status.offers.iter().map(|offer| offer.offer_id.clone());
+    transfers.chain(outbox).chain(offers).collect()
+}
+
+pub fn print_history(transfers: &[TransferStatus]) {
+    if transfers.is_empty() {
+        println!("No finished transfers since the daemon started.");
+        return;
+    }
+    let now = unix_now();
+    let mut table = Table::new(&[
+        "TRANSFER", "FILE", "PEER", "STATE", "SIZE", "STARTED", "ERROR",
+    ]);
+    for transfer in transfers {
+        let started = transfer
+            .attempts
+            .first()
+            .map(|attempt| {
+                format!(
+                    "{} ago",
+                    format_duration(now.saturating_sub(attempt.started_at))
+                )
+            })
+            .unwrap_or_else(|| "-".to_string());
+        let error = match transfer.state {
+            TransferState::Completed => None,
+            _ => transfer.attempts.last().and_then(|a| a.error.clone()),
+        };
+        table.row(vec![
+            transfer.transfer_id.clone(),
+            file_name(&transfer.file_path),
+            transfer.peer.clone(),
+            transfer.state.as_str().to_string(),
+            format_size(transfer_size(transfer)),
+            started,
+            error.unwrap_or_default(),
+        ]);
+    }
+    table.print();
+}
+
+pub fn transfer_ids(transfers: &[TransferStatus]) -> Vec<String> {
+    transfers.iter().map(|t| t.transfer_id.clone()).collect()
+}
+
+pub fn print_reload_report(report: &ReloadReport) {
+    if report.applied.is_empty() && report.restart_required.is_empty() {
+        println!("Configuration reloaded, nothing changed.");
+        return;
+    }
+    if !report.applied.is_empty() {
+        println!("Applied:");
+        for key in &report.applied {
+            println!("  {}", key);
+        }
+    }
+    if !report.restart_required.is_empty() {
+        println!("Requires a daemon restart (old value still in effect):");
+        for key in &report.restart_required {
+            println!("  {}", key);
+        }
+    }
+}
+
+pub fn print_web_drop(status: &WebDropStatus) {
+    let address = if status.listen.ip().is_unspecified() {
+        format!("<this computer's address>:{}", status.listen.port())
+    } else {
+        status.listen.to_string()
+    };
+    println!("Open http://{}/ on the other device.", address);
+    println!(
+        "PIN: {} (valid for {})",
+        status.pin,
+        format_duration(status.pin_expires_at.saturating_sub(unix_now()))
+    );
+
+    if status.shares.is_empty() {
+        println!("No files shared for download.");
+        return;
+    }
+    println!();
+    let mut table = Table::new(&["SHARE", "FILE", "SIZE"]);
+    for shared in &status.shares {
+        table.row(vec![
+            shared.id.clone(),
+            shared.name.clone(),
+            format_size(shared.size),
+        ]);
+    }
+    table.print();
+}
+
+/// Samples from the Prometheus text format keyed by name and labels, for
+/// `--json`.
+pub fn metric_samples(text: &str) -> BTreeMap<String, f64> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (sample, value) = line.rsplit_once(' ')?;
+            Some((sample.to_string(), value.parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_and_durations_are_short() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(600), "10m");
+        assert_eq!(format_duration(2 * 86400), "2d");
+    }
+
+    #[test]
+    fn metric_samples_skip_comments() {
+        let text = "# HELP lanshare_peers Peers\n# TYPE lanshare_peers gauge\nlanshare_peers 3\n\
+                    lanshare_transfers_total{direction=\"outbound\"} 12\n";
+        let samples = metric_samples(text);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples["lanshare_peers"], 3.0);
+        assert_eq!(
+            samples[r#"lanshare_transfers_total{direction="outbound"}"#],
+            12.0
+        );
+    }
+}
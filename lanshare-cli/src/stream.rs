@@ -0,0 +1,66 @@
+use std::{
+    ffi::CString,
+    fs::{self, File, OpenOptions},
+    io::{self, IsTerminal},
+    os::unix::{ffi::OsStrExt, fs::DirBuilderExt},
+    path::{Path, PathBuf},
+    thread,
+};
+
+/// A named pipe the daemon reads standard input from, in a directory next
+/// to its socket that only this user can enter. Removed when dropped.
+pub struct StdinPipe {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl StdinPipe {
+    /// Creates the pipe as `name`, which the receiver stores the data under,
+    /// and opens it for writing.
+    pub fn create(socket_path: &Path, name: &str) -> io::Result<(Self, File)> {
+        if io::stdin().is_terminal() {
+            return Err(io::Error::other(
+                "standard input is a terminal; pipe the data to send into lanshare-cli",
+            ));
+        }
+        let parent = socket_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir);
+        let dir = parent.join(format!("lanshare-cli-{}", std::process::id()));
+        fs::DirBuilder::new().mode(0o700).create(&dir)?;
+        let pipe = Self {
+            path: dir.join(name),
+            dir,
+        };
+
+        let path = CString::new(pipe.path.as_os_str().as_bytes()).map_err(io::Error::other)?;
+        // SAFETY: `path` is a valid NUL-terminated string.
+        if unsafe { libc::mkfifo(path.as_ptr(), 0o600) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Opened for reading too, so neither side waits in open() for the
+        // other; the daemon sees the end of the data once this is closed.
+        let writer = OpenOptions::new().read(true).write(true).open(&pipe.path)?;
+        Ok((pipe, writer))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StdinPipe {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_dir(&self.dir);
+    }
+}
+
+/// Copies standard input into the pipe until it ends.
+pub fn pump(mut writer: File) {
+    thread::spawn(move || {
+        let _ = io::copy(&mut io::stdin().lock(), &mut writer);
+    });
+}
@@ -0,0 +1,379 @@
+use std::path::Path;
+
+use lanshare_client::{Client, ClientError, DaemonEvent, SendFileParams, SendFileResponse};
+use lanshare_domain::models::{
+    IncomingOffer, OutboxEntry, OutboxEvent, Peer, PeerEvent, TransferState, TransferStatus,
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    widgets::TableState,
+};
+
+use crate::{absolute, output::file_name, send_target, tui::picker::FilePicker};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Peers,
+    Transfers,
+    Offers,
+}
+
+impl Pane {
+    fn next(self) -> Self {
+        match self {
+            Pane::Peers => Pane::Transfers,
+            Pane::Transfers => Pane::Offers,
+            Pane::Offers => Pane::Peers,
+        }
+    }
+
+    fn previous(self) -> Self {
+        self.next().next()
+    }
+}
+
+/// One line in the footer until the next key press.
+pub enum Notice {
+    Info(String),
+    Error(String),
+}
+
+pub struct App {
+    pub peers: Vec<Peer>,
+    /// Unfinished transfers; finished ones leave a notice instead.
+    pub transfers: Vec<TransferStatus>,
+    pub outbox: Vec<OutboxEntry>,
+    pub offers: Vec<IncomingOffer>,
+    pub focus: Pane,
+    pub peers_state: TableState,
+    /// Indexes transfers followed by outbox entries.
+    pub transfers_state: TableState,
+    pub offers_state: TableState,
+    /// Open while choosing a file for the selected peer.
+    pub picker: Option<FilePicker>,
+    pub notice: Option<Notice>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            peers: Vec::new(),
+            transfers: Vec::new(),
+            outbox: Vec::new(),
+            offers: Vec::new(),
+            focus: Pane::Peers,
+            peers_state: TableState::default(),
+            transfers_state: TableState::default(),
+            offers_state: TableState::default(),
+            picker: None,
+            notice: None,
+        }
+    }
+
+    /// Reloads what the event stream does not carry, such as offers and
+    /// the outbox.
+    pub fn refresh(&mut self, client: &mut Client) -> Result<(), ClientError> {
+        let status = client.get_status()?;
+        self.peers = client.list_peers()?;
+        self.transfers = status.transfers;
+        self.outbox = status.outbox;
+        self.offers = status.offers;
+        self.clamp_selection();
+        Ok(())
+    }
+
+    pub fn apply(&mut self, event: DaemonEvent) {
+        match event {
+            DaemonEvent::Peer(PeerEvent::Added(peer) | PeerEvent::Updated(peer)) => {
+                match self.peers.iter_mut().find(|p| p.address == peer.address) {
+                    Some(existing) => *existing = peer,
+                    None => self.peers.push(peer),
+                }
+            }
+            DaemonEvent::Peer(PeerEvent::Removed(peer)) => {
+                self.peers.retain(|p| p.address != peer.address)
+            }
+            DaemonEvent::Transfer(transfer) => {
+                if transfer.state.is_finished() {
+                    self.transfers
+                        .retain(|t| t.transfer_id != transfer.transfer_id);
+                    self.notice = Some(finished_notice(&transfer));
+                } else {
+                    match self
+                        .transfers
+                        .iter_mut()
+                        .find(|t| t.transfer_id == transfer.transfer_id)
+                    {
+                        Some(existing) => *existing = transfer,
+                        None => self.transfers.push(transfer),
+                    }
+                }
+            }
+            DaemonEvent::Outbox(event) => {
+                let (entry, notice) = match event {
+                    OutboxEvent::Delivered { entry } => {
+                        let notice = Notice::Info(format!(
+                            "Delivered queued {} to {}",
+                            file_name(&entry.file_path),
+                            entry.peer
+                        ));
+                        (entry, notice)
+                    }
+                    OutboxEvent::Dropped { entry, reason } => {
+                        let notice = Notice::Error(format!(
+                            "Dropped queued {} for {}: {}",
+                            file_name(&entry.file_path),
+                            entry.peer,
+                            reason
+                        ));
+                        (entry, notice)
+                    }
+                    OutboxEvent::Expired { entry } => {
+                        let notice = Notice::Error(format!(
+                            "{} never came online; gave up on {}",
+                            entry.peer,
+                            file_name(&entry.file_path)
+                        ));
+                        (entry, notice)
+                    }
+                };
+                self.outbox.retain(|queued| queued.id != entry.id);
+                self.notice = Some(notice);
+            }
+        }
+        self.clamp_selection();
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, client: &mut Client) -> Flow {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Flow::Quit;
+        }
+        self.notice = None;
+        if self.picker.is_some() {
+            self.picker_key(key, client);
+            return Flow::Continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Flow::Quit,
+            KeyCode::Tab => self.focus = self.focus.next(),
+            KeyCode::BackTab => self.focus = self.focus.previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.focused_state().select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.focused_state().select_previous(),
+            code => self.action(code, client),
+        }
+        Flow::Continue
+    }
+
+    fn action(&mut self, code: KeyCode, client: &mut Client) {
+        let result = match (self.focus, code) {
+            (Pane::Peers, KeyCode::Enter | KeyCode::Char('s')) => {
+                if self.selected_peer().is_some() {
+                    let dir = std::env::current_dir().unwrap_or_else(|_| "/".into());
+                    match FilePicker::open(&dir) {
+                        Ok(picker) => self.picker = Some(picker),
+                        Err(e) => self.notice = Some(Notice::Error(e.to_string())),
+                    }
+                }
+                return;
+            }
+            (Pane::Transfers, KeyCode::Char('p')) => self.selected_transfer().map(|id| {
+                client
+                    .pause_transfer(&id)
+                    .map(|_| format!("Pausing {}", id))
+            }),
+            (Pane::Transfers, KeyCode::Char('r')) => self.selected_transfer().map(|id| {
+                client
+                    .resume_transfer(&id)
+                    .map(|_| format!("Resumed {}", id))
+            }),
+            (Pane::Transfers, KeyCode::Char('c')) => self.selected_transfer().map(|id| {
+                client
+                    .cancel_transfer(&id)
+                    .map(|_| format!("Cancelled {}", id))
+            }),
+            (Pane::Offers, KeyCode::Char('a')) => self.selected_offer().map(|offer| {
+                client
+                    .accept_offer(&offer.offer_id)
+                    .map(|_| format!("Accepted {}", offer.name))
+            }),
+            (Pane::Offers, KeyCode::Char('x')) => self.selected_offer().map(|offer| {
+                client
+                    .reject_offer(&offer.offer_id)
+                    .map(|_| format!("Rejected {}", offer.name))
+            }),
+            _ => None,
+        };
+        match result {
+            Some(Ok(message)) => {
+                self.notice = Some(Notice::Info(message));
+                if let Err(e) = self.refresh(client) {
+                    self.notice = Some(Notice::Error(e.to_string()));
+                }
+            }
+            Some(Err(e)) => self.notice = Some(Notice::Error(e.to_string())),
+            None => {}
+        }
+    }
+
+    fn picker_key(&mut self, key: KeyEvent, client: &mut Client) {
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+        let result = match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.picker = None;
+                return;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                picker.select_next();
+                Ok(None)
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                picker.select_previous();
+                Ok(None)
+            }
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => picker.up().map(|_| None),
+            KeyCode::Char('.') => picker.toggle_hidden().map(|_| None),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => picker.enter(),
+            _ => Ok(None),
+        };
+        match result {
+            Ok(Some(file)) => {
+                self.picker = None;
+                self.send(&file, client);
+            }
+            Ok(None) => {}
+            Err(e) => self.notice = Some(Notice::Error(e.to_string())),
+        }
+    }
+
+    fn send(&mut self, file: &Path, client: &mut Client) {
+        let Some(peer) = self.selected_peer() else {
+            return;
+        };
+        let params = SendFileParams {
+            path: absolute(file),
+            peer: send_target(peer),
+            expires_in: None,
+        };
+        let name = peer.display_name().to_string();
+        let notice = match client.send_file(&params) {
+            Ok(SendFileResponse::Started { .. }) => {
+                Notice::Info(format!("Sending {} to {}", file_name(&params.path), name))
+            }
+            Ok(SendFileResponse::Queued { .. }) => Notice::Info(format!(
+                "{} is offline; {} is queued",
+                name,
+                file_name(&params.path)
+            )),
+            Err(e) => Notice::Error(e.to_string()),
+        };
+        self.notice = Some(notice);
+        self.focus = Pane::Transfers;
+        if let Err(e) = self.refresh(client) {
+            self.notice = Some(Notice::Error(e.to_string()));
+        }
+    }
+
+    fn focused_state(&mut self) -> &mut TableState {
+        match self.focus {
+            Pane::Peers => &mut self.peers_state,
+            Pane::Transfers => &mut self.transfers_state,
+            Pane::Offers => &mut self.offers_state,
+        }
+    }
+
+    fn selected_peer(&self) -> Option<&Peer> {
+        self.peers.get(self.peers_state.selected()?)
+    }
+
+    /// ID of the selected transfer or outbox entry.
+    fn selected_transfer(&self) -> Option<String> {
+        let index = self.transfers_state.selected()?;
+        match self.transfers.get(index) {
+            Some(transfer) => Some(transfer.transfer_id.clone()),
+            None => self
+                .outbox
+                .get(index - self.transfers.len())
+                .map(|entry| entry.id.clone()),
+        }
+    }
+
+    fn selected_offer(&self) -> Option<IncomingOffer> {
+        self.offers.get(self.offers_state.selected()?).cloned()
+    }
+
+    /// Keeps a row selected in every non-empty pane.
+    fn clamp_selection(&mut self) {
+        let rows = [
+            (&mut self.peers_state, self.peers.len()),
+            (
+                &mut self.transfers_state,
+                self.transfers.len() + self.outbox.len(),
+            ),
+            (&mut self.offers_state, self.offers.len()),
+        ];
+        for (state, len) in rows {
+            let selected = match state.selected() {
+                _ if len == 0 => None,
+                Some(index) => Some(index.min(len - 1)),
+                None => Some(0),
+            };
+            state.select(selected);
+        }
+    }
+}
+
+fn finished_notice(transfer: &TransferStatus) -> Notice {
+    let name = file_name(&transfer.file_path);
+    match transfer.state {
+        TransferState::Completed => Notice::Info(format!("Sent {} to {}", name, transfer.peer)),
+        state => {
+            let error = transfer.attempts.last().and_then(|a| a.error.as_deref());
+            let mut message = format!("{} to {}: {}", name, transfer.peer, state.as_str());
+            if let Some(error) = error {
+                message.push_str(&format!(" ({})", error));
+            }
+            Notice::Error(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(state: TransferState) -> TransferStatus {
+        TransferStatus {
+            transfer_id: "t1".to_string(),
+            peer: "192.168.1.20:7878".to_string(),
+            file_path: "/home/me/photo.jpg".to_string(),
+            state,
+            bytes_sent: 0,
+            total_bytes: 42,
+            attempts: Vec::new(),
+            streamed: false,
+        }
+    }
+
+    #[test]
+    fn finished_transfers_leave_the_list_with_a_notice() {
+        let mut app = App::new();
+        app.apply(DaemonEvent::Transfer(transfer(TransferState::InProgress)));
+        assert_eq!(app.transfers.len(), 1);
+        assert_eq!(app.transfers_state.selected(), Some(0));
+        assert_eq!(app.selected_transfer().as_deref(), Some("t1"));
+
+        app.apply(DaemonEvent::Transfer(transfer(TransferState::Completed)));
+        assert!(app.transfers.is_empty());
+        assert_eq!(app.transfers_state.selected(), None);
+        assert!(matches!(&app.notice, Some(Notice::Info(m)) if m.contains("photo.jpg")));
+    }
+}
@@ -0,0 +1,96 @@
+mod app;
+mod picker;
+mod ui;
+
+use std::{
+    path::Path,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use lanshare_client::{Client, ClientError, DaemonEvent};
+use ratatui::{
+    DefaultTerminal,
+    crossterm::event::{self, Event, KeyEventKind},
+};
+
+use crate::{
+    CliError,
+    tui::app::{App, Flow},
+};
+
+/// Offers and the outbox are not pushed as events, so they are polled.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+enum Message {
+    Input(Event),
+    Daemon(Box<DaemonEvent>),
+    HungUp,
+}
+
+/// Runs the interactive terminal UI until the user quits.
+pub fn run(socket_path: &Path) -> Result<(), CliError> {
+    let mut client = Client::connect(socket_path)?;
+    let events = Client::connect(socket_path)?.subscribe()?;
+    let mut app = App::new();
+    app.refresh(&mut client)?;
+
+    let (tx, rx) = mpsc::channel();
+    let daemon_tx = tx.clone();
+    thread::spawn(move || {
+        for event in events {
+            let Ok(event) = event else { break };
+            if daemon_tx.send(Message::Daemon(Box::new(event))).is_err() {
+                return;
+            }
+        }
+        let _ = daemon_tx.send(Message::HungUp);
+    });
+    // Blocks in read() until the process exits; nothing to join.
+    thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if tx.send(Message::Input(event)).is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut terminal = ratatui::try_init().map_err(CliError::Terminal)?;
+    let result = event_loop(&mut terminal, &mut app, &mut client, &rx);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    client: &mut Client,
+    rx: &Receiver<Message>,
+) -> Result<(), CliError> {
+    let mut refreshed = Instant::now();
+    loop {
+        terminal
+            .draw(|frame| ui::draw(frame, app))
+            .map_err(CliError::Terminal)?;
+
+        let timeout = REFRESH_INTERVAL.saturating_sub(refreshed.elapsed());
+        match rx.recv_timeout(timeout) {
+            Ok(Message::Input(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                if app.handle_key(key, client) == Flow::Quit {
+                    return Ok(());
+                }
+            }
+            // Resizes only need the redraw at the top of the loop.
+            Ok(Message::Input(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Message::Daemon(event)) => app.apply(*event),
+            Ok(Message::HungUp) | Err(RecvTimeoutError::Disconnected) => {
+                return Err(CliError::Daemon(ClientError::HungUp));
+            }
+        }
+        if refreshed.elapsed() >= REFRESH_INTERVAL {
+            app.refresh(client)?;
+            refreshed = Instant::now();
+        }
+    }
+}
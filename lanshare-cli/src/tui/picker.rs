@@ -0,0 +1,117 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use ratatui::widgets::ListState;
+
+/// Browses the local file system for a file to send.
+pub struct FilePicker {
+    pub dir: PathBuf,
+    pub entries: Vec<Entry>,
+    pub state: ListState,
+    pub show_hidden: bool,
+}
+
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+impl FilePicker {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        let mut picker = Self {
+            dir: dir.to_path_buf(),
+            entries: Vec::new(),
+            state: ListState::default(),
+            show_hidden: false,
+        };
+        picker.load()?;
+        Ok(picker)
+    }
+
+    /// Re-reads the directory. Directories come first, `..` at the top.
+    fn load(&mut self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') && !self.show_hidden {
+                continue;
+            }
+            // Follows symlinks; broken ones are listed as empty files.
+            let metadata = fs::metadata(entry.path()).ok();
+            entries.push(Entry {
+                name,
+                is_dir: metadata.as_ref().is_some_and(|m| m.is_dir()),
+                size: metadata.map_or(0, |m| m.len()),
+            });
+        }
+        entries.sort_by(|a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+        if self.dir.parent().is_some() {
+            entries.insert(
+                0,
+                Entry {
+                    name: "..".to_string(),
+                    is_dir: true,
+                    size: 0,
+                },
+            );
+        }
+        self.entries = entries;
+        self.state.select((!self.entries.is_empty()).then_some(0));
+        Ok(())
+    }
+
+    fn change_dir(&mut self, dir: PathBuf) -> io::Result<()> {
+        let previous = std::mem::replace(&mut self.dir, dir);
+        if let Err(e) = self.load() {
+            self.dir = previous;
+            self.load()?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn select_next(&mut self) {
+        self.state.select_next();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.state.select_previous();
+    }
+
+    /// Opens the selected directory, or returns the selected file.
+    pub fn enter(&mut self) -> io::Result<Option<PathBuf>> {
+        let Some(entry) = self.state.selected().and_then(|i| self.entries.get(i)) else {
+            return Ok(None);
+        };
+        if entry.name == ".." {
+            self.up()?;
+            return Ok(None);
+        }
+        let path = self.dir.join(&entry.name);
+        if entry.is_dir {
+            self.change_dir(path)?;
+            return Ok(None);
+        }
+        Ok(Some(path))
+    }
+
+    pub fn up(&mut self) -> io::Result<()> {
+        match self.dir.parent() {
+            Some(parent) => self.change_dir(parent.to_path_buf()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn toggle_hidden(&mut self) -> io::Result<()> {
+        self.show_hidden = !self.show_hidden;
+        self.load()
+    }
+}
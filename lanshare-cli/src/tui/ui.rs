@@ -0,0 +1,272 @@
+use lanshare_app::time::unix_now;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Clear, List, ListItem, Paragraph, Row, Table, TableState},
+};
+
+use crate::{
+    output::{
+        attempt_rate, file_name, format_duration, format_rate, format_size, is_streaming,
+        offer_size, percent, progress_bar, transfer_size,
+    },
+    tui::{
+        app::{App, Notice, Pane},
+        picker::FilePicker,
+    },
+};
+
+const BAR_WIDTH: usize = 16;
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    // Offers need an answer within minutes, so they get room once they arrive.
+    let offers_height = if app.offers.is_empty() {
+        3
+    } else {
+        (app.offers.len() as u16 + 3).min(10)
+    };
+    let [header, body, offers, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(6),
+        Constraint::Length(offers_height),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [peers, transfers] =
+        Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)]).areas(body);
+
+    draw_header(frame, app, header);
+    draw_peers(frame, app, peers);
+    draw_transfers(frame, app, transfers);
+    draw_offers(frame, app, offers);
+    draw_footer(frame, app, footer);
+    if let Some(picker) = app.picker.as_mut() {
+        draw_picker(frame, picker, frame.area());
+    }
+}
+
+fn pane_block(title: String, focused: bool) -> Block<'static> {
+    let block = Block::bordered().title(format!(" {} ", title));
+    if focused {
+        block
+            .border_style(Style::new().fg(Color::Cyan))
+            .title_style(Style::new().bold())
+    } else {
+        block
+    }
+}
+
+fn highlight() -> Style {
+    Style::new().add_modifier(Modifier::REVERSED)
+}
+
+fn render_table(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block<'static>,
+    columns: &[(&'static str, Constraint)],
+    rows: Vec<Row<'static>>,
+    state: &mut TableState,
+    empty: &'static str,
+) {
+    if rows.is_empty() {
+        let text = Paragraph::new(empty).dark_gray().block(block);
+        frame.render_widget(text, area);
+        return;
+    }
+    let widths = columns.iter().map(|(_, width)| *width);
+    let headers = columns.iter().map(|(header, _)| *header);
+    let table = Table::new(rows, widths)
+        .header(Row::new(headers).bold())
+        .block(block)
+        .row_highlight_style(highlight());
+    frame.render_stateful_widget(table, area, state);
+}
+
+fn draw_header(frame: &mut Frame, app: &App, area: Rect) {
+    let line = Line::from(vec![
+        Span::from(" LanShare ").bold().reversed(),
+        Span::from(format!(
+            "  {} peers  {} transfers  {} queued  {} offers",
+            app.peers.len(),
+            app.transfers.len(),
+            app.outbox.len(),
+            app.offers.len()
+        )),
+    ]);
+    frame.render_widget(line, area);
+}
+
+fn draw_peers(frame: &mut Frame, app: &mut App, area: Rect) {
+    let rows = app
+        .peers
+        .iter()
+        .map(|peer| {
+            Row::new(vec![
+                peer.display_name().to_string(),
+                peer.address.to_string(),
+                peer.metadata.status.as_str().to_string(),
+            ])
+        })
+        .collect();
+    render_table(
+        frame,
+        area,
+        pane_block("Peers".to_string(), app.focus == Pane::Peers),
+        &[
+            ("NAME", Constraint::Fill(2)),
+            ("ADDRESS", Constraint::Fill(2)),
+            ("STATUS", Constraint::Length(9)),
+        ],
+        rows,
+        &mut app.peers_state,
+        "No peers discovered yet.",
+    );
+}
+
+fn draw_transfers(frame: &mut Frame, app: &mut App, area: Rect) {
+    let now = unix_now();
+    let transfers = app.transfers.iter().map(|transfer| {
+        let progress = if is_streaming(transfer) {
+            "streaming".to_string()
+        } else {
+            let percent = percent(transfer);
+            format!("{} {:>3}%", progress_bar(percent, BAR_WIDTH), percent)
+        };
+        Row::new(vec![
+            Cell::from(file_name(&transfer.file_path)),
+            Cell::from(transfer.peer.clone()),
+            Cell::from(transfer.state.as_str()),
+            Cell::from(progress),
+            Cell::from(format_size(transfer_size(transfer))),
+            Cell::from(
+                attempt_rate(transfer)
+                    .map(format_rate)
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+        ])
+    });
+    let queued = app.outbox.iter().map(|entry| {
+        Row::new(vec![
+            Cell::from(file_name(&entry.file_path)),
+            Cell::from(entry.peer.clone()),
+            Cell::from("queued"),
+            Cell::from(format!(
+                "gives up in {}",
+                format_duration(entry.expires_at.saturating_sub(now))
+            )),
+            Cell::from("-"),
+            Cell::from("-"),
+        ])
+        .dark_gray()
+    });
+    let rows = transfers.chain(queued).collect();
+    render_table(
+        frame,
+        area,
+        pane_block("Transfers".to_string(), app.focus == Pane::Transfers),
+        &[
+            ("FILE", Constraint::Fill(2)),
+            ("PEER", Constraint::Fill(2)),
+            ("STATE", Constraint::Length(11)),
+            ("PROGRESS", Constraint::Length(BAR_WIDTH as u16 + 5)),
+            ("SIZE", Constraint::Length(10)),
+            ("SPEED", Constraint::Length(12)),
+        ],
+        rows,
+        &mut app.transfers_state,
+        "Nothing is being transferred. Pick a peer and press Enter to send a file.",
+    );
+}
+
+fn draw_offers(frame: &mut Frame, app: &mut App, area: Rect) {
+    let now = unix_now();
+    let rows = app
+        .offers
+        .iter()
+        .map(|offer| {
+            let sender = match &offer.peer {
+                Some(peer) => format!("{} ({})", peer, offer.sender.ip()),
+                None => offer.sender.ip().to_string(),
+            };
+            Row::new(vec![
+                offer.name.clone(),
+                offer_size(offer),
+                sender,
+                format_duration(offer.expires_at.saturating_sub(now)),
+            ])
+        })
+        .collect();
+    let title = match app.offers.len() {
+        0 => "Incoming offers".to_string(),
+        n => format!("Incoming offers ({})", n),
+    };
+    let mut block = pane_block(title, app.focus == Pane::Offers);
+    if !app.offers.is_empty() && app.focus != Pane::Offers {
+        block = block.border_style(Style::new().fg(Color::Yellow));
+    }
+    render_table(
+        frame,
+        area,
+        block,
+        &[
+            ("FILE", Constraint::Fill(3)),
+            ("SIZE", Constraint::Length(10)),
+            ("FROM", Constraint::Fill(2)),
+            ("EXPIRES IN", Constraint::Length(10)),
+        ],
+        rows,
+        &mut app.offers_state,
+        "No incoming offers.",
+    );
+}
+
+fn draw_footer(frame: &mut Frame, app: &App, area: Rect) {
+    let line = match &app.notice {
+        Some(Notice::Info(message)) => Line::from(message.as_str()).green(),
+        Some(Notice::Error(message)) => Line::from(message.as_str()).red(),
+        None => {
+            let keys = match (app.picker.is_some(), app.focus) {
+                (true, _) => "Enter open/send  Backspace up  . hidden files  Esc close",
+                (false, Pane::Peers) => "Enter send a file  Tab next pane  q quit",
+                (false, Pane::Transfers) => "p pause  r resume  c cancel  Tab next pane  q quit",
+                (false, Pane::Offers) => "a accept  x reject  Tab next pane  q quit",
+            };
+            Line::from(keys).dark_gray()
+        }
+    };
+    frame.render_widget(line, area);
+}
+
+fn draw_picker(frame: &mut Frame, picker: &mut FilePicker, area: Rect) {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Percentage(15),
+        Constraint::Percentage(70),
+        Constraint::Percentage(15),
+    ])
+    .areas(area);
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Percentage(15),
+        Constraint::Percentage(70),
+        Constraint::Percentage(15),
+    ])
+    .areas(area);
+
+    let items: Vec<ListItem> = picker
+        .entries
+        .iter()
+        .map(|entry| {
+            if entry.is_dir {
+                ListItem::new(format!("{}/", entry.name)).bold()
+            } else {
+                ListItem::new(format!("{}  {}", entry.name, format_size(entry.size)))
+            }
+        })
+        .collect();
+    let block = pane_block(format!("Send file: {}", picker.dir.display()), true);
+    let list = List::new(items).block(block).highlight_style(highlight());
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut picker.state);
+}
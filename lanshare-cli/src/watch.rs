@@ -0,0 +1,217 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    path::Path,
+    time::Instant,
+};
+
+use lanshare_client::{Client, DaemonEvent};
+use lanshare_domain::models::{TransferState, TransferStatus};
+
+use crate::{
+    CliError,
+    output::{
+        OutputMode, file_name, format_rate, format_size, is_streaming, percent, progress_bar,
+    },
+};
+
+const BAR_WIDTH: usize = 24;
+const NAME_WIDTH: usize = 24;
+/// Weight of the newest sample in the displayed speed.
+const RATE_SMOOTHING: f64 = 0.3;
+
+/// Follows transfers through the daemon's event stream until `only`
+/// finishes, or forever when watching everything.
+pub fn watch(socket_path: &Path, mode: OutputMode, only: Option<&str>) -> Result<(), CliError> {
+    // Subscribed before reading the status, so no update falls in between.
+    let events = Client::connect(socket_path)?.subscribe()?;
+    let mut client = Client::connect(socket_path)?;
+    let mut display = Display::new(mode);
+    for transfer in client.get_status()?.transfers {
+        if only.is_none_or(|id| id == transfer.transfer_id) {
+            display.update(transfer);
+        }
+    }
+    // A transfer that failed straight away is already in the history.
+    if let Some(id) = only
+        && display.rows.is_empty()
+        && let Some(transfer) = client
+            .get_history()?
+            .into_iter()
+            .find(|t| t.transfer_id == id)
+    {
+        display.update(transfer);
+    }
+    display.render();
+    if let Some(done) = only.and_then(|id| display.finished(id)) {
+        return done;
+    }
+
+    for event in events {
+        let DaemonEvent::Transfer(transfer) = event? else {
+            continue;
+        };
+        if only.is_some_and(|id| id != transfer.transfer_id) {
+            continue;
+        }
+        let transfer_id = transfer.transfer_id.clone();
+        display.update(transfer);
+        display.render();
+        if only.is_some()
+            && let Some(done) = display.finished(&transfer_id)
+        {
+            return done;
+        }
+    }
+    Err(CliError::Daemon(lanshare_client::ClientError::HungUp))
+}
+
+struct Row {
+    transfer: TransferStatus,
+    rate: Option<f64>,
+    sampled: Instant,
+    /// State shown by the last line printed when not redrawing in place.
+    printed_state: Option<TransferState>,
+}
+
+struct Display {
+    mode: OutputMode,
+    in_place: bool,
+    rows: Vec<Row>,
+    drawn_lines: usize,
+}
+
+impl Display {
+    fn new(mode: OutputMode) -> Self {
+        Self {
+            mode,
+            in_place: mode == OutputMode::Human && io::stdout().is_terminal(),
+            rows: Vec::new(),
+            drawn_lines: 0,
+        }
+    }
+
+    fn update(&mut self, transfer: TransferStatus) {
+        if self.mode == OutputMode::Json {
+            println!("{}", serde_json::to_string(&transfer).unwrap());
+        }
+        let now = Instant::now();
+        match self
+            .rows
+            .iter_mut()
+            .find(|row| row.transfer.transfer_id == transfer.transfer_id)
+        {
+            Some(row) => {
+                let elapsed = now.duration_since(row.sampled).as_secs_f64();
+                let sent = transfer.bytes_sent.saturating_sub(row.transfer.bytes_sent);
+                if transfer.state != TransferState::InProgress {
+                    row.rate = None;
+                } else if elapsed > 0.0 {
+                    let sample = sent as f64 / elapsed;
+                    row.rate = Some(match row.rate {
+                        Some(rate) => rate + RATE_SMOOTHING * (sample - rate),
+                        None => sample,
+                    });
+                }
+                row.transfer = transfer;
+                row.sampled = now;
+            }
+            None => self.rows.push(Row {
+                transfer,
+                rate: None,
+                sampled: now,
+                printed_state: None,
+            }),
+        }
+    }
+
+    /// `Some` once the transfer is over: `Ok` if it completed.
+    fn finished(&self, transfer_id: &str) -> Option<Result<(), CliError>> {
+        let row = self
+            .rows
+            .iter()
+            .find(|row| row.transfer.transfer_id == transfer_id)?;
+        let transfer = &row.transfer;
+        match transfer.state {
+            TransferState::Completed => Some(Ok(())),
+            state if state.is_finished() => Some(Err(CliError::Transfer(
+                state,
+                transfer.attempts.last().and_then(|a| a.error.clone()),
+            ))),
+            _ => None,
+        }
+    }
+
+    fn render(&mut self) {
+        if self.mode != OutputMode::Human {
+            return;
+        }
+        let mut stdout = io::stdout().lock();
+        if !self.in_place {
+            // Logs and pipes get a line per state change instead of redraws.
+            for row in &mut self.rows {
+                if row.printed_state != Some(row.transfer.state) {
+                    row.printed_state = Some(row.transfer.state);
+                    let _ = writeln!(stdout, "{}", line(row));
+                }
+            }
+            return;
+        }
+
+        if self.drawn_lines > 0 {
+            let _ = write!(stdout, "\x1b[{}A", self.drawn_lines);
+        }
+        if self.rows.is_empty() {
+            let _ = writeln!(stdout, "\x1b[2KWaiting for transfers, Ctrl-C to stop.");
+            self.drawn_lines = 1;
+        } else {
+            for row in &self.rows {
+                let _ = writeln!(stdout, "\x1b[2K{}", line(row));
+            }
+            // Clears the waiting notice when the first transfer replaces it.
+            for _ in self.rows.len()..self.drawn_lines {
+                let _ = writeln!(stdout, "\x1b[2K");
+            }
+            self.drawn_lines = self.rows.len().max(self.drawn_lines);
+        }
+        let _ = stdout.flush();
+    }
+}
+
+fn line(row: &Row) -> String {
+    let transfer = &row.transfer;
+    let detail = match (transfer.state, row.rate) {
+        (TransferState::InProgress, Some(rate)) if rate > 0.0 => format_rate(rate),
+        (state, _) => state.as_str().to_string(),
+    };
+    if is_streaming(transfer) {
+        return format!(
+            "{:<width$} streaming  {}  {}  -> {}",
+            truncate(&file_name(&transfer.file_path), NAME_WIDTH),
+            format_size(transfer.bytes_sent),
+            detail,
+            transfer.peer,
+            width = NAME_WIDTH
+        );
+    }
+    let percent = percent(transfer);
+    let bar = progress_bar(percent, BAR_WIDTH);
+    format!(
+        "{:<width$} [{}] {:>3}%  {} / {}  {}  -> {}",
+        truncate(&file_name(&transfer.file_path), NAME_WIDTH),
+        bar,
+        percent,
+        format_size(transfer.bytes_sent),
+        format_size(transfer.total_bytes),
+        detail,
+        transfer.peer,
+        width = NAME_WIDTH
+    )
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(width - 1).collect();
+    format!("{}…", kept)
+}
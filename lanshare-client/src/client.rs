@@ -0,0 +1,289 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+};
+
+use lanshare_config::{Config, ReloadReport};
+use lanshare_domain::models::{Peer, SharedFile, TransferStatus};
+use lanshare_ipc::{
+    AddPeerParams, AliasPeerParams, DISCOVER_METHOD, DaemonEvent, EVENT_METHOD, NoParams,
+    OfferParams, PeerParams, RpcRequest, RpcResponse, SendFileParams, SendFileResponse,
+    ShareFileParams, StatusResponse, TagPeerParams, TransferParams, UnshareFileParams,
+    WebDropStatus,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::error::{ClientError, protocol};
+
+/// Typed access to the daemon over its IPC socket. One client is one
+/// connection; calls on it are answered in order.
+pub struct Client {
+    stream: UnixStream,
+    reader: BufReader<UnixStream>,
+    next_id: u64,
+    /// Notifications that arrived while waiting for a response.
+    events: VecDeque<Value>,
+}
+
+impl Client {
+    pub fn connect(socket_path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        let stream = UnixStream::connect(socket_path).map_err(ClientError::Connect)?;
+        let reader = BufReader::new(stream.try_clone().map_err(ClientError::Connect)?);
+        Ok(Self {
+            stream,
+            reader,
+            next_id: 1,
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Calls `method` and decodes its result; the methods below wrap this
+    /// for everything the daemon offers.
+    pub fn call<P: Serialize, R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: P,
+    ) -> Result<R, ClientError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let params = serde_json::to_value(params).map_err(protocol)?;
+        let mut line =
+            serde_json::to_string(&RpcRequest::new(method, params, id)).map_err(protocol)?;
+        line.push('\n');
+        self.stream
+            .write_all(line.as_bytes())
+            .map_err(ClientError::Io)?;
+
+        loop {
+            let message = read_message(&mut self.reader)?;
+            let answers_us = match message.get("id") {
+                Some(Value::Null) => message.get("error").is_some(),
+                Some(other) => *other == id,
+                None => {
+                    self.events.push_back(message);
+                    continue;
+                }
+            };
+            if !answers_us {
+                continue;
+            }
+            let response: RpcResponse = serde_json::from_value(message).map_err(protocol)?;
+            let result = response.into_result().map_err(ClientError::Rpc)?;
+            return serde_json::from_value(result).map_err(protocol);
+        }
+    }
+
+    pub fn list_peers(&mut self) -> Result<Vec<Peer>, ClientError> {
+        self.call("list_peers", NoParams {})
+    }
+
+    pub fn send_file(&mut self, params: &SendFileParams) -> Result<SendFileResponse, ClientError> {
+        self.call("send_file", params)
+    }
+
+    pub fn get_status(&mut self) -> Result<StatusResponse, ClientError> {
+        self.call("get_status", NoParams {})
+    }
+
+    pub fn cancel_transfer(&mut self, transfer_id: &str) -> Result<(), ClientError> {
+        let params = TransferParams {
+            transfer_id: transfer_id.to_string(),
+        };
+        self.call::<_, Value>("cancel_transfer", params).map(drop)
+    }
+
+    pub fn pause_transfer(&mut self, transfer_id: &str) -> Result<(), ClientError> {
+        let params = TransferParams {
+            transfer_id: transfer_id.to_string(),
+        };
+        self.call::<_, Value>("pause_transfer", params).map(drop)
+    }
+
+    pub fn resume_transfer(&mut self, transfer_id: &str) -> Result<(), ClientError> {
+        let params = TransferParams {
+            transfer_id: transfer_id.to_string(),
+        };
+        self.call::<_, Value>("resume_transfer", params).map(drop)
+    }
+
+    pub fn get_history(&mut self) -> Result<Vec<TransferStatus>, ClientError> {
+        self.call("get_history", NoParams {})
+    }
+
+    pub fn accept_offer(&mut self, offer_id: &str) -> Result<(), ClientError> {
+        let params = OfferParams {
+            offer_id: offer_id.to_string(),
+        };
+        self.call::<_, Value>("accept_offer", params).map(drop)
+    }
+
+    pub fn reject_offer(&mut self, offer_id: &str) -> Result<(), ClientError> {
+        let params = OfferParams {
+            offer_id: offer_id.to_string(),
+        };
+        self.call::<_, Value>("reject_offer", params).map(drop)
+    }
+
+    pub fn add_peer(&mut self, params: &AddPeerParams) -> Result<Peer, ClientError> {
+        self.call("add_peer", params)
+    }
+
+    pub fn remove_peer(&mut self, peer: &str) -> Result<(), ClientError> {
+        let params = PeerParams {
+            peer: peer.to_string(),
+        };
+        self.call::<_, Value>("remove_peer", params).map(drop)
+    }
+
+    pub fn alias_peer(&mut self, peer: &str, alias: Option<&str>) -> Result<Peer, ClientError> {
+        let params = AliasPeerParams {
+            peer: peer.to_string(),
+            alias: alias.map(str::to_string),
+        };
+        self.call("alias_peer", params)
+    }
+
+    pub fn tag_peer(&mut self, peer: &str, tags: Vec<String>) -> Result<Peer, ClientError> {
+        let params = TagPeerParams {
+            peer: peer.to_string(),
+            tags,
+        };
+        self.call("tag_peer", params)
+    }
+
+    pub fn get_config(&mut self) -> Result<Config, ClientError> {
+        self.call("get_config", NoParams {})
+    }
+
+    pub fn reload_config(&mut self) -> Result<ReloadReport, ClientError> {
+        self.call("reload_config", NoParams {})
+    }
+
+    pub fn shutdown(&mut self) -> Result<(), ClientError> {
+        self.call::<_, Value>("shutdown", NoParams {}).map(drop)
+    }
+
+    /// Metrics in the Prometheus text format.
+    pub fn metrics(&mut self) -> Result<String, ClientError> {
+        self.call("metrics", NoParams {})
+    }
+
+    pub fn web_drop_status(&mut self) -> Result<WebDropStatus, ClientError> {
+        self.call("web_drop_status", NoParams {})
+    }
+
+    pub fn share_file(&mut self, path: &str) -> Result<SharedFile, ClientError> {
+        let params = ShareFileParams {
+            path: path.to_string(),
+        };
+        self.call("share_file", params)
+    }
+
+    pub fn unshare_file(&mut self, share_id: &str) -> Result<(), ClientError> {
+        let params = UnshareFileParams {
+            share_id: share_id.to_string(),
+        };
+        self.call::<_, Value>("unshare_file", params).map(drop)
+    }
+
+    /// The OpenRPC description of the protocol.
+    pub fn discover(&mut self) -> Result<Value, ClientError> {
+        self.call(DISCOVER_METHOD, NoParams {})
+    }
+
+    /// Turns this connection into a stream of daemon events. Use another
+    /// client for calls made while watching.
+    pub fn subscribe(mut self) -> Result<Events, ClientError> {
+        self.call::<_, Value>("subscribe", NoParams {})?;
+        Ok(Events {
+            reader: self.reader,
+            buffered: self.events,
+        })
+    }
+}
+
+/// Blocks until the next event; ends when the daemon goes away.
+pub struct Events {
+    reader: BufReader<UnixStream>,
+    buffered: VecDeque<Value>,
+}
+
+impl Iterator for Events {
+    type Item = Result<DaemonEvent, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let message = match self.buffered.pop_front() {
+                Some(message) => message,
+                None => match read_message(&mut self.reader) {
+                    Ok(message) => message,
+                    Err(ClientError::HungUp) => return None,
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            if message.get("method").and_then(Value::as_str) == Some(EVENT_METHOD) {
+                return Some(serde_json::from_value(message["params"].clone()).map_err(protocol));
+            }
+        }
+    }
+}
+
+fn read_message(reader: &mut BufReader<UnixStream>) -> Result<Value, ClientError> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => return Err(ClientError::HungUp),
+            Ok(_) if line.trim().is_empty() => continue,
+            Ok(_) => return serde_json::from_str(&line).map_err(protocol),
+            Err(e) => return Err(ClientError::Io(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{os::unix::net::UnixListener, path::PathBuf, thread};
+
+    #[test]
+    fn events_sent_before_the_subscribe_reply_are_kept() {
+        let socket_path = PathBuf::from("/tmp/lanshare-client-test.sock");
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        let daemon = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            BufReader::new(stream.try_clone().unwrap())
+                .read_line(&mut request)
+                .unwrap();
+            let request: Value = serde_json::from_str(&request).unwrap();
+            assert_eq!(request["method"], "subscribe");
+            let event = r#"{"jsonrpc":"2.0","method":"event","params":{"kind":"peer","data":{"event":"removed","peer":{"name":"test","address":"127.0.0.1:7878","last_seen":0}}}}"#;
+            let reply = format!(
+                r#"{{"jsonrpc":"2.0","result":"ok","id":{}}}"#,
+                request["id"]
+            );
+            stream
+                .write_all(format!("{}\n{}\n", event, reply).as_bytes())
+                .unwrap();
+        });
+
+        let client = Client::connect(&socket_path).unwrap();
+        let mut events = client.subscribe().unwrap();
+        daemon.join().unwrap();
+
+        match events.next() {
+            Some(Ok(DaemonEvent::Peer(lanshare_domain::models::PeerEvent::Removed(peer)))) => {
+                assert_eq!(peer.name, "test")
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(events.next().is_none());
+        let _ = std::fs::remove_file(&socket_path);
+    }
+}
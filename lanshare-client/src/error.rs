@@ -0,0 +1,60 @@
+use std::{fmt, io};
+
+use lanshare_ipc::RpcError;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Connect(io::Error),
+    Io(io::Error),
+    HungUp,
+    Protocol(String),
+    /// The daemon answered with an error.
+    Rpc(RpcError),
+}
+
+impl ClientError {
+    /// Stable name of a daemon error, e.g. `PEER_NOT_FOUND`.
+    pub fn kind(&self) -> Option<&str> {
+        match self {
+            ClientError::Rpc(error) => error.data.as_ref()?.get("code")?.as_str(),
+            _ => None,
+        }
+    }
+
+    /// Peers an ambiguous name could refer to.
+    pub fn candidates(&self) -> Vec<String> {
+        match self {
+            ClientError::Rpc(error) => error
+                .data
+                .as_ref()
+                .and_then(|data| data.get("candidates"))
+                .and_then(|candidates| serde_json::from_value(candidates.clone()).ok())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Connect(e) => write!(
+                f,
+                "Could not connect to LanShare daemon. Is it running?\n  ({})",
+                e
+            ),
+            ClientError::Io(e) => write!(f, "Daemon connection failed: {}", e),
+            ClientError::HungUp => write!(f, "Daemon closed the connection unexpectedly."),
+            ClientError::Protocol(message) => {
+                write!(f, "Unexpected reply from daemon: {}", message)
+            }
+            ClientError::Rpc(error) => write!(f, "{}", error.message),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+pub(crate) fn protocol(error: serde_json::Error) -> ClientError {
+    ClientError::Protocol(error.to_string())
+}
@@ -0,0 +1,9 @@
+mod client;
+mod error;
+
+pub use client::{Client, Events};
+pub use error::ClientError;
+pub use lanshare_ipc::{
+    AddPeerParams, DaemonEvent, RpcError, SendFileParams, SendFileResponse, StatusResponse,
+    WebDropStatus,
+};
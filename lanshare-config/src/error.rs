@@ -0,0 +1,39 @@
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: PathBuf, message: String },
+    Parse { path: PathBuf, message: String },
+    UnknownKey(String),
+    InvalidValue { key: String, message: String },
+    InvalidArgument(String),
+}
+
+impl ConfigError {
+    pub(crate) fn invalid(key: &str, message: impl Into<String>) -> Self {
+        ConfigError::InvalidValue {
+            key: key.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, message } => {
+                write!(f, "Cannot read config file {}: {}", path.display(), message)
+            }
+            ConfigError::Parse { path, message } => {
+                write!(f, "Invalid config file {}: {}", path.display(), message)
+            }
+            ConfigError::UnknownKey(key) => write!(f, "Unknown config key '{}'", key),
+            ConfigError::InvalidValue { key, message } => {
+                write!(f, "Invalid value for '{}': {}", key, message)
+            }
+            ConfigError::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
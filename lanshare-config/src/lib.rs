@@ -0,0 +1,12 @@
+mod error;
+mod load;
+mod model;
+mod reload;
+
+pub use error::ConfigError;
+pub use load::{Overrides, default_config_path, default_socket_path};
+pub use model::{
+    Config, DaemonConfig, DiscoveryConfig, GatewayConfig, LimitsConfig, LogConfig, LogFormat,
+    MetricsConfig, ReceiveConfig, TransferConfig, WebDropConfig,
+};
+pub use reload::ReloadReport;
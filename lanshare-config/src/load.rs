@@ -0,0 +1,219 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use toml::Value;
+
+use crate::{error::ConfigError, model::Config};
+
+const ENV_PREFIX: &str = "LANSHARE_";
+const CONFIG_PATH_ENV: &str = "LANSHARE_CONFIG";
+
+/// Settings that take precedence over the config file and environment,
+/// usually collected from command-line flags.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub config_path: Option<PathBuf>,
+    /// Dotted keys such as `daemon.port`, with raw string values.
+    pub values: Vec<(String, String)>,
+}
+
+impl Overrides {
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        self.values.push((key.to_string(), value.into()));
+    }
+}
+
+/// `$XDG_CONFIG_HOME/lanshare/config.toml`, falling back to
+/// `~/.config/lanshare/config.toml`.
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("lanshare").join("config.toml"))
+}
+
+/// `$XDG_RUNTIME_DIR/lanshare.sock`, private to the user; `/tmp` only when
+/// there is no runtime directory.
+pub fn default_socket_path() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join("lanshare.sock"))
+        .unwrap_or_else(|| PathBuf::from("/tmp/lanshare.sock"))
+}
+
+impl Config {
+    /// Builds the effective configuration: defaults, then the config file,
+    /// then `LANSHARE_*` environment variables, then explicit overrides.
+    pub fn load(overrides: &Overrides) -> Result<Self, ConfigError> {
+        let explicit_path = overrides
+            .config_path
+            .clone()
+            .or_else(|| env::var_os(CONFIG_PATH_ENV).map(PathBuf::from));
+
+        let mut config = match explicit_path {
+            Some(path) => Self::from_file(&path)?,
+            None => match default_config_path() {
+                Some(path) if path.exists() => Self::from_file(&path)?,
+                _ => Config::default(),
+            },
+        };
+
+        config.apply_env(env::vars())?;
+        for (key, value) in &overrides.values {
+            config.set(key, value)?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|e| ConfigError::Io {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        toml::from_str(&contents).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.message().to_string(),
+        })
+    }
+
+    /// All settable dotted keys, e.g. `transfer.chunk_size`.
+    pub fn keys() -> Vec<String> {
+        let Ok(Value::Table(root)) = Value::try_from(Config::default()) else {
+            return Vec::new();
+        };
+        root.iter()
+            .filter_map(|(section, fields)| fields.as_table().map(|fields| (section, fields)))
+            .flat_map(|(section, fields)| {
+                fields
+                    .keys()
+                    .map(move |field| format!("{}.{}", section, field))
+            })
+            .collect()
+    }
+
+    pub fn env_var_name(key: &str) -> String {
+        format!("{}{}", ENV_PREFIX, key.replace('.', "_").to_uppercase())
+    }
+
+    /// Sets one value from its string form; lists are comma separated.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let mut root =
+            Value::try_from(&*self).map_err(|e| ConfigError::invalid(key, e.to_string()))?;
+        let slot = key
+            .split_once('.')
+            .and_then(|(section, field)| root.get_mut(section)?.get_mut(field))
+            .ok_or_else(|| ConfigError::UnknownKey(key.to_string()))?;
+        *slot = parse_like(slot, value).map_err(|message| ConfigError::invalid(key, message))?;
+
+        *self = root
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::invalid(key, e.message().to_string()))?;
+        Ok(())
+    }
+
+    fn apply_env(
+        &mut self,
+        vars: impl Iterator<Item = (String, String)>,
+    ) -> Result<(), ConfigError> {
+        let vars: Vec<(String, String)> = vars
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name != CONFIG_PATH_ENV)
+            .collect();
+        for key in Self::keys() {
+            let name = Self::env_var_name(&key);
+            if let Some((_, value)) = vars.iter().find(|(var, _)| *var == name) {
+                self.set(&key, value).map_err(|e| match e {
+                    ConfigError::InvalidValue { message, .. } => ConfigError::InvalidValue {
+                        key: name.clone(),
+                        message,
+                    },
+                    other => other,
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_like(current: &Value, raw: &str) -> Result<Value, String> {
+    let raw = raw.trim();
+    match current {
+        Value::Integer(_) => raw
+            .parse::<i64>()
+            .map(Value::Integer)
+            .map_err(|_| format!("'{}' is not a whole number", raw)),
+        Value::Boolean(_) => match raw.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Ok(Value::Boolean(true)),
+            "false" | "no" | "off" | "0" => Ok(Value::Boolean(false)),
+            _ => Err(format!("'{}' is not a boolean", raw)),
+        },
+        Value::Array(_) => Ok(Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        )),
+        _ => Ok(Value::String(raw.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_parsed_by_the_type_of_the_key() {
+        let mut config = Config::default();
+        config.set("daemon.port", "9000").unwrap();
+        config.set("discovery.mdns", "off").unwrap();
+        config
+            .set("discovery.exclude_interfaces", "docker0, veth*")
+            .unwrap();
+
+        assert_eq!(config.daemon.port, 9000);
+        assert!(!config.discovery.mdns);
+        assert_eq!(
+            config.discovery.exclude_interfaces,
+            vec!["docker0", "veth*"]
+        );
+        assert!(matches!(
+            config.set("daemon.port", "70000"),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            config.set("daemon.prot", "1"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn environment_variables_override_file_values() {
+        let mut config: Config = toml::from_str("[daemon]\nport = 7000\n").unwrap();
+        let vars = vec![
+            ("LANSHARE_DAEMON_PORT".to_string(), "7100".to_string()),
+            (
+                "LANSHARE_TRANSFER_CHUNK_SIZE".to_string(),
+                "65536".to_string(),
+            ),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ];
+        config.apply_env(vars.into_iter()).unwrap();
+
+        assert_eq!(config.daemon.port, 7100);
+        assert_eq!(config.transfer.chunk_size, 65536);
+    }
+
+    #[test]
+    fn unknown_file_keys_and_bad_values_are_rejected() {
+        assert!(toml::from_str::<Config>("[daemon]\nprot = 1\n").is_err());
+
+        let mut config = Config::default();
+        config.transfer.chunk_size = 10;
+        let error = config.validate().unwrap_err();
+        assert!(error.to_string().contains("transfer.chunk_size"));
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ConfigError,
+    load::{default_config_path, default_socket_path},
+};
+
+const MIN_CHUNK_SIZE: usize = 1024;
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+/// Senders wait five minutes for an answer; leave them some slack.
+const MAX_OFFER_TIMEOUT_SECS: u64 = 240;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub daemon: DaemonConfig,
+    pub transfer: TransferConfig,
+    pub discovery: DiscoveryConfig,
+    pub receive: ReceiveConfig,
+    pub limits: LimitsConfig,
+    pub log: LogConfig,
+    pub metrics: MetricsConfig,
+    pub gateway: GatewayConfig,
+    pub web_drop: WebDropConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// Name shown to other peers; empty means the hostname.
+    pub name: String,
+    pub storage_dir: PathBuf,
+    pub socket_path: PathBuf,
+    /// Transfer listener port; 0 lets the OS pick one.
+    pub port: u16,
+    /// How long shutdown waits for in-flight transfers to checkpoint.
+    pub shutdown_timeout_secs: u64,
+    /// CLI and other IPC clients served at the same time.
+    pub max_ipc_clients: usize,
+    /// Users (names or UIDs) besides the daemon's own allowed on the IPC
+    /// socket. They also need access to its directory, which the default
+    /// `$XDG_RUNTIME_DIR` does not grant.
+    pub ipc_allowed_users: Vec<String>,
+    /// Groups (names or GIDs) whose members are allowed on the IPC socket.
+    pub ipc_allowed_groups: Vec<String>,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            storage_dir: PathBuf::from("./lanshare_storage"),
+            socket_path: default_socket_path(),
+            port: 8080,
+            shutdown_timeout_secs: 10,
+            max_ipc_clients: 16,
+            ipc_allowed_users: Vec::new(),
+            ipc_allowed_groups: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransferConfig {
+    pub chunk_size: usize,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub deadline_secs: u64,
+    pub outbox_expiry_secs: u64,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 8192,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            deadline_secs: 10 * 60,
+            outbox_expiry_secs: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    pub mdns: bool,
+    pub udp: bool,
+    pub udp_port: u16,
+    pub multicast_group: Ipv4Addr,
+    pub beacon_interval_secs: u64,
+    /// Authenticates UDP beacons, so set the same key on every device. Empty
+    /// uses a key generated for this install.
+    pub network_key: String,
+    /// Interface names (`eth0`, `veth*`) or CIDR subnets to use exclusively.
+    pub interfaces: Vec<String>,
+    pub exclude_interfaces: Vec<String>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            mdns: true,
+            udp: true,
+            udp_port: 45454,
+            multicast_group: Ipv4Addr::new(239, 255, 76, 83),
+            beacon_interval_secs: 5,
+            network_key: String::new(),
+            interfaces: Vec::new(),
+            exclude_interfaces: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReceiveConfig {
+    /// Where completed files are moved; empty means `<storage_dir>/final`.
+    pub directory: PathBuf,
+    /// When off, transfers wait for `lanshare-cli accept` or `reject`.
+    pub auto_accept: bool,
+    /// How long an unanswered offer waits before it is rejected; senders
+    /// give up after five minutes.
+    pub offer_timeout_secs: u64,
+    /// Sender IPs or peer references allowed to send; empty allows everyone.
+    pub accept_from: Vec<String>,
+    /// Largest accepted file in bytes; 0 means no limit.
+    pub max_file_size: u64,
+}
+
+impl Default for ReceiveConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::new(),
+            auto_accept: true,
+            offer_timeout_secs: 120,
+            accept_from: Vec::new(),
+            max_file_size: 0,
+        }
+    }
+}
+
+/// Bandwidth caps shared by all transfers in one direction; 0 means unlimited.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub upload_bytes_per_sec: u64,
+    pub download_bytes_per_sec: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// `tracing` filter directives, e.g. `info` or `warn,lanshare_network=debug`.
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
+/// Output format on stderr; under systemd logs go to journald instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Prometheus endpoint; metrics are always collected and also available
+/// over IPC, this only controls the HTTP listener.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// Must be a loopback address; the endpoint has no authentication.
+    pub listen: SocketAddr,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 9464),
+        }
+    }
+}
+
+/// HTTP/WebSocket access to the IPC operations for clients that cannot use
+/// the Unix socket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct GatewayConfig {
+    pub enabled: bool,
+    /// Must be a loopback address.
+    pub listen: SocketAddr,
+    /// Bearer token clients must present, created on first start; empty
+    /// means `gateway.token` next to the config file.
+    pub token_file: PathBuf,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 7880),
+            token_file: PathBuf::new(),
+        }
+    }
+}
+
+/// Browser page on the LAN for devices without LanShare, unlocked by a PIN
+/// that changes every `pin_lifetime_secs`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebDropConfig {
+    pub enabled: bool,
+    pub listen: SocketAddr,
+    pub pin_lifetime_secs: u64,
+}
+
+impl Default for WebDropConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 8088),
+            pin_lifetime_secs: 10 * 60,
+        }
+    }
+}
+
+impl Config {
+    /// Directory completed files end up in.
+    pub fn receive_dir(&self) -> PathBuf {
+        if self.receive.directory.as_os_str().is_empty() {
+            self.daemon.storage_dir.join("final")
+        } else {
+            self.receive.directory.clone()
+        }
+    }
+
+    /// `None` when no file is configured and there is no config directory.
+    pub fn gateway_token_file(&self) -> Option<PathBuf> {
+        if self.gateway.token_file.as_os_str().is_empty() {
+            Some(default_config_path()?.with_file_name("gateway.token"))
+        } else {
+            Some(self.gateway.token_file.clone())
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.daemon.storage_dir.as_os_str().is_empty() {
+            return Err(ConfigError::invalid(
+                "daemon.storage_dir",
+                "must not be empty",
+            ));
+        }
+        if self.daemon.socket_path.as_os_str().is_empty() {
+            return Err(ConfigError::invalid(
+                "daemon.socket_path",
+                "must not be empty",
+            ));
+        }
+        if self.daemon.shutdown_timeout_secs == 0 {
+            return Err(ConfigError::invalid(
+                "daemon.shutdown_timeout_secs",
+                "must be greater than 0",
+            ));
+        }
+
+        if self.daemon.max_ipc_clients == 0 {
+            return Err(ConfigError::invalid(
+                "daemon.max_ipc_clients",
+                "must be greater than 0",
+            ));
+        }
+
+        if !(1..=MAX_OFFER_TIMEOUT_SECS).contains(&self.receive.offer_timeout_secs) {
+            return Err(ConfigError::invalid(
+                "receive.offer_timeout_secs",
+                format!("must be between 1 and {}", MAX_OFFER_TIMEOUT_SECS),
+            ));
+        }
+
+        if self.log.level.trim().is_empty() {
+            return Err(ConfigError::invalid("log.level", "must not be empty"));
+        }
+
+        if !self.metrics.listen.ip().is_loopback() {
+            return Err(ConfigError::invalid(
+                "metrics.listen",
+                format!("{} is not a loopback address", self.metrics.listen),
+            ));
+        }
+        if !self.gateway.listen.ip().is_loopback() {
+            return Err(ConfigError::invalid(
+                "gateway.listen",
+                format!("{} is not a loopback address", self.gateway.listen),
+            ));
+        }
+        if self.web_drop.pin_lifetime_secs == 0 {
+            return Err(ConfigError::invalid(
+                "web_drop.pin_lifetime_secs",
+                "must be greater than 0",
+            ));
+        }
+
+        let transfer = &self.transfer;
+        if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&transfer.chunk_size) {
+            return Err(ConfigError::invalid(
+                "transfer.chunk_size",
+                format!(
+                    "{} is outside {}..={} bytes",
+                    transfer.chunk_size, MIN_CHUNK_SIZE, MAX_CHUNK_SIZE
+                ),
+            ));
+        }
+        if transfer.initial_backoff_ms == 0 {
+            return Err(ConfigError::invalid(
+                "transfer.initial_backoff_ms",
+                "must be greater than 0",
+            ));
+        }
+        if transfer.max_backoff_ms < transfer.initial_backoff_ms {
+            return Err(ConfigError::invalid(
+                "transfer.max_backoff_ms",
+                "must not be smaller than transfer.initial_backoff_ms",
+            ));
+        }
+        if transfer.deadline_secs == 0 {
+            return Err(ConfigError::invalid(
+                "transfer.deadline_secs",
+                "must be greater than 0",
+            ));
+        }
+
+        let discovery = &self.discovery;
+        if discovery.udp && !discovery.multicast_group.is_multicast() {
+            return Err(ConfigError::invalid(
+                "discovery.multicast_group",
+                format!("{} is not a multicast address", discovery.multicast_group),
+            ));
+        }
+        if discovery.beacon_interval_secs == 0 {
+            return Err(ConfigError::invalid(
+                "discovery.beacon_interval_secs",
+                "must be greater than 0",
+            ));
+        }
+        if discovery.udp && discovery.udp_port == 0 {
+            return Err(ConfigError::invalid(
+                "discovery.udp_port",
+                "must not be 0 while UDP discovery is enabled",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap_or_default()
+    }
+}
@@ -0,0 +1,87 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::model::Config;
+
+/// Keys (or `section.` prefixes) that the daemon applies without a restart.
+const LIVE_KEYS: &[&str] = &["daemon.name", "receive.", "limits.", "log.level"];
+
+/// Outcome of re-reading the configuration in a running daemon.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ReloadReport {
+    /// Changed keys that took effect immediately.
+    pub applied: Vec<String>,
+    /// Changed keys that keep their old value until the daemon restarts.
+    pub restart_required: Vec<String>,
+}
+
+impl Config {
+    pub fn is_live_key(key: &str) -> bool {
+        LIVE_KEYS
+            .iter()
+            .any(|live| key == *live || (live.ends_with('.') && key.starts_with(live)))
+    }
+
+    /// Merges `new` into the running configuration: live keys take the new
+    /// value, every other key keeps the current one and is reported.
+    pub fn reload_from(&self, new: &Config) -> (Config, ReloadReport) {
+        let mut effective = self.clone();
+        let mut report = ReloadReport::default();
+        let (Ok(old_values), Ok(new_values)) = (Value::try_from(self), Value::try_from(new)) else {
+            return (effective, report);
+        };
+
+        for key in Config::keys() {
+            let lookup = |root: &Value| -> Option<Value> {
+                let (section, field) = key.split_once('.')?;
+                root.get(section)?.get(field).cloned()
+            };
+            if lookup(&old_values) == lookup(&new_values) {
+                continue;
+            }
+            if Self::is_live_key(&key) {
+                report.applied.push(key);
+            } else {
+                report.restart_required.push(key);
+            }
+        }
+
+        effective.daemon.name = new.daemon.name.clone();
+        effective.receive = new.receive.clone();
+        effective.limits = new.limits.clone();
+        effective.log.level = new.log.level.clone();
+        (effective, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_live_keys_change_the_effective_config() {
+        let current = Config::default();
+        let mut new = Config::default();
+        new.daemon.name = "studio".to_string();
+        new.daemon.port = 9000;
+        new.limits.upload_bytes_per_sec = 1_000_000;
+        new.receive.auto_accept = false;
+
+        let (effective, report) = current.reload_from(&new);
+
+        assert_eq!(effective.daemon.name, "studio");
+        assert_eq!(effective.daemon.port, current.daemon.port);
+        assert_eq!(effective.limits.upload_bytes_per_sec, 1_000_000);
+        assert!(!effective.receive.auto_accept);
+        assert_eq!(report.restart_required, vec!["daemon.port"]);
+        assert_eq!(
+            report.applied,
+            vec![
+                "daemon.name",
+                "limits.upload_bytes_per_sec",
+                "receive.auto_accept"
+            ]
+        );
+    }
+}
@@ -2,7 +2,7 @@
 use mdns_sd::{ServiceDaemon, ServiceInfo};
 use std::time::Duration;
 
-const SERVICE_NAME: &'static str = "_lanshare._tcp.local.";
+const SERVICE_NAME: &str = "_lanshare._tcp.local.";
 
 fn main() {
     let mdns = ServiceDaemon::new().unwrap();
@@ -13,7 +13,14 @@ fn main() {
         "test.local.",
         "192.168.1.100",
         8080,
-        &[("id", "DIFFERENT_PEER")][..],
+        &[
+            ("id", "DIFFERENT_PEER"),
+            ("name", "Different Peer"),
+            ("os", "linux"),
+            ("proto", "1"),
+            ("caps", ""),
+            ("status", "accepting"),
+        ][..],
     )
     .unwrap();
 
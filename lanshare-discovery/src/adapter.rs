@@ -1,100 +1,236 @@
 use std::{
-    collections::HashMap,
     net::{IpAddr, SocketAddr},
-    sync::{Arc, RwLock},
+    sync::{Arc, RwLock, mpsc::Receiver},
     thread,
-    time::{SystemTime, UNIX_EPOCH},
+    time::Duration,
 };
 
-use lanshare_domain::{error::DomainError, models::Peer, ports::DiscoveryPort};
-use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use lanshare_domain::{
+    error::DomainError,
+    models::{Peer, PeerEvent},
+    ports::DiscoveryPort,
+};
+use mdns_sd::{IfKind, ServiceDaemon, ServiceEvent, ServiceInfo};
+use tracing::{info, warn};
+
+use crate::{
+    interfaces::{InterfaceFilter, LocalInterface, local_interfaces},
+    registry::{PeerRegistry, unix_now},
+    txt::{decode_metadata, encode_metadata},
+};
 
 const SERVICE_NAME: &str = "_lanshare._tcp.local.";
+const INTERFACE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const UNREGISTER_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub struct MdnsDiscoveryAdapter {
     daemon: ServiceDaemon,
-    registry: Arc<RwLock<HashMap<String, Peer>>>,
+    registry: Arc<PeerRegistry>,
+    filter: InterfaceFilter,
+    interfaces: Arc<RwLock<Vec<LocalInterface>>>,
+    advertised: Arc<RwLock<Option<Peer>>>,
 }
 
 impl MdnsDiscoveryAdapter {
     pub fn new() -> Result<Self, DomainError> {
+        Self::with_filter(InterfaceFilter::default())
+    }
+
+    pub fn with_filter(filter: InterfaceFilter) -> Result<Self, DomainError> {
         let daemon = ServiceDaemon::new().map_err(|e| DomainError::IoError(e.to_string()))?;
-        let registry = Arc::new(RwLock::new(HashMap::new()));
-        let registry_clone = Arc::clone(&registry);
+        let registry = Arc::new(PeerRegistry::new());
+        let interfaces = Arc::new(RwLock::new(local_interfaces()));
+        let advertised: Arc<RwLock<Option<Peer>>> = Arc::new(RwLock::new(None));
+
+        if let Ok(current) = interfaces.read() {
+            select_interfaces(&daemon, &filter, &current)?;
+        }
 
         let browse_rx = daemon
             .browse(SERVICE_NAME)
             .map_err(|e| DomainError::IoError(e.to_string()))?;
 
+        let registry_clone = Arc::clone(&registry);
+        let interfaces_clone = Arc::clone(&interfaces);
+        let filter_clone = filter.clone();
         thread::spawn(move || {
             while let Ok(event) = browse_rx.recv() {
                 match event {
                     ServiceEvent::ServiceResolved(info) => {
-                        if let Some(ip_v4) = info.get_addresses_v4().iter().next() {
-                            let addr = SocketAddr::new(IpAddr::V4(*ip_v4), info.get_port());
-                            let now = SystemTime::now()
-                                .duration_since(UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs();
-
-                            let peer = Peer::new(info.get_fullname().to_string(), addr, now);
-
-                            if let Ok(mut guard) = registry_clone.write() {
-                                guard.insert(info.get_fullname().to_string(), peer);
-                            }
+                        let current = interfaces_clone
+                            .read()
+                            .map(|g| g.clone())
+                            .unwrap_or_default();
+                        let address = info
+                            .get_addresses_v4()
+                            .iter()
+                            .map(|ip_v4| IpAddr::V4(*ip_v4))
+                            .find(|ip| filter_clone.allows_remote(*ip, &current));
+                        if let Some(ip) = address {
+                            let addr = SocketAddr::new(ip, info.get_port());
+                            let metadata = decode_metadata(|key| info.get_property_val_str(key));
+                            let peer = Peer::new(info.get_fullname().to_string(), addr, unix_now())
+                                .with_metadata(metadata);
+                            registry_clone.upsert(info.get_fullname(), peer);
                         }
                     }
+                    // mDNS re-announces only on change, so peers stay until
+                    // they say goodbye or their records expire in the daemon.
                     ServiceEvent::ServiceRemoved(_, fullname) => {
-                        if let Ok(mut guard) = registry_clone.write() {
-                            guard.remove(&fullname);
-                        }
+                        registry_clone.remove(&fullname);
                     }
                     _ => {}
                 }
             }
         });
 
-        Ok(Self { daemon, registry })
+        let daemon_clone = daemon.clone();
+        let interfaces_clone = Arc::clone(&interfaces);
+        let advertised_clone = Arc::clone(&advertised);
+        let filter_clone = filter.clone();
+        thread::spawn(move || {
+            loop {
+                thread::sleep(INTERFACE_POLL_INTERVAL);
+                let current = local_interfaces();
+                let changed = match interfaces_clone.write() {
+                    Ok(mut previous) if *previous != current => {
+                        *previous = current.clone();
+                        true
+                    }
+                    _ => false,
+                };
+                if !changed {
+                    continue;
+                }
+
+                info!("Network interfaces changed, re-announcing LanShare service");
+                if let Err(e) = select_interfaces(&daemon_clone, &filter_clone, &current) {
+                    warn!(error = ?e, "Failed to update mDNS interfaces");
+                }
+                let peer = advertised_clone.read().ok().and_then(|peer| peer.clone());
+                if let Some(peer) = peer {
+                    let registered =
+                        service_info(&peer, &filter_clone, &current).and_then(|info| {
+                            daemon_clone
+                                .register(info)
+                                .map_err(|e| DomainError::IoError(e.to_string()))
+                        });
+                    if let Err(e) = registered {
+                        warn!(error = ?e, "Failed to re-register mDNS service");
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            daemon,
+            registry,
+            filter,
+            interfaces,
+            advertised,
+        })
     }
 }
 
-impl DiscoveryPort for MdnsDiscoveryAdapter {
-    fn discover_peers(&self) -> Result<Vec<Peer>, DomainError> {
-        let mut guard = self
-            .registry
-            .write()
-            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+fn select_interfaces(
+    daemon: &ServiceDaemon,
+    filter: &InterfaceFilter,
+    interfaces: &[LocalInterface],
+) -> Result<(), DomainError> {
+    if filter.is_unrestricted() {
+        return Ok(());
+    }
+    let allowed: Vec<IfKind> = interfaces
+        .iter()
+        .filter(|interface| filter.allows_local(interface))
+        .map(|interface| IfKind::Addr(interface.ip))
+        .collect();
+    daemon
+        .disable_interface(IfKind::All)
+        .and_then(|_| daemon.enable_interface(allowed))
+        .map_err(|e| DomainError::IoError(e.to_string()))
+}
 
-        guard.retain(|_, peer| now - peer.last_seen < 60);
+/// Advertises the peer's own address unless it is a wildcard or interfaces
+/// are restricted, in which case every allowed local address is announced.
+fn service_info(
+    peer: &Peer,
+    filter: &InterfaceFilter,
+    interfaces: &[LocalInterface],
+) -> Result<ServiceInfo, DomainError> {
+    let instance_name = peer.name.clone();
+    let host_name = format!("{}.local.", instance_name);
+    let mut addresses: Vec<IpAddr> = Vec::new();
+    if peer.address.ip().is_unspecified() || !filter.is_unrestricted() {
+        addresses = interfaces
+            .iter()
+            .filter(|interface| filter.allows_local(interface))
+            .map(|interface| interface.ip)
+            .collect();
+    }
+    if addresses.is_empty() {
+        addresses.push(peer.address.ip());
+    }
+    let properties = encode_metadata(&peer.metadata);
+
+    ServiceInfo::new(
+        SERVICE_NAME,
+        &instance_name,
+        &host_name,
+        &addresses[..],
+        peer.address.port(),
+        properties,
+    )
+    .map_err(|e| DomainError::IoError(e.to_string()))
+}
 
-        Ok(guard.values().cloned().collect())
+impl DiscoveryPort for MdnsDiscoveryAdapter {
+    fn discover_peers(&self) -> Result<Vec<Peer>, DomainError> {
+        self.registry.snapshot()
     }
 
     fn broadcast_presence(&self, peer: &Peer) -> Result<(), DomainError> {
-        let instance_name = peer.name.clone();
-        let host_name = format!("{}.local.", instance_name);
-        let ip = peer.address.ip().to_string();
-        let port = peer.address.port();
-        let properties = [("version", "1.0")];
-
-        let service_info = ServiceInfo::new(
-            SERVICE_NAME,
-            &instance_name,
-            &host_name,
-            ip,
-            port,
-            &properties[..],
-        )
-        .map_err(|e| DomainError::IoError(e.to_string()))?;
+        self.registry.set_own_device_id(&peer.metadata.device_id);
+        let interfaces = self
+            .interfaces
+            .read()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?
+            .clone();
+        let service_info = service_info(peer, &self.filter, &interfaces)?;
 
         self.daemon
             .register(service_info)
             .map_err(|e| DomainError::IoError(e.to_string()))?;
 
+        if let Ok(mut advertised) = self.advertised.write() {
+            *advertised = Some(peer.clone());
+        }
         Ok(())
     }
+
+    fn withdraw_presence(&self) -> Result<(), DomainError> {
+        let advertised = self
+            .advertised
+            .write()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?
+            .take();
+        let Some(peer) = advertised else {
+            return Ok(());
+        };
+
+        let fullname = format!("{}.{}", peer.name, SERVICE_NAME);
+        let status = self
+            .daemon
+            .unregister(&fullname)
+            .map_err(|e| DomainError::IoError(e.to_string()))?;
+        // Wait for the goodbye packets to go out before the caller exits.
+        status
+            .recv_timeout(UNREGISTER_TIMEOUT)
+            .map_err(|e| DomainError::IoError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<Receiver<PeerEvent>, DomainError> {
+        self.registry.subscribe()
+    }
 }
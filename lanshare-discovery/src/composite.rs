@@ -0,0 +1,268 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex, mpsc::Receiver},
+    thread,
+};
+
+use lanshare_domain::{
+    error::DomainError,
+    models::{Peer, PeerEvent},
+    ports::DiscoveryPort,
+};
+
+use crate::registry::PeerRegistry;
+
+/// Merges several discovery sources into one view, de-duplicating peers
+/// that are seen by more than one source.
+pub struct CompositeDiscoveryAdapter {
+    sources: Vec<Arc<dyn DiscoveryPort>>,
+    registry: Arc<PeerRegistry>,
+}
+
+impl CompositeDiscoveryAdapter {
+    pub fn new(sources: Vec<Arc<dyn DiscoveryPort>>) -> Result<Self, DomainError> {
+        let registry = Arc::new(PeerRegistry::new());
+        let views = Arc::new(Mutex::new(SourceViews::default()));
+
+        for (index, source) in sources.iter().enumerate() {
+            let events = source.subscribe()?;
+            for peer in source.discover_peers()? {
+                merge_event(&registry, &views, index, PeerEvent::Added(peer));
+            }
+
+            let registry = Arc::clone(&registry);
+            let views = Arc::clone(&views);
+            thread::spawn(move || forward_events(events, &registry, &views, index));
+        }
+
+        Ok(Self { sources, registry })
+    }
+
+    /// Fails only when every source fails, so one broken transport does not
+    /// hide us from the others.
+    fn for_each_source<F>(&self, f: F) -> Result<(), DomainError>
+    where
+        F: Fn(&dyn DiscoveryPort) -> Result<(), DomainError>,
+    {
+        let mut last_error = None;
+        let mut any_succeeded = false;
+        for source in &self.sources {
+            match f(source.as_ref()) {
+                Ok(()) => any_succeeded = true,
+                Err(e) => last_error = Some(e),
+            }
+        }
+        match last_error {
+            Some(e) if !any_succeeded => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn dedup_key(peer: &Peer) -> String {
+    if peer.metadata.device_id.is_empty() {
+        peer.address.to_string()
+    } else {
+        peer.metadata.device_id.clone()
+    }
+}
+
+/// What each source currently reports, by source index and `dedup_key`.
+#[derive(Default)]
+struct SourceViews {
+    peers: HashMap<(usize, String), Peer>,
+    merged_keys: HashSet<String>,
+}
+
+impl SourceViews {
+    /// Groups the views that describe the same device. A view without a
+    /// device ID, such as a peer book entry, joins the device announced at
+    /// its address, lending it its alias and tags.
+    fn merged(&self) -> HashMap<String, Peer> {
+        let mut views: Vec<(&(usize, String), &Peer)> = self.peers.iter().collect();
+        views.sort_by_key(|(key, _)| *key);
+
+        let device_at: HashMap<SocketAddr, String> = views
+            .iter()
+            .filter(|(_, peer)| !peer.metadata.device_id.is_empty())
+            .map(|(_, peer)| (peer.address, peer.metadata.device_id.clone()))
+            .collect();
+
+        let mut merged: HashMap<String, Peer> = HashMap::new();
+        for ((_, key), peer) in views {
+            let key = match device_at.get(&peer.address) {
+                Some(device_id) if peer.metadata.device_id.is_empty() => device_id.clone(),
+                _ => key.clone(),
+            };
+            match merged.get_mut(&key) {
+                Some(existing) => merge_into(existing, peer),
+                None => {
+                    merged.insert(key, peer.clone());
+                }
+            }
+        }
+        merged
+    }
+}
+
+fn merge_into(existing: &mut Peer, peer: &Peer) {
+    let fresher = existing.metadata.device_id.is_empty() || peer.last_seen > existing.last_seen;
+    if !peer.metadata.device_id.is_empty() && fresher {
+        existing.name = peer.name.clone();
+        existing.address = peer.address;
+        existing.metadata = peer.metadata.clone();
+    }
+    if existing.alias.is_none() {
+        existing.alias = peer.alias.clone();
+    }
+    if existing.tags.is_empty() {
+        existing.tags = peer.tags.clone();
+    }
+    existing.last_seen = existing.last_seen.max(peer.last_seen);
+}
+
+fn forward_events(
+    events: Receiver<PeerEvent>,
+    registry: &PeerRegistry,
+    views: &Mutex<SourceViews>,
+    index: usize,
+) {
+    for event in events {
+        merge_event(registry, views, index, event);
+    }
+}
+
+fn merge_event(
+    registry: &PeerRegistry,
+    views: &Mutex<SourceViews>,
+    index: usize,
+    event: PeerEvent,
+) {
+    let Ok(mut views) = views.lock() else {
+        return;
+    };
+    match event {
+        PeerEvent::Added(peer) | PeerEvent::Updated(peer) => {
+            views.peers.insert((index, dedup_key(&peer)), peer);
+        }
+        PeerEvent::Removed(peer) => {
+            views.peers.remove(&(index, dedup_key(&peer)));
+        }
+    }
+
+    let merged = views.merged();
+    for key in views.merged_keys.iter() {
+        if !merged.contains_key(key) {
+            registry.remove(key);
+        }
+    }
+    views.merged_keys = merged.keys().cloned().collect();
+    for (key, peer) in merged {
+        registry.upsert(&key, peer);
+    }
+}
+
+impl DiscoveryPort for CompositeDiscoveryAdapter {
+    fn discover_peers(&self) -> Result<Vec<Peer>, DomainError> {
+        self.registry.snapshot()
+    }
+
+    fn broadcast_presence(&self, peer: &Peer) -> Result<(), DomainError> {
+        self.registry.set_own_device_id(&peer.metadata.device_id);
+        self.for_each_source(|source| source.broadcast_presence(peer))
+    }
+
+    fn withdraw_presence(&self) -> Result<(), DomainError> {
+        self.for_each_source(|source| source.withdraw_presence())
+    }
+
+    fn subscribe(&self) -> Result<Receiver<PeerEvent>, DomainError> {
+        self.registry.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeSource {
+        registry: PeerRegistry,
+    }
+
+    impl DiscoveryPort for FakeSource {
+        fn discover_peers(&self) -> Result<Vec<Peer>, DomainError> {
+            self.registry.snapshot()
+        }
+        fn broadcast_presence(&self, _peer: &Peer) -> Result<(), DomainError> {
+            Ok(())
+        }
+        fn withdraw_presence(&self) -> Result<(), DomainError> {
+            Ok(())
+        }
+        fn subscribe(&self) -> Result<Receiver<PeerEvent>, DomainError> {
+            self.registry.subscribe()
+        }
+    }
+
+    fn peer(device_id: &str, address: &str) -> Peer {
+        let mut peer = Peer::new(device_id.to_string(), address.parse().unwrap(), 0);
+        peer.metadata.device_id = device_id.to_string();
+        peer
+    }
+
+    #[test]
+    fn peers_seen_by_several_sources_are_merged() {
+        let mdns = Arc::new(FakeSource {
+            registry: PeerRegistry::new(),
+        });
+        let udp = Arc::new(FakeSource {
+            registry: PeerRegistry::new(),
+        });
+        mdns.registry.upsert("a", peer("a", "10.0.0.1:8080"));
+        udp.registry.upsert("a", peer("a", "10.0.0.1:8080"));
+        udp.registry.upsert("b", peer("b", "10.0.0.2:8080"));
+
+        let composite = CompositeDiscoveryAdapter::new(vec![mdns.clone(), udp.clone()]).unwrap();
+        let events = composite.subscribe().unwrap();
+        assert_eq!(composite.discover_peers().unwrap().len(), 2);
+
+        mdns.registry.remove("a");
+        udp.registry.remove("a");
+        match events.recv().unwrap() {
+            PeerEvent::Removed(removed) => assert_eq!(removed.metadata.device_id, "a"),
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(composite.discover_peers().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn peer_book_entries_join_the_device_at_their_address() {
+        let book = Arc::new(FakeSource {
+            registry: PeerRegistry::new(),
+        });
+        let mdns = Arc::new(FakeSource {
+            registry: PeerRegistry::new(),
+        });
+        let mut entry = Peer::new("10.0.0.1:8080".into(), "10.0.0.1:8080".parse().unwrap(), 0);
+        entry.alias = Some("nas".to_string());
+        book.registry.upsert("10.0.0.1:8080", entry);
+
+        let composite = CompositeDiscoveryAdapter::new(vec![book.clone(), mdns.clone()]).unwrap();
+        let events = composite.subscribe().unwrap();
+        mdns.registry.upsert("a", peer("a", "10.0.0.1:8080"));
+        while !matches!(events.recv().unwrap(), PeerEvent::Added(_)) {}
+
+        let peers = composite.discover_peers().unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].metadata.device_id, "a");
+        assert_eq!(peers[0].alias.as_deref(), Some("nas"));
+
+        mdns.registry.remove("a");
+        while !matches!(events.recv().unwrap(), PeerEvent::Added(_)) {}
+        let peers = composite.discover_peers().unwrap();
+        assert_eq!(peers.len(), 1);
+        assert!(peers[0].metadata.device_id.is_empty());
+        assert_eq!(peers[0].alias.as_deref(), Some("nas"));
+    }
+}
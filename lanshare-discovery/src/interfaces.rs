@@ -0,0 +1,221 @@
+use std::{net::IpAddr, str::FromStr};
+
+use if_addrs::{IfAddr, get_if_addrs};
+use lanshare_domain::error::DomainError;
+
+/// An interface name (with an optional trailing `*` wildcard, e.g. `veth*`)
+/// or a subnet in CIDR notation (e.g. `172.17.0.0/16`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterfaceRule {
+    Name(String),
+    Subnet { network: IpAddr, prefix_len: u8 },
+}
+
+impl InterfaceRule {
+    fn matches(&self, name: Option<&str>, ip: IpAddr) -> bool {
+        match self {
+            InterfaceRule::Name(pattern) => {
+                name.is_some_and(|name| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == pattern,
+                })
+            }
+            InterfaceRule::Subnet {
+                network,
+                prefix_len,
+            } => in_subnet(ip, *network, *prefix_len),
+        }
+    }
+}
+
+impl FromStr for InterfaceRule {
+    type Err = DomainError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let Some((network, prefix_len)) = value.split_once('/') else {
+            if value.is_empty() {
+                return Err(DomainError::ParseError("Empty interface name".to_string()));
+            }
+            return Ok(InterfaceRule::Name(value.to_string()));
+        };
+
+        let network: IpAddr = network
+            .parse()
+            .map_err(|_| DomainError::ParseError(format!("Invalid subnet '{}'", value)))?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len
+            .parse::<u8>()
+            .ok()
+            .filter(|len| *len <= max_len)
+            .ok_or_else(|| DomainError::ParseError(format!("Invalid subnet '{}'", value)))?;
+        Ok(InterfaceRule::Subnet {
+            network,
+            prefix_len,
+        })
+    }
+}
+
+/// Restricts which local interfaces discovery browses and advertises on,
+/// and which remote addresses are accepted as peers. Exclusions win over
+/// inclusions; an empty include list allows everything not excluded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InterfaceFilter {
+    pub include: Vec<InterfaceRule>,
+    pub exclude: Vec<InterfaceRule>,
+}
+
+impl InterfaceFilter {
+    pub fn parse(include: &[String], exclude: &[String]) -> Result<Self, DomainError> {
+        Ok(Self {
+            include: include
+                .iter()
+                .map(|rule| rule.parse())
+                .collect::<Result<_, _>>()?,
+            exclude: exclude
+                .iter()
+                .map(|rule| rule.parse())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn allows_local(&self, interface: &LocalInterface) -> bool {
+        self.allows(Some(&interface.name), interface.ip)
+    }
+
+    /// Checks a peer address against the local interface whose subnet it is
+    /// on. Routed peers (on no local subnet) are only judged by subnet rules.
+    pub fn allows_remote(&self, ip: IpAddr, interfaces: &[LocalInterface]) -> bool {
+        let name = interfaces
+            .iter()
+            .find(|interface| interface.contains(ip))
+            .map(|interface| interface.name.as_str());
+        if name.is_none()
+            && !self
+                .include
+                .iter()
+                .any(|rule| matches!(rule, InterfaceRule::Subnet { .. }))
+        {
+            return !self.exclude.iter().any(|rule| rule.matches(None, ip));
+        }
+        self.allows(name, ip)
+    }
+
+    fn allows(&self, name: Option<&str>, ip: IpAddr) -> bool {
+        if self.exclude.iter().any(|rule| rule.matches(name, ip)) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(|rule| rule.matches(name, ip))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LocalInterface {
+    pub name: String,
+    pub ip: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl LocalInterface {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        in_subnet(ip, self.ip, self.prefix_len)
+    }
+}
+
+/// Non-loopback interfaces that are currently up, sorted so that snapshots
+/// can be compared to detect address changes.
+pub fn local_interfaces() -> Vec<LocalInterface> {
+    let mut interfaces: Vec<LocalInterface> = get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|interface| !interface.is_loopback() && interface.is_oper_up())
+        .map(|interface| {
+            let prefix_len = match &interface.addr {
+                IfAddr::V4(addr) => addr.prefixlen,
+                IfAddr::V6(addr) => addr.prefixlen,
+            };
+            LocalInterface {
+                ip: interface.ip(),
+                name: interface.name,
+                prefix_len,
+            }
+        })
+        .collect();
+    interfaces.sort();
+    interfaces
+}
+
+fn in_subnet(ip: IpAddr, network: IpAddr, prefix_len: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX
+                .checked_shl(32 - u32::from(prefix_len))
+                .unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX
+                .checked_shl(128 - u32::from(prefix_len))
+                .unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interface(name: &str, ip: &str, prefix_len: u8) -> LocalInterface {
+        LocalInterface {
+            name: name.to_string(),
+            ip: ip.parse().unwrap(),
+            prefix_len,
+        }
+    }
+
+    #[test]
+    fn exclusions_cover_names_wildcards_and_subnets() {
+        let filter = InterfaceFilter::parse(
+            &[],
+            &[
+                "docker0".to_string(),
+                "tun*".to_string(),
+                "10.8.0.0/16".to_string(),
+            ],
+        )
+        .unwrap();
+        let interfaces = [
+            interface("eth0", "192.168.1.10", 24),
+            interface("docker0", "172.17.0.1", 16),
+            interface("tun0", "10.9.0.2", 24),
+        ];
+
+        assert!(filter.allows_local(&interfaces[0]));
+        assert!(!filter.allows_local(&interfaces[1]));
+        assert!(!filter.allows_local(&interfaces[2]));
+        assert!(filter.allows_remote("192.168.1.20".parse().unwrap(), &interfaces));
+        assert!(!filter.allows_remote("172.17.0.5".parse().unwrap(), &interfaces));
+        assert!(!filter.allows_remote("10.8.3.4".parse().unwrap(), &interfaces));
+        assert!(filter.allows_remote("10.20.0.1".parse().unwrap(), &interfaces));
+    }
+
+    #[test]
+    fn inclusions_restrict_to_listed_interfaces() {
+        let filter = InterfaceFilter::parse(&["eth0".to_string()], &[]).unwrap();
+        let interfaces = [
+            interface("eth0", "192.168.1.10", 24),
+            interface("wlan0", "192.168.50.3", 24),
+        ];
+
+        assert!(filter.allows_local(&interfaces[0]));
+        assert!(!filter.allows_local(&interfaces[1]));
+        assert!(!filter.allows_remote("192.168.50.9".parse().unwrap(), &interfaces));
+        assert!("10.0.0.0/33".parse::<InterfaceRule>().is_err());
+    }
+}
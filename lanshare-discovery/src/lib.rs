@@ -1 +1,7 @@
-pub mod adapter;
\ No newline at end of file
+pub mod adapter;
+pub mod composite;
+pub mod interfaces;
+pub mod peer_book;
+mod registry;
+mod txt;
+pub mod udp;
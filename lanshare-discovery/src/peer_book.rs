@@ -0,0 +1,194 @@
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Mutex, mpsc::Receiver},
+};
+
+use lanshare_domain::{
+    error::DomainError,
+    models::{Peer, PeerEvent},
+    ports::DiscoveryPort,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::registry::{PeerRegistry, unix_now};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerBookEntry {
+    pub address: SocketAddr,
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub added_at: u64,
+}
+
+impl PeerBookEntry {
+    fn to_peer(&self) -> Peer {
+        let mut peer = Peer::new(self.address.to_string(), self.address, unix_now());
+        peer.alias = self.alias.clone();
+        peer.tags = self.tags.clone();
+        peer
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        self.alias.as_deref() == Some(query) || self.address.to_string() == query
+    }
+}
+
+/// Statically configured peers persisted in a JSON file, for networks where
+/// multicast discovery cannot reach them.
+pub struct PeerBookDiscoveryAdapter {
+    path: PathBuf,
+    entries: Mutex<Vec<PeerBookEntry>>,
+    registry: PeerRegistry,
+}
+
+impl PeerBookDiscoveryAdapter {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, DomainError> {
+        let path = path.as_ref().to_path_buf();
+        let entries: Vec<PeerBookEntry> = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+
+        let registry = PeerRegistry::new();
+        for entry in &entries {
+            registry.upsert(&entry.address.to_string(), entry.to_peer());
+        }
+
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+            registry,
+        })
+    }
+
+    pub fn entries(&self) -> Result<Vec<PeerBookEntry>, DomainError> {
+        Ok(self.lock()?.clone())
+    }
+
+    pub fn add(
+        &self,
+        address: SocketAddr,
+        alias: Option<String>,
+        tags: Vec<String>,
+    ) -> Result<Peer, DomainError> {
+        let mut entries = self.lock()?;
+        if let Some(alias) = &alias {
+            ensure_alias_free(&entries, alias, &address)?;
+        }
+        entries.retain(|entry| entry.address != address);
+        let entry = PeerBookEntry {
+            address,
+            alias,
+            tags,
+            added_at: unix_now(),
+        };
+        entries.push(entry.clone());
+        self.save(&entries)?;
+
+        let peer = entry.to_peer();
+        self.registry.upsert(&address.to_string(), peer.clone());
+        Ok(peer)
+    }
+
+    pub fn remove(&self, query: &str) -> Result<(), DomainError> {
+        let mut entries = self.lock()?;
+        let position = entries
+            .iter()
+            .position(|entry| entry.matches(query))
+            .ok_or_else(|| DomainError::NotFound(format!("No peer book entry for {}", query)))?;
+        let removed = entries.remove(position);
+        self.save(&entries)?;
+        self.registry.remove(&removed.address.to_string());
+        Ok(())
+    }
+
+    pub fn set_alias(&self, query: &str, alias: Option<String>) -> Result<Peer, DomainError> {
+        self.modify(query, |entries, index| {
+            if let Some(alias) = &alias {
+                ensure_alias_free(entries, alias, &entries[index].address)?;
+            }
+            entries[index].alias = alias;
+            Ok(())
+        })
+    }
+
+    pub fn set_tags(&self, query: &str, tags: Vec<String>) -> Result<Peer, DomainError> {
+        self.modify(query, |entries, index| {
+            entries[index].tags = tags;
+            Ok(())
+        })
+    }
+
+    fn modify<F>(&self, query: &str, f: F) -> Result<Peer, DomainError>
+    where
+        F: FnOnce(&mut Vec<PeerBookEntry>, usize) -> Result<(), DomainError>,
+    {
+        let mut entries = self.lock()?;
+        let index = entries
+            .iter()
+            .position(|entry| entry.matches(query))
+            .ok_or_else(|| DomainError::NotFound(format!("No peer book entry for {}", query)))?;
+        f(&mut entries, index)?;
+        self.save(&entries)?;
+
+        let peer = entries[index].to_peer();
+        self.registry
+            .upsert(&entries[index].address.to_string(), peer.clone());
+        Ok(peer)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Vec<PeerBookEntry>>, DomainError> {
+        self.entries
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))
+    }
+
+    fn save(&self, entries: &[PeerBookEntry]) -> Result<(), DomainError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(entries)?)?;
+        fs::rename(tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+fn ensure_alias_free(
+    entries: &[PeerBookEntry],
+    alias: &str,
+    address: &SocketAddr,
+) -> Result<(), DomainError> {
+    let taken = entries
+        .iter()
+        .any(|entry| entry.alias.as_deref() == Some(alias) && &entry.address != address);
+    if taken {
+        return Err(DomainError::Conflict(format!(
+            "Alias '{}' is already used by another peer",
+            alias
+        )));
+    }
+    Ok(())
+}
+
+impl DiscoveryPort for PeerBookDiscoveryAdapter {
+    fn discover_peers(&self) -> Result<Vec<Peer>, DomainError> {
+        Ok(self.lock()?.iter().map(PeerBookEntry::to_peer).collect())
+    }
+
+    fn broadcast_presence(&self, _peer: &Peer) -> Result<(), DomainError> {
+        Ok(())
+    }
+
+    fn withdraw_presence(&self) -> Result<(), DomainError> {
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<Receiver<PeerEvent>, DomainError> {
+        self.registry.subscribe()
+    }
+}
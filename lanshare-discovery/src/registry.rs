@@ -0,0 +1,188 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex, RwLock,
+        mpsc::{self, Receiver, Sender},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use lanshare_domain::{
+    error::DomainError,
+    models::{Peer, PeerEvent},
+};
+use tracing::{info, trace};
+
+#[derive(Default)]
+pub struct PeerRegistry {
+    peers: RwLock<HashMap<String, Peer>>,
+    subscribers: Mutex<Vec<Sender<PeerEvent>>>,
+    own_device_id: RwLock<Option<String>>,
+}
+
+impl PeerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ignores announcements carrying our own device ID from now on and
+    /// drops any that were already recorded.
+    pub fn set_own_device_id(&self, device_id: &str) {
+        if device_id.is_empty() {
+            return;
+        }
+        if let Ok(mut guard) = self.own_device_id.write() {
+            *guard = Some(device_id.to_string());
+        }
+        let own_keys: Vec<String> = match self.peers.read() {
+            Ok(guard) => guard
+                .iter()
+                .filter(|(_, peer)| peer.metadata.device_id == device_id)
+                .map(|(key, _)| key.clone())
+                .collect(),
+            Err(_) => return,
+        };
+        for key in own_keys {
+            self.remove(&key);
+        }
+    }
+
+    fn is_own(&self, peer: &Peer) -> bool {
+        self.own_device_id.read().is_ok_and(|guard| {
+            guard
+                .as_deref()
+                .is_some_and(|own| own == peer.metadata.device_id)
+        })
+    }
+
+    pub fn upsert(&self, key: &str, peer: Peer) {
+        if self.is_own(&peer) {
+            return;
+        }
+        let previous = match self.peers.write() {
+            Ok(mut guard) => guard.insert(key.to_string(), peer.clone()),
+            Err(_) => return,
+        };
+        let event = match previous {
+            Some(previous) if same_announcement(&previous, &peer) => return,
+            Some(_) => PeerEvent::Updated(peer),
+            None => PeerEvent::Added(peer),
+        };
+        self.publish(event);
+    }
+
+    pub fn remove(&self, key: &str) {
+        let removed = match self.peers.write() {
+            Ok(mut guard) => guard.remove(key),
+            Err(_) => return,
+        };
+        if let Some(peer) = removed {
+            self.publish(PeerEvent::Removed(peer));
+        }
+    }
+
+    pub fn expire(&self, ttl_secs: u64) {
+        let now = unix_now();
+        let expired: Vec<Peer> = match self.peers.write() {
+            Ok(mut guard) => {
+                let stale: Vec<String> = guard
+                    .iter()
+                    .filter(|(_, peer)| now.saturating_sub(peer.last_seen) >= ttl_secs)
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                stale.iter().filter_map(|key| guard.remove(key)).collect()
+            }
+            Err(_) => return,
+        };
+        for peer in expired {
+            self.publish(PeerEvent::Removed(peer));
+        }
+    }
+
+    pub fn snapshot(&self) -> Result<Vec<Peer>, DomainError> {
+        let guard = self
+            .peers
+            .read()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        Ok(guard.values().cloned().collect())
+    }
+
+    pub fn subscribe(&self) -> Result<Receiver<PeerEvent>, DomainError> {
+        let (tx, rx) = mpsc::channel();
+        let mut guard = self
+            .subscribers
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        guard.push(tx);
+        Ok(rx)
+    }
+
+    fn publish(&self, event: PeerEvent) {
+        match &event {
+            PeerEvent::Added(peer) => {
+                info!(peer = %peer.display_name(), address = %peer.address, "Peer discovered")
+            }
+            PeerEvent::Updated(peer) => {
+                trace!(peer = %peer.display_name(), address = %peer.address, "Peer updated")
+            }
+            PeerEvent::Removed(peer) => {
+                info!(peer = %peer.display_name(), address = %peer.address, "Peer went away")
+            }
+        }
+        if let Ok(mut guard) = self.subscribers.lock() {
+            guard.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+    }
+}
+
+/// Whether `peer` only repeats what `previous` said, apart from when.
+fn same_announcement(previous: &Peer, peer: &Peer) -> bool {
+    previous.name == peer.name
+        && previous.address == peer.address
+        && previous.metadata == peer.metadata
+        && previous.alias == peer.alias
+        && previous.tags == peer.tags
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use lanshare_domain::models::PeerStatus;
+
+    use super::*;
+
+    #[test]
+    fn repeated_beacons_publish_only_real_changes() {
+        let registry = PeerRegistry::new();
+        let events = registry.subscribe().unwrap();
+        let peer = Peer::new("desk".to_string(), "10.0.0.2:7000".parse().unwrap(), 1);
+
+        registry.upsert("desk", peer.clone());
+        registry.upsert(
+            "desk",
+            Peer {
+                last_seen: 2,
+                ..peer.clone()
+            },
+        );
+        let mut busy = Peer {
+            last_seen: 3,
+            ..peer
+        };
+        busy.metadata.status = PeerStatus::Busy;
+        registry.upsert("desk", busy);
+
+        let events: Vec<PeerEvent> = events.try_iter().collect();
+        assert!(matches!(
+            events.as_slice(),
+            [PeerEvent::Added(_), PeerEvent::Updated(updated)]
+                if updated.metadata.status == PeerStatus::Busy
+        ));
+    }
+}
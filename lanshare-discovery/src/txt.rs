@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use lanshare_domain::models::{Capability, PeerMetadata, PeerStatus};
+
+const KEY_DEVICE_ID: &str = "id";
+const KEY_DISPLAY_NAME: &str = "name";
+const KEY_PLATFORM: &str = "os";
+const KEY_PROTOCOL_VERSION: &str = "proto";
+const KEY_CAPABILITIES: &str = "caps";
+const KEY_FINGERPRINT: &str = "fp";
+const KEY_STATUS: &str = "status";
+
+pub fn encode_metadata(metadata: &PeerMetadata) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    properties.insert(KEY_DEVICE_ID.to_string(), metadata.device_id.clone());
+    properties.insert(KEY_DISPLAY_NAME.to_string(), metadata.display_name.clone());
+    properties.insert(KEY_PLATFORM.to_string(), metadata.platform.clone());
+    properties.insert(
+        KEY_PROTOCOL_VERSION.to_string(),
+        metadata.protocol_version.to_string(),
+    );
+    let capabilities: Vec<&str> = metadata.capabilities.iter().map(|c| c.as_str()).collect();
+    properties.insert(KEY_CAPABILITIES.to_string(), capabilities.join(","));
+    if let Some(fingerprint) = &metadata.fingerprint {
+        properties.insert(KEY_FINGERPRINT.to_string(), fingerprint.clone());
+    }
+    properties.insert(KEY_STATUS.to_string(), metadata.status.as_str().to_string());
+    properties
+}
+
+pub fn decode_metadata<'a, F: Fn(&str) -> Option<&'a str>>(get: F) -> PeerMetadata {
+    let capabilities = get(KEY_CAPABILITIES)
+        .map(|caps| caps.split(',').filter_map(Capability::parse).collect())
+        .unwrap_or_default();
+
+    PeerMetadata {
+        device_id: get(KEY_DEVICE_ID).unwrap_or_default().to_string(),
+        display_name: get(KEY_DISPLAY_NAME).unwrap_or_default().to_string(),
+        platform: get(KEY_PLATFORM).unwrap_or_default().to_string(),
+        protocol_version: get(KEY_PROTOCOL_VERSION)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        capabilities,
+        fingerprint: get(KEY_FINGERPRINT)
+            .filter(|fp| !fp.is_empty())
+            .map(str::to_string),
+        status: get(KEY_STATUS)
+            .and_then(PeerStatus::parse)
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_round_trips_through_txt_properties() {
+        let metadata = PeerMetadata {
+            device_id: "5f0c".to_string(),
+            display_name: "build-server".to_string(),
+            platform: "linux".to_string(),
+            protocol_version: 1,
+            capabilities: vec![Capability::Compression, Capability::DirectoryTransfer],
+            fingerprint: Some("ab:cd".to_string()),
+            status: PeerStatus::Busy,
+        };
+
+        let properties = encode_metadata(&metadata);
+        let decoded = decode_metadata(|key| properties.get(key).map(String::as_str));
+        assert_eq!(decoded, metadata);
+    }
+
+    #[test]
+    fn unknown_capabilities_are_ignored() {
+        let decoded = decode_metadata(|key| match key {
+            "caps" => Some("quic,teleport"),
+            _ => None,
+        });
+        assert_eq!(decoded.capabilities, vec![Capability::Quic]);
+        assert_eq!(decoded.status, PeerStatus::Accepting);
+    }
+}
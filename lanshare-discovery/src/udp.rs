@@ -0,0 +1,402 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{Arc, RwLock, mpsc::Receiver},
+    thread,
+    time::Duration,
+};
+
+use hmac::{Hmac, Mac};
+use lanshare_domain::{
+    error::DomainError,
+    models::{Peer, PeerEvent, PeerMetadata},
+    ports::DiscoveryPort,
+};
+use sha2::Sha256;
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use tracing::{debug, warn};
+
+use crate::{
+    interfaces::{InterfaceFilter, LocalInterface, local_interfaces},
+    registry::{PeerRegistry, unix_now},
+    txt::{decode_metadata, encode_metadata},
+};
+
+const MAGIC: &[u8; 4] = b"LSB1";
+const TAG_LEN: usize = 32;
+const MAX_PACKET_LEN: usize = 1400;
+const MAX_CLOCK_SKEW_SECS: u64 = 300;
+/// Keeps a socket that keeps failing from spinning the receive loop.
+const RECEIVE_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone)]
+pub struct UdpBeaconConfig {
+    pub port: u16,
+    pub multicast_group: Ipv4Addr,
+    pub interval: Duration,
+    pub network_key: Vec<u8>,
+    pub interfaces: InterfaceFilter,
+}
+
+impl Default for UdpBeaconConfig {
+    fn default() -> Self {
+        Self {
+            port: 45454,
+            multicast_group: Ipv4Addr::new(239, 255, 76, 83),
+            interval: Duration::from_secs(5),
+            network_key: Vec::new(),
+            interfaces: InterfaceFilter::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Announcement {
+    instance_name: String,
+    port: u16,
+    timestamp: u64,
+    metadata: PeerMetadata,
+}
+
+pub struct UdpBeaconDiscoveryAdapter {
+    registry: Arc<PeerRegistry>,
+    announcement: Arc<RwLock<Option<Announcement>>>,
+}
+
+impl UdpBeaconDiscoveryAdapter {
+    /// Fails without a network key: a well-known one would let anyone on
+    /// the network forge beacons.
+    pub fn new(config: UdpBeaconConfig) -> Result<Self, DomainError> {
+        if config.network_key.is_empty() {
+            return Err(DomainError::IoError(
+                "UDP discovery needs a network key".into(),
+            ));
+        }
+        let registry = Arc::new(PeerRegistry::new());
+        let announcement: Arc<RwLock<Option<Announcement>>> = Arc::new(RwLock::new(None));
+        let interfaces = Arc::new(RwLock::new(local_interfaces()));
+
+        let listen_socket = bind_listen_socket(&config, &allowed_v4(&config, &local_interfaces()))?;
+        let send_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .and_then(|socket| {
+                socket.set_broadcast(true)?;
+                socket.set_multicast_ttl_v4(1)?;
+                Ok(socket)
+            })
+            .map_err(|e| DomainError::IoError(e.to_string()))?;
+
+        let registry_clone = Arc::clone(&registry);
+        let interfaces_clone = Arc::clone(&interfaces);
+        let filter = config.interfaces.clone();
+        let key = config.network_key.clone();
+        thread::spawn(move || {
+            let mut buffer = [0u8; MAX_PACKET_LEN];
+            loop {
+                let (len, source) = match listen_socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(e) => {
+                        warn!(error = %e, "UDP discovery receive error");
+                        thread::sleep(RECEIVE_ERROR_BACKOFF);
+                        continue;
+                    }
+                };
+                let Ok(received) = decode_announcement(&buffer[..len], &key) else {
+                    continue;
+                };
+                if unix_now().abs_diff(received.timestamp) > MAX_CLOCK_SKEW_SECS {
+                    continue;
+                }
+                let allowed = interfaces_clone
+                    .read()
+                    .is_ok_and(|current| filter.allows_remote(source.ip(), &current));
+                if !allowed {
+                    continue;
+                }
+
+                let address = SocketAddr::new(source.ip(), received.port);
+                let key = if received.metadata.device_id.is_empty() {
+                    address.to_string()
+                } else {
+                    received.metadata.device_id.clone()
+                };
+                let peer = Peer::new(received.instance_name, address, unix_now())
+                    .with_metadata(received.metadata);
+                registry_clone.upsert(&key, peer);
+            }
+        });
+
+        let registry_clone = Arc::clone(&registry);
+        let announcement_clone = Arc::clone(&announcement);
+        let interfaces_clone = Arc::clone(&interfaces);
+        thread::spawn(move || {
+            let ttl_secs = (config.interval * 3).as_secs().max(1);
+            loop {
+                let local = local_interfaces();
+                if let Ok(mut previous) = interfaces_clone.write() {
+                    *previous = local.clone();
+                }
+                let current = announcement_clone.read().ok().and_then(|a| a.clone());
+                if let Some(mut current) = current {
+                    current.timestamp = unix_now();
+                    let packet = encode_announcement(&current, &config.network_key);
+                    send_beacon(&send_socket, &packet, &config, &local);
+                }
+                registry_clone.expire(ttl_secs);
+                thread::sleep(config.interval);
+            }
+        });
+
+        Ok(Self {
+            registry,
+            announcement,
+        })
+    }
+}
+
+/// IPv4 interfaces the beacon may use, or `None` when unrestricted so the
+/// kernel's default routing applies.
+fn allowed_v4(
+    config: &UdpBeaconConfig,
+    interfaces: &[LocalInterface],
+) -> Option<Vec<LocalInterface>> {
+    if config.interfaces.is_unrestricted() {
+        return None;
+    }
+    Some(
+        interfaces
+            .iter()
+            .filter(|interface| interface.ip.is_ipv4() && config.interfaces.allows_local(interface))
+            .cloned()
+            .collect(),
+    )
+}
+
+fn bind_listen_socket(
+    config: &UdpBeaconConfig,
+    allowed: &Option<Vec<LocalInterface>>,
+) -> Result<UdpSocket, DomainError> {
+    let bind = || -> std::io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), config.port).into())?;
+        let socket: UdpSocket = socket.into();
+        match allowed {
+            None => socket.join_multicast_v4(&config.multicast_group, &Ipv4Addr::UNSPECIFIED)?,
+            Some(interfaces) => {
+                for interface in interfaces {
+                    if let IpAddr::V4(ip) = interface.ip {
+                        socket.join_multicast_v4(&config.multicast_group, &ip)?;
+                    }
+                }
+            }
+        }
+        Ok(socket)
+    };
+    bind().map_err(|e| DomainError::IoError(e.to_string()))
+}
+
+/// Sends one beacon to the limited broadcast address and the multicast
+/// group, or, with restricted interfaces, to each allowed interface's
+/// directed broadcast address and multicast via that interface.
+fn send_beacon(
+    socket: &UdpSocket,
+    packet: &[u8],
+    config: &UdpBeaconConfig,
+    interfaces: &[LocalInterface],
+) {
+    let send = |target: SocketAddr| {
+        if let Err(e) = socket.send_to(packet, target) {
+            debug!(%target, error = %e, "UDP beacon failed");
+        }
+    };
+    let multicast = SocketAddr::new(IpAddr::V4(config.multicast_group), config.port);
+
+    let Some(allowed) = allowed_v4(config, interfaces) else {
+        send(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::BROADCAST),
+            config.port,
+        ));
+        send(multicast);
+        return;
+    };
+    for interface in allowed {
+        let IpAddr::V4(ip) = interface.ip else {
+            continue;
+        };
+        let host_mask = u32::MAX
+            .checked_shr(u32::from(interface.prefix_len))
+            .unwrap_or(0);
+        let broadcast = Ipv4Addr::from(u32::from(ip) | host_mask);
+        send(SocketAddr::new(IpAddr::V4(broadcast), config.port));
+        if let Err(e) = SockRef::from(socket).set_multicast_if_v4(&ip) {
+            warn!(interface = %interface.name, error = %e, "Failed to select multicast interface");
+            continue;
+        }
+        send(multicast);
+    }
+}
+
+impl DiscoveryPort for UdpBeaconDiscoveryAdapter {
+    fn discover_peers(&self) -> Result<Vec<Peer>, DomainError> {
+        self.registry.snapshot()
+    }
+
+    fn broadcast_presence(&self, peer: &Peer) -> Result<(), DomainError> {
+        self.registry.set_own_device_id(&peer.metadata.device_id);
+        let mut guard = self
+            .announcement
+            .write()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        *guard = Some(Announcement {
+            instance_name: peer.name.clone(),
+            port: peer.address.port(),
+            timestamp: unix_now(),
+            metadata: peer.metadata.clone(),
+        });
+        Ok(())
+    }
+
+    fn withdraw_presence(&self) -> Result<(), DomainError> {
+        let mut guard = self
+            .announcement
+            .write()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        *guard = None;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<Receiver<PeerEvent>, DomainError> {
+        self.registry.subscribe()
+    }
+}
+
+fn encode_announcement(announcement: &Announcement, key: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(256);
+    packet.extend_from_slice(MAGIC);
+    packet.extend_from_slice(&announcement.port.to_le_bytes());
+    packet.extend_from_slice(&announcement.timestamp.to_le_bytes());
+    write_short_str(&mut packet, &announcement.instance_name);
+
+    let mut properties: Vec<(String, String)> = encode_metadata(&announcement.metadata)
+        .into_iter()
+        .collect();
+    properties.sort();
+    packet.push(properties.len() as u8);
+    for (name, value) in &properties {
+        write_short_str(&mut packet, name);
+        write_short_str(&mut packet, value);
+    }
+
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&packet);
+    packet.extend_from_slice(&mac.finalize().into_bytes());
+    packet
+}
+
+fn decode_announcement(packet: &[u8], key: &[u8]) -> Result<Announcement, DomainError> {
+    if packet.len() < MAGIC.len() + TAG_LEN || &packet[..MAGIC.len()] != MAGIC {
+        return Err(DomainError::ProtocolError);
+    }
+    let (body, tag) = packet.split_at(packet.len() - TAG_LEN);
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(body);
+    mac.verify_slice(tag)
+        .map_err(|_| DomainError::IntegrityError)?;
+
+    let mut cursor = &body[MAGIC.len()..];
+    let port = u16::from_le_bytes(take::<2>(&mut cursor)?);
+    let timestamp = u64::from_le_bytes(take::<8>(&mut cursor)?);
+    let instance_name = read_short_str(&mut cursor)?;
+
+    let [property_count] = take::<1>(&mut cursor)?;
+    let mut properties = Vec::with_capacity(property_count as usize);
+    for _ in 0..property_count {
+        let name = read_short_str(&mut cursor)?;
+        let value = read_short_str(&mut cursor)?;
+        properties.push((name, value));
+    }
+    let metadata = decode_metadata(|key| {
+        properties
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    });
+
+    Ok(Announcement {
+        instance_name,
+        port,
+        timestamp,
+        metadata,
+    })
+}
+
+fn write_short_str(packet: &mut Vec<u8>, value: &str) {
+    let mut end = value.len().min(u8::MAX as usize);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    packet.push(end as u8);
+    packet.extend_from_slice(&value.as_bytes()[..end]);
+}
+
+fn read_short_str(cursor: &mut &[u8]) -> Result<String, DomainError> {
+    let [len] = take::<1>(cursor)?;
+    let len = len as usize;
+    if cursor.len() < len {
+        return Err(DomainError::ProtocolError);
+    }
+    let (value, rest) = cursor.split_at(len);
+    *cursor = rest;
+    String::from_utf8(value.to_vec()).map_err(|e| DomainError::ParseError(e.to_string()))
+}
+
+fn take<const N: usize>(cursor: &mut &[u8]) -> Result<[u8; N], DomainError> {
+    if cursor.len() < N {
+        return Err(DomainError::ProtocolError);
+    }
+    let (value, rest) = cursor.split_at(N);
+    *cursor = rest;
+    let mut bytes = [0u8; N];
+    bytes.copy_from_slice(value);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use lanshare_domain::models::Capability;
+
+    use super::*;
+
+    fn sample() -> Announcement {
+        Announcement {
+            instance_name: "build-server".to_string(),
+            port: 8080,
+            timestamp: 1_700_000_000,
+            metadata: PeerMetadata {
+                device_id: "5f0c".to_string(),
+                display_name: "Build Server".to_string(),
+                platform: "linux".to_string(),
+                protocol_version: 1,
+                capabilities: vec![Capability::Compression],
+                fingerprint: None,
+                ..PeerMetadata::default()
+            },
+        }
+    }
+
+    #[test]
+    fn announcement_round_trips() {
+        let packet = encode_announcement(&sample(), b"secret");
+        assert_eq!(decode_announcement(&packet, b"secret").unwrap(), sample());
+    }
+
+    #[test]
+    fn tampered_or_foreign_announcements_are_rejected() {
+        let mut packet = encode_announcement(&sample(), b"secret");
+        assert!(decode_announcement(&packet, b"other").is_err());
+
+        packet[5] ^= 0xff;
+        assert!(decode_announcement(&packet, b"secret").is_err());
+    }
+}
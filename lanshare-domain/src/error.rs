@@ -4,9 +4,24 @@ use serde::{Deserialize, Serialize};
 pub enum DomainError {
     ProtocolError,
     IntegrityError,
+    TransferRejected,
+    DeadlineExceeded,
     NotFound(String),
+    AmbiguousPeer(Vec<String>),
+    Conflict(String),
     IoError(String),
     ParseError(String),
+    ConnectionFailed(String),
+    Interrupted,
+    /// Stopped on request; `resume` picks it up where the receiver left off.
+    Paused,
+    Cancelled,
+}
+
+impl DomainError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, DomainError::ConnectionFailed(_))
+    }
 }
 
 impl From<std::io::Error> for DomainError {
@@ -1,11 +1,18 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Peer {
     pub name: String,
     pub address: SocketAddr,
     pub last_seen: u64,
+    #[serde(default)]
+    pub metadata: PeerMetadata,
+    #[serde(default)]
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl Peer {
@@ -14,14 +21,108 @@ impl Peer {
             name,
             address,
             last_seen,
+            metadata: PeerMetadata::default(),
+            alias: None,
+            tags: Vec::new(),
         }
     }
+    pub fn with_metadata(mut self, metadata: PeerMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
     pub fn address(&self) -> &SocketAddr {
         &self.address
     }
+    pub fn display_name(&self) -> &str {
+        if let Some(alias) = &self.alias {
+            alias
+        } else if self.metadata.display_name.is_empty() {
+            &self.name
+        } else {
+            &self.metadata.display_name
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PeerMetadata {
+    pub device_id: String,
+    pub display_name: String,
+    pub platform: String,
+    pub protocol_version: u32,
+    pub capabilities: Vec<Capability>,
+    pub fingerprint: Option<String>,
+    pub status: PeerStatus,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Compression,
+    Encryption,
+    Quic,
+    DirectoryTransfer,
+    /// Receives data whose size is only known once it has ended.
+    Streaming,
+}
+
+impl Capability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Compression => "compression",
+            Capability::Encryption => "encryption",
+            Capability::Quic => "quic",
+            Capability::DirectoryTransfer => "directory_transfer",
+            Capability::Streaming => "streaming",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "compression" => Some(Capability::Compression),
+            "encryption" => Some(Capability::Encryption),
+            "quic" => Some(Capability::Quic),
+            "directory_transfer" => Some(Capability::DirectoryTransfer),
+            "streaming" => Some(Capability::Streaming),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerStatus {
+    #[default]
+    Accepting,
+    Busy,
+}
+
+impl PeerStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PeerStatus::Accepting => "accepting",
+            PeerStatus::Busy => "busy",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "accepting" => Some(PeerStatus::Accepting),
+            "busy" => Some(PeerStatus::Busy),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "event", content = "peer", rename_all = "snake_case")]
+pub enum PeerEvent {
+    Added(Peer),
+    Updated(Peer),
+    Removed(Peer),
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct FileManifest {
     pub file_id: String,
     pub name: String,
@@ -35,3 +136,164 @@ pub struct FileBlock {
     pub offset: u64,
     pub data: Vec<u8>,
 }
+
+/// Which way data flows relative to this daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Inbound => "inbound",
+            Direction::Outbound => "outbound",
+        }
+    }
+}
+
+/// How a transfer ended, as reported to metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferOutcome {
+    Completed,
+    Failed,
+    Rejected,
+    Interrupted,
+    IntegrityFailed,
+    Cancelled,
+}
+
+impl TransferOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferOutcome::Completed => "completed",
+            TransferOutcome::Failed => "failed",
+            TransferOutcome::Rejected => "rejected",
+            TransferOutcome::Interrupted => "interrupted",
+            TransferOutcome::IntegrityFailed => "integrity_failed",
+            TransferOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferState {
+    Queued,
+    InProgress,
+    Retrying,
+    Completed,
+    Failed,
+    /// Stopped by a daemon shutdown; resumes from the receiver's checkpoint.
+    Interrupted,
+    Paused,
+    Cancelled,
+}
+
+impl TransferState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferState::Queued => "queued",
+            TransferState::InProgress => "in_progress",
+            TransferState::Retrying => "retrying",
+            TransferState::Completed => "completed",
+            TransferState::Failed => "failed",
+            TransferState::Interrupted => "interrupted",
+            TransferState::Paused => "paused",
+            TransferState::Cancelled => "cancelled",
+        }
+    }
+
+    /// Whether the transfer is over and only kept for the history.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            TransferState::Completed
+                | TransferState::Failed
+                | TransferState::Interrupted
+                | TransferState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TransferAttempt {
+    pub number: u32,
+    pub started_at: u64,
+    pub address: SocketAddr,
+    pub resume_offset: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TransferStatus {
+    pub transfer_id: String,
+    pub peer: String,
+    pub file_path: String,
+    pub state: TransferState,
+    pub bytes_sent: u64,
+    /// Unknown for a stream until it has ended.
+    pub total_bytes: u64,
+    pub attempts: Vec<TransferAttempt>,
+    /// Read from a pipe or device, so it can be neither paused nor retried.
+    #[serde(default)]
+    pub streamed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OutboxEntry {
+    pub id: String,
+    pub peer: String,
+    pub file_path: String,
+    pub queued_at: u64,
+    pub expires_at: u64,
+}
+
+impl OutboxEntry {
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+/// How a queued file left the outbox.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum OutboxEvent {
+    Delivered {
+        entry: OutboxEntry,
+    },
+    /// Given up on for good, e.g. rejected by the peer or cancelled.
+    Dropped {
+        entry: OutboxEntry,
+        reason: String,
+    },
+    Expired {
+        entry: OutboxEntry,
+    },
+}
+
+/// A local file offered for download on the web drop page.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SharedFile {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+}
+
+/// An incoming transfer waiting for the user to accept or reject it.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IncomingOffer {
+    pub offer_id: String,
+    pub sender: SocketAddr,
+    /// Display name of the sender, if it is a known peer.
+    pub peer: Option<String>,
+    pub name: String,
+    /// Zero for a stream, whose size is only known once it has ended.
+    pub size: u64,
+    #[serde(default)]
+    pub streamed: bool,
+    pub offered_at: u64,
+    pub expires_at: u64,
+}
@@ -1,8 +1,12 @@
 use crate::{
     error::DomainError,
-    models::{FileBlock, FileManifest, Peer},
+    models::{Direction, FileBlock, FileManifest, OutboxEntry, Peer, PeerEvent, TransferOutcome},
+};
+use std::{
+    io::Read,
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
 };
-use std::sync::Arc;
 
 pub trait StoragePort: Send + Sync {
     fn create_file_manifest(&self, file_path: &str) -> Result<FileManifest, DomainError>;
@@ -17,6 +21,13 @@ pub trait StoragePort: Send + Sync {
     fn write_block(&self, block: &FileBlock) -> Result<(), DomainError>;
     fn complete_transfer(&self, file_id: &str) -> Result<(), DomainError>;
     fn cancel_transfer(&self, file_id: &str) -> Result<(), DomainError>;
+    /// Whether `file_path` is a pipe or device, which can only be read once,
+    /// front to back, and is therefore sent as a stream.
+    fn is_stream(&self, file_path: &str) -> Result<bool, DomainError>;
+    fn open_stream(&self, file_path: &str) -> Result<Box<dyn Read + Send>, DomainError>;
+    /// Sets the size and checksum a received stream is verified against by
+    /// `complete_transfer`; both are only known once the stream has ended.
+    fn seal_stream(&self, file_id: &str, size: u64, sha256: &[u8; 32]) -> Result<(), DomainError>;
 }
 
 impl<T: StoragePort + ?Sized> StoragePort for Arc<T> {
@@ -46,10 +57,20 @@ impl<T: StoragePort + ?Sized> StoragePort for Arc<T> {
     fn cancel_transfer(&self, file_id: &str) -> Result<(), DomainError> {
         (**self).cancel_transfer(file_id)
     }
+    fn is_stream(&self, file_path: &str) -> Result<bool, DomainError> {
+        (**self).is_stream(file_path)
+    }
+    fn open_stream(&self, file_path: &str) -> Result<Box<dyn Read + Send>, DomainError> {
+        (**self).open_stream(file_path)
+    }
+    fn seal_stream(&self, file_id: &str, size: u64, sha256: &[u8; 32]) -> Result<(), DomainError> {
+        (**self).seal_stream(file_id, size, sha256)
+    }
 }
 
 pub trait NetworkConnection {
     fn send(&mut self, data: &[u8]) -> Result<(), DomainError>;
+    fn receive(&mut self, buf: &mut [u8]) -> Result<usize, DomainError>;
 }
 
 pub trait NetworkPort: Send + Sync {
@@ -65,4 +86,93 @@ impl<T: NetworkPort + ?Sized> NetworkPort for Arc<T> {
 pub trait DiscoveryPort: Send + Sync {
     fn discover_peers(&self) -> Result<Vec<Peer>, DomainError>;
     fn broadcast_presence(&self, peer: &Peer) -> Result<(), DomainError>;
+    /// Stops advertising so peers drop us without waiting for a timeout.
+    fn withdraw_presence(&self) -> Result<(), DomainError>;
+    fn subscribe(&self) -> Result<Receiver<PeerEvent>, DomainError>;
+}
+
+impl<T: DiscoveryPort + ?Sized> DiscoveryPort for Arc<T> {
+    fn discover_peers(&self) -> Result<Vec<Peer>, DomainError> {
+        (**self).discover_peers()
+    }
+    fn broadcast_presence(&self, peer: &Peer) -> Result<(), DomainError> {
+        (**self).broadcast_presence(peer)
+    }
+    fn withdraw_presence(&self) -> Result<(), DomainError> {
+        (**self).withdraw_presence()
+    }
+    fn subscribe(&self) -> Result<Receiver<PeerEvent>, DomainError> {
+        (**self).subscribe()
+    }
+}
+
+pub trait OutboxPort: Send + Sync {
+    fn enqueue(&self, entry: &OutboxEntry) -> Result<(), DomainError>;
+    fn pending(&self) -> Result<Vec<OutboxEntry>, DomainError>;
+    fn remove(&self, entry_id: &str) -> Result<(), DomainError>;
+    /// Removes and returns the matching entries in one step, so concurrent
+    /// callers never take the same entry.
+    fn take(&self, matches: &dyn Fn(&OutboxEntry) -> bool)
+    -> Result<Vec<OutboxEntry>, DomainError>;
+}
+
+impl<T: OutboxPort + ?Sized> OutboxPort for Arc<T> {
+    fn enqueue(&self, entry: &OutboxEntry) -> Result<(), DomainError> {
+        (**self).enqueue(entry)
+    }
+    fn pending(&self) -> Result<Vec<OutboxEntry>, DomainError> {
+        (**self).pending()
+    }
+    fn remove(&self, entry_id: &str) -> Result<(), DomainError> {
+        (**self).remove(entry_id)
+    }
+    fn take(
+        &self,
+        matches: &dyn Fn(&OutboxEntry) -> bool,
+    ) -> Result<Vec<OutboxEntry>, DomainError> {
+        (**self).take(matches)
+    }
+}
+
+/// Sink for transfer and connection statistics. Recording must be cheap and
+/// never fail; adapters that export nothing can use [`NoopMetrics`].
+pub trait MetricsPort: Send + Sync {
+    fn record_bytes(&self, direction: Direction, bytes: u64);
+    fn record_chunk_latency(&self, direction: Direction, latency: Duration);
+    fn record_transfer(&self, direction: Direction, outcome: TransferOutcome);
+    fn connection_opened(&self, direction: Direction);
+    fn connection_closed(&self, direction: Direction);
+    fn set_discovered_peers(&self, count: usize);
+}
+
+impl<T: MetricsPort + ?Sized> MetricsPort for Arc<T> {
+    fn record_bytes(&self, direction: Direction, bytes: u64) {
+        (**self).record_bytes(direction, bytes)
+    }
+    fn record_chunk_latency(&self, direction: Direction, latency: Duration) {
+        (**self).record_chunk_latency(direction, latency)
+    }
+    fn record_transfer(&self, direction: Direction, outcome: TransferOutcome) {
+        (**self).record_transfer(direction, outcome)
+    }
+    fn connection_opened(&self, direction: Direction) {
+        (**self).connection_opened(direction)
+    }
+    fn connection_closed(&self, direction: Direction) {
+        (**self).connection_closed(direction)
+    }
+    fn set_discovered_peers(&self, count: usize) {
+        (**self).set_discovered_peers(count)
+    }
+}
+
+pub struct NoopMetrics;
+
+impl MetricsPort for NoopMetrics {
+    fn record_bytes(&self, _direction: Direction, _bytes: u64) {}
+    fn record_chunk_latency(&self, _direction: Direction, _latency: Duration) {}
+    fn record_transfer(&self, _direction: Direction, _outcome: TransferOutcome) {}
+    fn connection_opened(&self, _direction: Direction) {}
+    fn connection_closed(&self, _direction: Direction) {}
+    fn set_discovered_peers(&self, _count: usize) {}
 }
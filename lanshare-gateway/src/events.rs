@@ -0,0 +1,88 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::Duration,
+};
+
+use lanshare_ipc::{CommandHandler, DaemonEvent};
+use tiny_http::{Header, ReadWrite, Request, Response, StatusCode};
+use tracing::{Span, debug};
+use tungstenite::{Message, WebSocket, handshake::derive_accept_key, protocol::Role};
+
+use crate::server::{error_response, from_error, respond};
+
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Quiet streams are pinged this often so a vanished client is noticed.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Upgrades `GET /api/events` to a WebSocket carrying one JSON text message
+/// per daemon event, the same `{"kind", "data"}` objects IPC subscribers get.
+pub(crate) fn stream(
+    request: Request,
+    handler: Arc<dyn CommandHandler>,
+    shutdown: Arc<AtomicBool>,
+) {
+    let key = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+        .map(|header| header.value.to_string());
+    let Some(key) = key else {
+        let response = error_response(400, "INVALID_REQUEST", "Expected a WebSocket upgrade");
+        return respond(request, response);
+    };
+    let events = match handler.subscribe() {
+        Ok(events) => events,
+        Err(e) => return respond(request, from_error(&e)),
+    };
+
+    let accept = Header::from_bytes("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes()))
+        .expect("accept key is a valid header");
+    let response = Response::empty(StatusCode(101)).with_header(accept);
+    let stream = request.upgrade("websocket", response);
+    let span = Span::current();
+    thread::spawn(move || {
+        let _entered = span.enter();
+        forward(
+            WebSocket::from_raw_socket(stream, Role::Server, None),
+            events,
+            &shutdown,
+        );
+    });
+}
+
+fn forward(
+    mut socket: WebSocket<Box<dyn ReadWrite + Send>>,
+    events: Receiver<DaemonEvent>,
+    shutdown: &AtomicBool,
+) {
+    debug!("Event stream opened");
+    let mut idle = Duration::ZERO;
+    while !shutdown.load(Ordering::Relaxed) {
+        let message = match events.recv_timeout(EVENT_POLL_INTERVAL) {
+            Ok(event) => match serde_json::to_string(&event) {
+                Ok(text) => Message::text(text),
+                Err(_) => continue,
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                idle += EVENT_POLL_INTERVAL;
+                if idle < PING_INTERVAL {
+                    continue;
+                }
+                Message::Ping(Default::default())
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        idle = Duration::ZERO;
+        if let Err(e) = socket.send(message) {
+            debug!(error = %e, "Event stream closed");
+            return;
+        }
+    }
+    let _ = socket.close(None);
+    let _ = socket.flush();
+}
@@ -0,0 +1,6 @@
+mod events;
+mod server;
+mod token;
+
+pub use server::serve;
+pub use token::load_or_create_token;
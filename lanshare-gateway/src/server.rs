@@ -0,0 +1,201 @@
+use std::{
+    io::Read,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use lanshare_ipc::{CommandHandler, IPCError, SendFileParams};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{debug, info, info_span, warn};
+
+use crate::{events, token::token_matches};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+
+pub(crate) type JsonResponse = Response<std::io::Cursor<Vec<u8>>>;
+
+/// Serves the REST API and the `/api/events` WebSocket on `address` until
+/// `shutdown` is set. Every request must carry `token`.
+pub fn serve(
+    address: SocketAddr,
+    token: String,
+    handler: Arc<dyn CommandHandler>,
+    shutdown: Arc<AtomicBool>,
+) -> Result<JoinHandle<()>, IPCError> {
+    let server = Server::http(address)
+        .map_err(|e| IPCError::Other(format!("Failed to start HTTP gateway: {}", e)))?;
+    info!(address = %address, "Serving HTTP gateway");
+
+    Ok(thread::spawn(move || {
+        while !shutdown.load(Ordering::Relaxed) {
+            let request = match server.recv_timeout(POLL_INTERVAL) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(error = %e, "HTTP gateway failed");
+                    break;
+                }
+            };
+            let span =
+                info_span!("http_request", method = %request.method(), path = path_of(&request));
+            let _entered = span.enter();
+
+            if !is_authorized(&request, &token) {
+                warn!("Rejected HTTP request without a valid token");
+                respond(
+                    request,
+                    error_response(401, "UNAUTHORIZED", "Missing or wrong token"),
+                );
+                continue;
+            }
+            route(request, &handler, &shutdown);
+        }
+    }))
+}
+
+fn route(mut request: Request, handler: &Arc<dyn CommandHandler>, shutdown: &Arc<AtomicBool>) {
+    let path = path_of(&request).to_string();
+    let response = match (request.method(), path.as_str()) {
+        (Method::Get, "/api/events") => {
+            return events::stream(request, handler.clone(), shutdown.clone());
+        }
+        (Method::Get, "/api/peers") => to_response(200, handler.list_peers()),
+        (Method::Get, "/api/transfers") => to_response(200, handler.get_status()),
+        (Method::Post, "/api/transfers") => match read_json::<SendFileParams>(&mut request) {
+            Ok(params) => to_response(
+                202,
+                handler.send_file(&params.path, &params.peer, params.expires_in),
+            ),
+            Err(e) => from_error(&e),
+        },
+        (Method::Delete, path) if path.starts_with("/api/transfers/") => {
+            let transfer_id = &path["/api/transfers/".len()..];
+            match handler.cancel_transfer(transfer_id) {
+                Ok(()) => Response::from_data(Vec::new()).with_status_code(204),
+                Err(e) => from_error(&e),
+            }
+        }
+        (_, "/api/events" | "/api/peers" | "/api/transfers") => {
+            error_response(405, "METHOD_NOT_ALLOWED", "Method not allowed")
+        }
+        _ => error_response(404, "NOT_FOUND", "Not found"),
+    };
+    respond(request, response);
+}
+
+/// Browsers cannot set headers on WebSocket requests, so the token may also
+/// come as a `token` query parameter.
+fn is_authorized(request: &Request, token: &str) -> bool {
+    let from_header = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "));
+    let from_query = request.url().split_once('?').and_then(|(_, query)| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+    });
+    from_header
+        .or(from_query)
+        .is_some_and(|presented| token_matches(token, presented.trim()))
+}
+
+fn path_of(request: &Request) -> &str {
+    request.url().split('?').next().unwrap_or_default()
+}
+
+fn read_json<T: DeserializeOwned>(request: &mut Request) -> Result<T, IPCError> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_SIZE)
+        .read_to_end(&mut body)
+        .map_err(|e| IPCError::Other(format!("Failed to read request body: {}", e)))?;
+    serde_json::from_slice(&body).map_err(|e| IPCError::InvalidArgument(e.to_string()))
+}
+
+fn to_response<T: Serialize>(status: u16, outcome: Result<T, IPCError>) -> JsonResponse {
+    match outcome
+        .and_then(|value| serde_json::to_vec(&value).map_err(|e| IPCError::Other(e.to_string())))
+    {
+        Ok(body) => json_response(status, body),
+        Err(e) => from_error(&e),
+    }
+}
+
+pub(crate) fn from_error(error: &IPCError) -> JsonResponse {
+    let status = match error {
+        IPCError::InvalidJson | IPCError::InvalidRequest(_) | IPCError::InvalidArgument(_) => 400,
+        IPCError::PermissionDenied => 403,
+        IPCError::PeerNotFound
+        | IPCError::FileNotFound
+        | IPCError::TransferNotFound
+        | IPCError::UnknownCommand => 404,
+        IPCError::AmbiguousPeer(_) => 409,
+        IPCError::TooManyClients => 503,
+        _ => 500,
+    };
+    let mut body = json!({ "code": error.code(), "message": error.to_string() });
+    if let IPCError::AmbiguousPeer(candidates) = error {
+        body["candidates"] = json!(candidates);
+    }
+    json_response(status, json!({ "error": body }).to_string().into_bytes())
+}
+
+pub(crate) fn error_response(status: u16, code: &str, message: &str) -> JsonResponse {
+    let body = json!({ "error": { "code": code, "message": message } });
+    json_response(status, body.to_string().into_bytes())
+}
+
+fn json_response(status: u16, body: Vec<u8>) -> JsonResponse {
+    let header =
+        Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+    Response::from_data(body)
+        .with_status_code(status)
+        .with_header(header)
+}
+
+pub(crate) fn respond(request: Request, response: JsonResponse) {
+    if let Err(e) = request.respond(response) {
+        debug!(error = %e, "Failed to answer HTTP request");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::TestRequest;
+
+    #[test]
+    fn token_is_accepted_from_the_header_or_the_query() {
+        let bearer = |value: &str| {
+            let header = Header::from_bytes("Authorization", format!("Bearer {}", value)).unwrap();
+            TestRequest::new()
+                .with_path("/api/peers")
+                .with_header(header)
+                .into()
+        };
+
+        assert!(is_authorized(&bearer("secret"), "secret"));
+        assert!(!is_authorized(&bearer("secreT"), "secret"));
+        assert!(!is_authorized(
+            &TestRequest::new().with_path("/api/peers").into(),
+            "secret"
+        ));
+
+        let query: Request = TestRequest::new()
+            .with_path("/api/events?x=1&token=secret")
+            .into();
+        assert!(is_authorized(&query, "secret"));
+        assert_eq!(path_of(&query), "/api/events");
+    }
+}
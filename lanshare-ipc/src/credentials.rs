@@ -0,0 +1,170 @@
+use std::{
+    ffi::CString,
+    fs, io, mem,
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    ptr,
+};
+
+use crate::error::IPCError;
+
+/// The process on the other end of an IPC connection, as reported by the
+/// kernel when it connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PeerCredentials {
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl PeerCredentials {
+    pub(crate) fn of(stream: &UnixStream) -> io::Result<Self> {
+        let mut cred: libc::ucred = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut cred as *mut libc::ucred as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            pid: cred.pid,
+            uid: cred.uid,
+            gid: cred.gid,
+        })
+    }
+
+    /// Primary group plus, while the process is still around, its
+    /// supplementary groups.
+    fn groups(&self) -> Vec<u32> {
+        let mut groups = vec![self.gid];
+        if let Ok(status) = fs::read_to_string(format!("/proc/{}/status", self.pid))
+            && let Some(line) = status.lines().find_map(|line| line.strip_prefix("Groups:"))
+        {
+            groups.extend(
+                line.split_whitespace()
+                    .filter_map(|gid| gid.parse::<u32>().ok()),
+            );
+        }
+        groups
+    }
+}
+
+/// Who may use the IPC socket: the daemon's own user, plus any listed users
+/// and members of listed groups.
+#[derive(Debug, Clone, Default)]
+pub struct AccessPolicy {
+    uids: Vec<u32>,
+    gids: Vec<u32>,
+}
+
+impl AccessPolicy {
+    /// Users and groups may be given by name or numeric ID.
+    pub fn new(users: &[String], groups: &[String]) -> Result<Self, IPCError> {
+        let uids = users
+            .iter()
+            .map(|user| resolve(user, lookup_user).ok_or_else(|| unknown("user", user)))
+            .collect::<Result<_, _>>()?;
+        let gids = groups
+            .iter()
+            .map(|group| resolve(group, lookup_group).ok_or_else(|| unknown("group", group)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { uids, gids })
+    }
+
+    /// Only the daemon's own user gets in.
+    pub fn is_private(&self) -> bool {
+        self.uids.is_empty() && self.gids.is_empty()
+    }
+
+    pub(crate) fn permits(&self, peer: &PeerCredentials) -> bool {
+        let own_uid = unsafe { libc::geteuid() };
+        peer.uid == own_uid
+            || self.uids.contains(&peer.uid)
+            || (!self.gids.is_empty() && peer.groups().iter().any(|gid| self.gids.contains(gid)))
+    }
+}
+
+fn resolve(name: &str, lookup: fn(&str) -> Option<u32>) -> Option<u32> {
+    name.parse().ok().or_else(|| lookup(name))
+}
+
+fn unknown(kind: &str, name: &str) -> IPCError {
+    IPCError::InvalidConfig(format!("unknown {} '{}'", kind, name))
+}
+
+const LOOKUP_BUFFER_SIZE: usize = 16 * 1024;
+
+fn lookup_user(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut buf = vec![0 as libc::c_char; LOOKUP_BUFFER_SIZE];
+    let mut entry: libc::passwd = unsafe { mem::zeroed() };
+    let mut result = ptr::null_mut();
+    let ret = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut entry,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    (ret == 0 && !result.is_null()).then_some(entry.pw_uid)
+}
+
+fn lookup_group(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut buf = vec![0 as libc::c_char; LOOKUP_BUFFER_SIZE];
+    let mut entry: libc::group = unsafe { mem::zeroed() };
+    let mut result = ptr::null_mut();
+    let ret = unsafe {
+        libc::getgrnam_r(
+            name.as_ptr(),
+            &mut entry,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    (ret == 0 && !result.is_null()).then_some(entry.gr_gid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_own_user_and_listed_ids_are_permitted() {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let own = PeerCredentials::of(&client).unwrap();
+        assert_eq!(own.uid, unsafe { libc::geteuid() });
+
+        let stranger = PeerCredentials {
+            pid: 0,
+            uid: own.uid + 4242,
+            gid: own.gid + 4242,
+        };
+        let private = AccessPolicy::default();
+        assert!(private.permits(&own));
+        assert!(!private.permits(&stranger));
+
+        let by_user = AccessPolicy::new(&[stranger.uid.to_string()], &[]).unwrap();
+        assert!(by_user.permits(&stranger));
+        let by_group = AccessPolicy::new(&[], &[stranger.gid.to_string()]).unwrap();
+        assert!(by_group.permits(&stranger));
+
+        assert_eq!(
+            AccessPolicy::new(&["root".to_string()], &[]).unwrap().uids,
+            [0]
+        );
+        assert!(matches!(
+            AccessPolicy::new(&[], &["no-such-group-here".to_string()]),
+            Err(IPCError::InvalidConfig(_))
+        ));
+    }
+}
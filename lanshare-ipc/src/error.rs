@@ -1,10 +1,111 @@
+use std::fmt;
+
+use serde_json::json;
+
+use crate::message::RpcError;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
 #[derive(Debug)]
 pub enum IPCError {
     ConnectionRefused,
     InvalidJson,
+    InvalidRequest(String),
     UnknownCommand,
     PeerNotFound,
+    AmbiguousPeer(Vec<String>),
     FileNotFound,
+    /// No such transfer, outbox entry or offer.
+    TransferNotFound,
     TransferFailed,
+    InvalidArgument(String),
+    InvalidConfig(String),
+    TooManyClients,
+    PermissionDenied,
     Other(String),
 }
+
+impl IPCError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            IPCError::ConnectionRefused => "CONNECTION_REFUSED",
+            IPCError::InvalidJson => "INVALID_JSON",
+            IPCError::InvalidRequest(_) => "INVALID_REQUEST",
+            IPCError::UnknownCommand => "UNKNOWN_COMMAND",
+            IPCError::PeerNotFound => "PEER_NOT_FOUND",
+            IPCError::AmbiguousPeer(_) => "AMBIGUOUS_PEER",
+            IPCError::FileNotFound => "FILE_NOT_FOUND",
+            IPCError::TransferNotFound => "TRANSFER_NOT_FOUND",
+            IPCError::TransferFailed => "TRANSFER_FAILED",
+            IPCError::InvalidArgument(_) => "INVALID_ARGUMENT",
+            IPCError::InvalidConfig(_) => "INVALID_CONFIG",
+            IPCError::TooManyClients => "TOO_MANY_CLIENTS",
+            IPCError::PermissionDenied => "PERMISSION_DENIED",
+            IPCError::Other(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    /// JSON-RPC error code: the reserved codes for protocol errors, the
+    /// -32000 range for daemon errors.
+    pub fn rpc_code(&self) -> i64 {
+        match self {
+            IPCError::InvalidJson => PARSE_ERROR,
+            IPCError::InvalidRequest(_) => INVALID_REQUEST,
+            IPCError::UnknownCommand => METHOD_NOT_FOUND,
+            IPCError::InvalidArgument(_) => INVALID_PARAMS,
+            IPCError::Other(_) => INTERNAL_ERROR,
+            IPCError::ConnectionRefused => -32000,
+            IPCError::PeerNotFound => -32001,
+            IPCError::AmbiguousPeer(_) => -32002,
+            IPCError::FileNotFound => -32003,
+            IPCError::TransferFailed => -32004,
+            IPCError::InvalidConfig(_) => -32005,
+            IPCError::TooManyClients => -32006,
+            IPCError::PermissionDenied => -32007,
+            IPCError::TransferNotFound => -32008,
+        }
+    }
+
+    pub fn to_rpc_error(&self) -> RpcError {
+        let mut data = json!({ "code": self.code() });
+        if let IPCError::AmbiguousPeer(candidates) = self {
+            data["candidates"] = json!(candidates);
+        }
+        RpcError {
+            code: self.rpc_code(),
+            message: self.to_string(),
+            data: Some(data),
+        }
+    }
+}
+
+impl fmt::Display for IPCError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IPCError::ConnectionRefused => write!(f, "Connection refused"),
+            IPCError::InvalidJson => write!(f, "Invalid JSON"),
+            IPCError::InvalidRequest(message) => write!(f, "Invalid request: {}", message),
+            IPCError::UnknownCommand => write!(f, "Method not found"),
+            IPCError::PeerNotFound => write!(f, "Peer not found"),
+            IPCError::AmbiguousPeer(candidates) => write!(
+                f,
+                "Peer name is ambiguous, candidates: {}",
+                candidates.join("; ")
+            ),
+            IPCError::FileNotFound => write!(f, "File not found"),
+            IPCError::TransferNotFound => write!(f, "Transfer not found"),
+            IPCError::TransferFailed => write!(f, "Transfer failed"),
+            IPCError::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+            IPCError::InvalidConfig(message) => write!(f, "Invalid configuration: {}", message),
+            IPCError::TooManyClients => write!(f, "Too many clients connected"),
+            IPCError::PermissionDenied => {
+                write!(f, "Permission denied: this user may not control the daemon")
+            }
+            IPCError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
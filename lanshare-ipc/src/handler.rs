@@ -0,0 +1,51 @@
+use std::sync::mpsc::Receiver;
+
+use lanshare_config::{Config, ReloadReport};
+use lanshare_domain::models::{Peer, SharedFile, TransferStatus};
+
+use crate::{
+    error::IPCError,
+    message::{DaemonEvent, SendFileResponse, StatusResponse, WebDropStatus},
+};
+
+pub trait CommandHandler: Send + Sync {
+    fn list_peers(&self) -> Result<Vec<Peer>, IPCError>;
+    fn send_file(
+        &self,
+        path: &str,
+        peer: &str,
+        expires_in: Option<u64>,
+    ) -> Result<SendFileResponse, IPCError>;
+    fn get_status(&self) -> Result<StatusResponse, IPCError>;
+    /// Cancels a transfer or drops a queued outbox entry.
+    fn cancel_transfer(&self, transfer_id: &str) -> Result<(), IPCError>;
+    fn pause_transfer(&self, transfer_id: &str) -> Result<(), IPCError>;
+    fn resume_transfer(&self, transfer_id: &str) -> Result<(), IPCError>;
+    /// Finished transfers since the daemon started, oldest first.
+    fn get_history(&self) -> Result<Vec<TransferStatus>, IPCError>;
+    fn accept_offer(&self, offer_id: &str) -> Result<(), IPCError>;
+    fn reject_offer(&self, offer_id: &str) -> Result<(), IPCError>;
+    fn add_peer(
+        &self,
+        address: &str,
+        alias: Option<&str>,
+        tags: Vec<String>,
+    ) -> Result<Peer, IPCError>;
+    fn remove_peer(&self, peer: &str) -> Result<(), IPCError>;
+    fn alias_peer(&self, peer: &str, alias: Option<&str>) -> Result<Peer, IPCError>;
+    fn tag_peer(&self, peer: &str, tags: Vec<String>) -> Result<Peer, IPCError>;
+    fn get_config(&self) -> Result<Config, IPCError>;
+    /// Re-reads the configuration and applies what can change live.
+    fn reload_config(&self) -> Result<ReloadReport, IPCError>;
+    /// Asks the daemon to stop; the reply is sent before it does.
+    fn shutdown(&self) -> Result<(), IPCError>;
+    /// Current metrics in the Prometheus text format.
+    fn metrics(&self) -> Result<String, IPCError>;
+    /// Peer and transfer changes from now on, until the receiver is dropped.
+    fn subscribe(&self) -> Result<Receiver<DaemonEvent>, IPCError>;
+    /// Current PIN and shared files of the web drop page.
+    fn web_drop_status(&self) -> Result<WebDropStatus, IPCError>;
+    /// Offers a local file for download on the web drop page.
+    fn share_file(&self, path: &str) -> Result<SharedFile, IPCError>;
+    fn unshare_file(&self, share_id: &str) -> Result<(), IPCError>;
+}
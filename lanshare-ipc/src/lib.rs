@@ -1,6 +1,19 @@
 mod server;
+mod handler;
 mod message;
 mod error;
+mod schema;
+mod credentials;
 
 
-pub use server::IPCServer;
\ No newline at end of file
+pub use credentials::AccessPolicy;
+pub use error::IPCError;
+pub use handler::CommandHandler;
+pub use message::{
+    AddPeerParams, AliasPeerParams, DaemonEvent, EVENT_METHOD, OfferParams,
+    JSONRPC_VERSION, NoParams, PeerParams, RpcError, RpcOutcome, RpcRequest, RpcResponse,
+    SendFileParams, SendFileResponse, ShareFileParams, StatusResponse, TagPeerParams,
+    TransferParams, UnshareFileParams, WebDropStatus,
+};
+pub use schema::{DISCOVER_METHOD, openrpc};
+pub use server::IPCServer;
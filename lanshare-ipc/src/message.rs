@@ -1,38 +1,223 @@
+use std::net::SocketAddr;
+
+use lanshare_domain::models::{
+    IncomingOffer, OutboxEntry, OutboxEvent, PeerEvent, SharedFile, TransferStatus,
+};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const JSONRPC_VERSION: &str = "2.0";
+/// Method of the notifications a subscribed connection receives; their
+/// params are a [`DaemonEvent`].
+pub const EVENT_METHOD: &str = "event";
+
+/// A JSON-RPC 2.0 request; without an `id` it is a notification and gets no
+/// response.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+}
+
+impl RpcRequest {
+    pub fn new(method: &str, params: Value, id: u64) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.to_string(),
+            params: Some(params),
+            id: Some(id.into()),
+        }
+    }
+}
+
+/// A JSON-RPC 2.0 response. `id` is `null` when the request was too broken
+/// to read one from it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    #[serde(flatten)]
+    pub outcome: RpcOutcome,
+    pub id: Value,
+}
 
-#[derive(Serialize, Deserialize)]
-pub struct SuccessMessage<T> {
-    pub id: u64,
-    pub status: String,
-    pub data: T,
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RpcOutcome {
+    Result(Value),
+    Error(RpcError),
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ErrorMessage {
-    pub id: u64,
-    pub status: String,
-    pub error: String,
-    pub code: String,
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    /// `{"code": "PEER_NOT_FOUND", ...}`: a stable name for the error plus
+    /// details such as the candidates of an ambiguous peer name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
 }
 
+impl RpcResponse {
+    pub fn result(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            outcome: RpcOutcome::Result(result),
+            id,
+        }
+    }
+
+    pub fn error(id: Value, error: RpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            outcome: RpcOutcome::Error(error),
+            id,
+        }
+    }
+
+    pub fn into_result(self) -> Result<Value, RpcError> {
+        match self.outcome {
+            RpcOutcome::Result(result) => Ok(result),
+            RpcOutcome::Error(error) => Err(error),
+        }
+    }
+}
 
+/// Methods and their parameters, by name (`{"peer": ...}`) or by position.
 #[derive(Debug, Deserialize)]
-#[serde(tag = "command", rename_all = "snake_case")]
-pub enum CommandRequest {
-    ListPeers {
-        id: Option<u64>,
-    },
-    SendFile {
-        id: Option<u64>,
-        path: String,
-        peer: String,
-        file_name: Option<String>,
-    },
-    GetStatus {
-        id: Option<u64>,
-    },
-    CancelTransfer {
-        id: Option<u64>,
-        transfer_id: String,
-    },
-}
\ No newline at end of file
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub(crate) enum CommandRequest {
+    ListPeers(NoParams),
+    SendFile(SendFileParams),
+    GetStatus(NoParams),
+    CancelTransfer(TransferParams),
+    PauseTransfer(TransferParams),
+    ResumeTransfer(TransferParams),
+    GetHistory(NoParams),
+    AcceptOffer(OfferParams),
+    RejectOffer(OfferParams),
+    AddPeer(AddPeerParams),
+    RemovePeer(PeerParams),
+    AliasPeer(AliasPeerParams),
+    TagPeer(TagPeerParams),
+    GetConfig(NoParams),
+    ReloadConfig(NoParams),
+    Shutdown(NoParams),
+    Metrics(NoParams),
+    Subscribe(NoParams),
+    WebDropStatus(NoParams),
+    ShareFile(ShareFileParams),
+    UnshareFile(UnshareFileParams),
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NoParams {}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SendFileParams {
+    pub path: String,
+    /// Alias, display name, hostname, device ID prefix or `ip:port`.
+    pub peer: String,
+    /// Seconds to keep the file queued while the peer is offline.
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TransferParams {
+    /// Transfer ID, or for `cancel_transfer` also an outbox entry ID.
+    pub transfer_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OfferParams {
+    pub offer_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AddPeerParams {
+    pub address: String,
+    #[serde(default)]
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PeerParams {
+    /// Address or alias of a peer book entry.
+    pub peer: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AliasPeerParams {
+    pub peer: String,
+    /// Omit or pass `null` to clear the alias.
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TagPeerParams {
+    pub peer: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ShareFileParams {
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UnshareFileParams {
+    pub share_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum SendFileResponse {
+    Started { transfer_id: String },
+    Queued { outbox_id: String, expires_at: u64 },
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StatusResponse {
+    /// Transfers that have not finished yet, paused ones included.
+    pub transfers: Vec<TransferStatus>,
+    pub outbox: Vec<OutboxEntry>,
+    /// Incoming transfers waiting to be accepted or rejected.
+    #[serde(default)]
+    pub offers: Vec<IncomingOffer>,
+}
+
+/// What a browser needs to use the web drop page.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WebDropStatus {
+    pub listen: SocketAddr,
+    pub pin: String,
+    pub pin_expires_at: u64,
+    pub shares: Vec<SharedFile>,
+}
+
+/// Pushed to subscribed clients as they happen.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum DaemonEvent {
+    Peer(PeerEvent),
+    Transfer(TransferStatus),
+    Outbox(OutboxEvent),
+}
@@ -0,0 +1,178 @@
+use lanshare_config::{Config, ReloadReport};
+use lanshare_domain::models::{Peer, SharedFile, TransferStatus};
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings};
+use serde_json::{Value, json};
+
+use crate::{
+    error::IPCError,
+    message::{
+        AddPeerParams, AliasPeerParams, DaemonEvent, NoParams, OfferParams, PeerParams,
+        SendFileParams, SendFileResponse, ShareFileParams, StatusResponse, TagPeerParams,
+        TransferParams, UnshareFileParams, WebDropStatus,
+    },
+};
+
+/// Standard OpenRPC method returning the document below.
+pub const DISCOVER_METHOD: &str = "rpc.discover";
+const OPENRPC_VERSION: &str = "1.3.2";
+
+struct Method {
+    name: &'static str,
+    summary: &'static str,
+    params: fn(&mut SchemaGenerator) -> Schema,
+    result: fn(&mut SchemaGenerator) -> Schema,
+}
+
+fn method<P: JsonSchema, R: JsonSchema>(name: &'static str, summary: &'static str) -> Method {
+    Method {
+        name,
+        summary,
+        params: P::json_schema,
+        result: |generator| generator.subschema_for::<R>(),
+    }
+}
+
+fn methods() -> Vec<Method> {
+    vec![
+        method::<NoParams, Vec<Peer>>("list_peers", "Peers currently visible on the network"),
+        method::<SendFileParams, SendFileResponse>(
+            "send_file",
+            "Send a file, queueing it while the peer is offline",
+        ),
+        method::<NoParams, StatusResponse>("get_status", "Running transfers and queued files"),
+        method::<TransferParams, String>(
+            "cancel_transfer",
+            "Cancel a transfer or drop a queued outbox entry",
+        ),
+        method::<TransferParams, String>("pause_transfer", "Pause a running transfer"),
+        method::<TransferParams, String>("resume_transfer", "Resume a paused transfer"),
+        method::<NoParams, Vec<TransferStatus>>(
+            "get_history",
+            "Finished transfers since the daemon started",
+        ),
+        method::<OfferParams, String>("accept_offer", "Accept an incoming transfer"),
+        method::<OfferParams, String>("reject_offer", "Reject an incoming transfer"),
+        method::<AddPeerParams, Peer>("add_peer", "Add a static peer to the peer book"),
+        method::<PeerParams, String>("remove_peer", "Remove a peer book entry"),
+        method::<AliasPeerParams, Peer>("alias_peer", "Set or clear the alias of a peer"),
+        method::<TagPeerParams, Peer>("tag_peer", "Replace the tags of a peer"),
+        method::<NoParams, Config>("get_config", "The daemon's effective configuration"),
+        method::<NoParams, ReloadReport>(
+            "reload_config",
+            "Re-read the configuration and apply what can change live",
+        ),
+        method::<NoParams, String>("shutdown", "Stop the daemon after checkpointing transfers"),
+        method::<NoParams, String>("metrics", "Metrics in the Prometheus text format"),
+        method::<NoParams, String>(
+            "subscribe",
+            "Receive `event` notifications carrying a DaemonEvent on this connection",
+        ),
+        method::<NoParams, WebDropStatus>("web_drop_status", "PIN and files of the web drop page"),
+        method::<ShareFileParams, SharedFile>(
+            "share_file",
+            "Offer a file for download on the web drop page",
+        ),
+        method::<UnshareFileParams, String>("unshare_file", "Stop offering a shared file"),
+    ]
+}
+
+pub(crate) fn is_method(name: &str) -> bool {
+    methods().iter().any(|method| method.name == name)
+}
+
+/// Describes the socket protocol as an OpenRPC document, with JSON Schemas
+/// generated from the request and response types.
+pub fn openrpc() -> Value {
+    let mut generator = SchemaSettings::draft07()
+        .with(|settings| settings.definitions_path = "/components/schemas".into())
+        .into_generator();
+
+    let methods: Vec<Value> = methods()
+        .into_iter()
+        .map(|method| {
+            let params = (method.params)(&mut generator);
+            let result = (method.result)(&mut generator);
+            json!({
+                "name": method.name,
+                "summary": method.summary,
+                "paramStructure": "by-name",
+                "params": content_descriptors(&params),
+                "result": { "name": "result", "schema": result },
+            })
+        })
+        .collect();
+    // Not the result of any method, but the params of `event` notifications.
+    generator.subschema_for::<DaemonEvent>();
+
+    json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": {
+            "title": "LanShare daemon",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "methods": methods,
+        "components": {
+            "schemas": generator.take_definitions(true),
+            "errors": errors(),
+        },
+    })
+}
+
+/// One descriptor per property of a params object schema.
+fn content_descriptors(params: &Schema) -> Vec<Value> {
+    let required: Vec<&str> = params
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    params
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, schema)| {
+                    json!({
+                        "name": name,
+                        "required": required.contains(&name.as_str()),
+                        "schema": schema,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn errors() -> Value {
+    let errors = [
+        IPCError::InvalidJson,
+        IPCError::InvalidRequest(String::new()),
+        IPCError::UnknownCommand,
+        IPCError::InvalidArgument(String::new()),
+        IPCError::Other(String::new()),
+        IPCError::ConnectionRefused,
+        IPCError::PeerNotFound,
+        IPCError::AmbiguousPeer(Vec::new()),
+        IPCError::FileNotFound,
+        IPCError::TransferFailed,
+        IPCError::TransferNotFound,
+        IPCError::InvalidConfig(String::new()),
+        IPCError::TooManyClients,
+        IPCError::PermissionDenied,
+    ];
+    errors
+        .iter()
+        .map(|error| {
+            let message = error.to_string();
+            let message = match message.trim_end_matches([':', ' ']) {
+                "" => "Internal error",
+                trimmed => trimmed,
+            };
+            (
+                error.code().to_string(),
+                json!({ "code": error.rpc_code(), "message": message }),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
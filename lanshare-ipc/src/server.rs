@@ -2,29 +2,48 @@ use std::{
     fs,
     io::{BufRead, BufReader, ErrorKind, Write},
     os::unix::{
-        fs::PermissionsExt,
+        fs::DirBuilderExt,
         net::{UnixListener, UnixStream},
     },
-    path::{Path, PathBuf},
+    path::PathBuf,
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
     },
     thread::{self, JoinHandle},
+    time::Duration,
 };
 
 use serde::Serialize;
+use serde_json::{Value, json};
+use tracing::{debug, error, info, info_span, warn};
 
 use crate::{
+    credentials::{AccessPolicy, PeerCredentials},
     error::IPCError,
-    message::{CommandRequest, ErrorMessage, SuccessMessage},
+    handler::CommandHandler,
+    message::{CommandRequest, DaemonEvent, EVENT_METHOD, JSONRPC_VERSION, RpcResponse},
+    schema::{DISCOVER_METHOD, is_method, openrpc},
 };
 
-#[derive(Debug)]
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Clients may keep a session open between requests; an idle one gives its
+/// slot back after this long.
+const CLIENT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_CLIENTS: usize = 16;
+
 pub struct IPCServer {
     socket_path: Arc<PathBuf>,
     shutdown: Arc<AtomicBool>,
+    handler: Arc<dyn CommandHandler>,
     listener_handle: Option<JoinHandle<()>>,
+    /// False for sockets handed to us by the service manager, which must
+    /// outlive the daemon so the next client can start it again.
+    owns_socket: bool,
+    max_clients: usize,
+    access: AccessPolicy,
 }
 
 impl Clone for IPCServer {
@@ -32,26 +51,62 @@ impl Clone for IPCServer {
         Self {
             socket_path: self.socket_path.clone(),
             shutdown: self.shutdown.clone(),
+            handler: self.handler.clone(),
             listener_handle: None,
+            owns_socket: self.owns_socket,
+            max_clients: self.max_clients,
+            access: self.access.clone(),
         }
     }
 }
 
 impl IPCServer {
-    pub fn new(path: PathBuf, shutdown: Arc<AtomicBool>) -> Self {
+    pub fn new(path: PathBuf, shutdown: Arc<AtomicBool>, handler: Arc<dyn CommandHandler>) -> Self {
         Self {
             socket_path: Arc::new(path),
             shutdown,
+            handler,
             listener_handle: None,
+            owns_socket: true,
+            max_clients: DEFAULT_MAX_CLIENTS,
+            access: AccessPolicy::default(),
         }
     }
+
+    /// Caps concurrent clients; further connections get a `TOO_MANY_CLIENTS`
+    /// error and are closed.
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+
+    /// Decides which local users besides our own may connect; everyone else
+    /// gets a `PERMISSION_DENIED` error and is closed.
+    pub fn with_access_policy(mut self, access: AccessPolicy) -> Self {
+        self.access = access;
+        self
+    }
+
     pub fn start(&mut self) -> Result<(), IPCError> {
         self.initialize_socket()?;
+        let listener = self.bind_socket()?;
+        self.spawn_listener(listener);
+        Ok(())
+    }
+
+    /// Serves an already bound socket, e.g. one passed in through systemd
+    /// socket activation. The socket file is left in place on shutdown.
+    pub fn start_with_listener(&mut self, listener: UnixListener) -> Result<(), IPCError> {
+        self.owns_socket = false;
+        self.spawn_listener(listener);
+        Ok(())
+    }
+
+    fn spawn_listener(&mut self, listener: UnixListener) {
         let self_clone = self.clone();
         let shutdown = self.shutdown.clone();
-        let handle = thread::spawn(move || self_clone.run_listener(shutdown));
+        let handle = thread::spawn(move || self_clone.run_listener(listener, shutdown));
         self.listener_handle = Some(handle);
-        Ok(())
     }
 
     fn initialize_socket(&self) -> Result<(), IPCError> {
@@ -63,214 +118,338 @@ impl IPCServer {
         Ok(())
     }
 
+    /// The umask applies to bind(2) itself, so the socket never exists with
+    /// looser permissions than intended, not even briefly. It is only opened
+    /// up to other users when the access policy admits some.
     fn bind_socket(&self) -> Result<UnixListener, IPCError> {
-        let listener = UnixListener::bind(self.socket_path.as_path())
-            .map_err(|_e| IPCError::Other("Failed to bind socket".into()))?;
-        Ok(listener)
-    }
-    fn set_permissions(&self) -> Result<(), IPCError> {
-        fs::set_permissions(
-            self.socket_path.as_path(),
-            fs::Permissions::from_mode(0o600),
-        )
-        .map_err(|_e| IPCError::Other("Failed to set permissions".into()))?;
-        Ok(())
-    }
+        if let Some(dir) = self.socket_path.parent()
+            && !dir.as_os_str().is_empty()
+            && !dir.exists()
+        {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .map_err(|e| {
+                    IPCError::Other(format!("Failed to create socket directory: {}", e))
+                })?;
+        }
 
-    fn accept_connection(&self, listener: &UnixListener) -> Result<UnixStream, IPCError> {
-        let (stream, addr) = listener
-            .accept()
-            .map_err(|_e| IPCError::Other("Failed to accept connection".into()))?;
-        print!("accept a new client from {:#?}", addr);
-        Ok(stream)
+        let mode: libc::mode_t = if self.access.is_private() {
+            0o600
+        } else {
+            0o666
+        };
+        let previous = unsafe { libc::umask(!mode & 0o777) };
+        let listener = UnixListener::bind(self.socket_path.as_path());
+        unsafe { libc::umask(previous) };
+        listener.map_err(|e| IPCError::Other(format!("Failed to bind socket: {}", e)))
     }
 
-    fn validate_connection(&self, _client_socket: &UnixStream) -> Result<bool, IPCError> {
-        Ok(true)
+    fn validate_connection(&self, client_socket: &UnixStream) -> Result<(), IPCError> {
+        let peer = PeerCredentials::of(client_socket)
+            .map_err(|e| IPCError::Other(format!("Failed to read peer credentials: {}", e)))?;
+        if self.access.permits(&peer) {
+            Ok(())
+        } else {
+            warn!(
+                uid = peer.uid,
+                pid = peer.pid,
+                "Rejected IPC client from another user"
+            );
+            Err(IPCError::PermissionDenied)
+        }
     }
 
-    fn run_listener(&self, shutdown: Arc<AtomicBool>) {
-        let listener = match self.bind_socket() {
-            Ok(l) => l,
-            Err(e) => {
-                eprintln!("Failed to bind socket: {:?}", e);
-                return;
-            }
-        };
-
-        self.set_permissions().ok();
-
-        loop {
-            if shutdown.load(Ordering::Relaxed) {
-                println!("[IPC Listener] Shutdown received, stopping.");
-                break;
-            }
+    fn run_listener(&self, listener: UnixListener, shutdown: Arc<AtomicBool>) {
+        if let Err(e) = listener.set_nonblocking(true) {
+            error!(error = %e, "Failed to make IPC listener non-blocking");
+        }
 
+        let mut clients: Vec<(UnixStream, JoinHandle<()>)> = Vec::new();
+        let mut next_client: u64 = 0;
+        while !shutdown.load(Ordering::Relaxed) {
             match listener.accept() {
                 Ok((stream, _addr)) => {
-                    match self.validate_connection(&stream) {
-                        Ok(_) => match self.handle_connection(stream) {
-                            Ok(_) => println!("Connection handled successfully"),
-                            Err(_) => eprintln!("Error handling connection"),
-                        },
+                    next_client += 1;
+                    clients.retain(|(_, handle)| !handle.is_finished());
+                    let span = info_span!("ipc_client", client = next_client);
+                    if let Err(e) = span.in_scope(|| self.validate_connection(&stream)) {
+                        self.reject(stream, e);
+                        continue;
+                    }
+                    if clients.len() >= self.max_clients {
+                        let _entered = span.enter();
+                        warn!(max_clients = self.max_clients, "Too many IPC clients");
+                        self.reject(stream, IPCError::TooManyClients);
+                        continue;
+                    }
+                    let control = match stream
+                        .set_nonblocking(false)
+                        .and_then(|_| stream.set_read_timeout(Some(CLIENT_IDLE_TIMEOUT)))
+                        .and_then(|_| stream.try_clone())
+                    {
+                        Ok(control) => control,
                         Err(e) => {
-                            eprintln!("Error validating connection: {:?}", e);
+                            warn!(parent: &span, error = %e, "Failed to set up IPC client");
+                            continue;
                         }
                     };
+                    let server = self.clone();
+                    let handle = thread::spawn(move || {
+                        let _entered = span.enter();
+                        match server.serve_client(stream) {
+                            Ok(()) => debug!("IPC client disconnected"),
+                            Err(e) => warn!(error = %e, "IPC client failed"),
+                        }
+                    });
+                    clients.push((control, handle));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
                 }
                 Err(e) => {
-                    eprintln!("Error accepting connection: {}", e);
+                    error!(error = %e, "Error accepting IPC connection");
                 }
             }
         }
-    }
-
-    fn read_request(
-        &self,
-        client_socket: &UnixStream,
-    ) -> Result<Option<serde_json::Value>, IPCError> {
-        let mut reader = BufReader::new(client_socket);
-        let mut line = String::new();
-        let res = match reader.read_line(&mut line) {
-            Ok(0) => Ok(None),
-            Ok(n) => {
-                let trimmed = line.trim_end_matches(|c: char| c.is_whitespace());
-                if trimmed.is_empty() {
-                    Ok(None)
-                } else {
-                    let obj: serde_json::Value = serde_json::from_str(trimmed)
-                        .map_err(|e| IPCError::Other(format!("Failed to parse JSON: {}", e)))?;
-                    Ok(Some(obj))
-                }
-            }
-            Err(e) if e.kind() == ErrorKind::Interrupted => self.read_request(client_socket),
-            Err(e) => Err(IPCError::Other(format!(
-                "Failed to read from socket: {}",
-                e
-            ))),
-        };
 
-        res
-    }
-
-    fn handle_connection(&self, client_socket: UnixStream) -> Result<(), IPCError> {
-        match self.read_request(&client_socket) {
-            Ok(Some(request)) => match self.handle_command(request) {
-                Ok(response) => self.send_response(client_socket, response),
-                Err(handle_error) => {
-                    let response = self.create_error_response(
-                        None,
-                        "Failed to handle command".to_string(),
-                        "HANDLE_ERROR".to_string(),
-                    )?;
-                    let _ = self.send_response(client_socket, response);
-                    Err(handle_error)
-                }
-            },
-            Ok(None) => self.clean_connection(client_socket),
-            Err(read_error) => {
-                let response = self.create_error_response(
-                    None,
-                    "Failed to read request".to_string(),
-                    "READ_ERROR".to_string(),
-                )?;
-                let _ = self.send_response(client_socket, response);
-                Err(read_error)
-            }
+        info!("IPC listener shutting down");
+        drop(listener);
+        // Only the read side is closed, so a response being written (such as
+        // the reply to `shutdown`) still reaches its client.
+        for (stream, handle) in clients {
+            let _ = stream.shutdown(std::net::Shutdown::Read);
+            let _ = handle.join();
         }
+        self.remove_socket();
     }
 
-    fn handle_list_peers(&self, id: Option<u64>) -> Result<Vec<u8>, IPCError> {
-        let peers = vec!["test1", "test2"];
-        Ok(self.create_success_response(id, peers)?)
+    fn remove_socket(&self) {
+        if self.owns_socket {
+            let _ = fs::remove_file(self.socket_path.as_path());
+        }
     }
 
-    fn handle_send_file(
-        &self,
-        id: Option<u64>,
-        path: String,
-        peer: String,
-        file_name: Option<String>,
-    ) -> Result<Vec<u8>, IPCError> {
-        Ok(self.create_success_response(id, "ok")?)
+    fn reject(&self, stream: UnixStream, error: IPCError) {
+        if let Some(response) = encode(&RpcResponse::error(Value::Null, error.to_rpc_error())) {
+            let _ = self.send_response(&stream, response);
+        }
     }
 
-    fn handle_get_status(&self, id: Option<u64>) -> Result<Vec<u8>, IPCError> {
-        Ok(self.create_success_response(id, "ok")?)
+    /// Answers requests from one client until it hangs up or goes idle.
+    /// Each line holds one request or a batch; responses go out in order.
+    fn serve_client(&self, stream: UnixStream) -> Result<(), IPCError> {
+        let mut reader = BufReader::new(clone_stream(&stream)?);
+        let session = Session::new(&stream)?;
+        while let Some(line) = self.read_request(&mut reader, session.is_subscribed())? {
+            if let Some(response) = self.respond_to_line(&line, &session) {
+                session.send(&response)?;
+            }
+        }
+        Ok(())
     }
 
-    fn handle_cancel_transfer(
+    /// Reads the next non-empty request line; `None` once the client hangs
+    /// up or, unless it is waiting for events, has been silent for
+    /// `CLIENT_IDLE_TIMEOUT`.
+    fn read_request(
         &self,
-        id: Option<u64>,
-        transfer_id: String,
-    ) -> Result<Vec<u8>, IPCError> {
-        Ok(self.create_success_response(id, "ok")?)
-    }
-
-    fn handle_command(&self, raw: serde_json::Value) -> Result<Vec<u8>, IPCError> {
-        match serde_json::from_value::<CommandRequest>(raw) {
-            Ok(cmd) => match cmd {
-                CommandRequest::ListPeers { id } => self.handle_list_peers(id),
-                CommandRequest::SendFile {
-                    id,
-                    path,
-                    peer,
-                    file_name,
-                } => self.handle_send_file(id, path, peer, file_name),
-                CommandRequest::GetStatus { id } => self.handle_get_status(id),
-                CommandRequest::CancelTransfer { id, transfer_id } => {
-                    self.handle_cancel_transfer(id, transfer_id)
+        reader: &mut BufReader<UnixStream>,
+        keep_alive: bool,
+    ) -> Result<Option<String>, IPCError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {
+                    let trimmed = line.trim();
+                    if !trimmed.is_empty() {
+                        return Ok(Some(trimmed.to_string()));
+                    }
                 }
-            },
-            Err(_) => return Err(IPCError::Other("Failed to parse command".to_string())),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if keep_alive {
+                        continue;
+                    }
+                    debug!("IPC client idle, closing");
+                    return Ok(None);
+                }
+                Err(e) => {
+                    return Err(IPCError::Other(format!(
+                        "Failed to read from socket: {}",
+                        e
+                    )));
+                }
+            }
         }
     }
 
-    fn create_success_response<T: Serialize>(
-        &self,
-        id: Option<u64>,
-        data: T,
-    ) -> Result<Vec<u8>, IPCError> {
-        let response_id = id.unwrap_or(0);
-        let response = SuccessMessage {
-            id: response_id,
-            status: "success".to_string(),
-            data,
+    /// `None` when nothing is owed to the client, i.e. the line held only
+    /// notifications.
+    fn respond_to_line(&self, line: &str, session: &Session) -> Option<Vec<u8>> {
+        let request = match serde_json::from_str::<Value>(line) {
+            Ok(request) => request,
+            Err(e) => {
+                debug!(error = %e, "Unparsable IPC request");
+                return encode(&RpcResponse::error(
+                    Value::Null,
+                    IPCError::InvalidJson.to_rpc_error(),
+                ));
+            }
         };
-
-        let json_string = serde_json::to_string(&response)
-            .map_err(|e| IPCError::Other(format!("Failed to serialize response: {}", e)))?;
-
-        let mut bytes = json_string.into_bytes();
-        bytes.push(b'\n');
-
-        Ok(bytes)
+        match request {
+            Value::Array(batch) if batch.is_empty() => encode(&RpcResponse::error(
+                Value::Null,
+                IPCError::InvalidRequest("Empty batch".to_string()).to_rpc_error(),
+            )),
+            Value::Array(batch) => {
+                let responses: Vec<RpcResponse> = batch
+                    .into_iter()
+                    .filter_map(|request| self.respond(request, session))
+                    .collect();
+                if responses.is_empty() {
+                    None
+                } else {
+                    encode(&responses)
+                }
+            }
+            request => self
+                .respond(request, session)
+                .and_then(|response| encode(&response)),
+        }
     }
 
-    fn create_error_response(
-        &self,
-        id: Option<u64>,
-        error: String,
-        code: String,
-    ) -> Result<Vec<u8>, IPCError> {
-        let response_id = id.unwrap_or(0);
-        let response = ErrorMessage {
-            id: response_id,
-            status: "error".to_string(),
-            error,
-            code,
+    fn respond(&self, request: Value, session: &Session) -> Option<RpcResponse> {
+        let Value::Object(mut request) = request else {
+            return Some(invalid_request("Request must be an object"));
+        };
+        let id = request.remove("id");
+        let is_notification = id.is_none();
+        let id = id.unwrap_or(Value::Null);
+        if !matches!(id, Value::Null | Value::Number(_) | Value::String(_)) {
+            return Some(invalid_request("id must be a string, number or null"));
+        }
+        if request.get("jsonrpc").and_then(Value::as_str) != Some(JSONRPC_VERSION) {
+            return Some(invalid_request("jsonrpc must be \"2.0\""));
+        }
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(invalid_request("method must be a string"));
         };
 
-        let json_string = serde_json::to_string(&response)
-            .map_err(|e| IPCError::Other(format!("Failed to serialize response: {}", e)))?;
+        let span = info_span!("ipc_request", id = %id, method);
+        let _entered = span.enter();
+        let outcome = self.call(method, request.get("params").cloned(), session);
+        if let Err(e) = &outcome {
+            warn!(error = %e, "IPC request failed");
+        }
+        if is_notification {
+            return None;
+        }
+        Some(match outcome {
+            Ok(result) => RpcResponse::result(id, result),
+            Err(e) => RpcResponse::error(id, e.to_rpc_error()),
+        })
+    }
 
-        let mut bytes = json_string.into_bytes();
-        bytes.push(b'\n');
+    fn call(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        session: &Session,
+    ) -> Result<Value, IPCError> {
+        if method == DISCOVER_METHOD {
+            return Ok(openrpc());
+        }
+        let params = params.unwrap_or_else(|| Value::Object(Default::default()));
+        if !params.is_object() && !params.is_array() {
+            return Err(IPCError::InvalidArgument(
+                "params must be an object or an array".to_string(),
+            ));
+        }
+        let command =
+            serde_json::from_value::<CommandRequest>(json!({ "method": method, "params": params }))
+                .map_err(|e| {
+                    if is_method(method) {
+                        IPCError::InvalidArgument(e.to_string())
+                    } else {
+                        IPCError::UnknownCommand
+                    }
+                })?;
+        self.dispatch(command, session)
+    }
 
-        Ok(bytes)
+    fn dispatch(&self, command: CommandRequest, session: &Session) -> Result<Value, IPCError> {
+        let handler = &self.handler;
+        match command {
+            CommandRequest::ListPeers(_) => to_result(handler.list_peers()?),
+            CommandRequest::SendFile(params) => to_result(handler.send_file(
+                &params.path,
+                &params.peer,
+                params.expires_in,
+            )?),
+            CommandRequest::GetStatus(_) => to_result(handler.get_status()?),
+            CommandRequest::CancelTransfer(params) => {
+                handler.cancel_transfer(&params.transfer_id)?;
+                to_result("ok")
+            }
+            CommandRequest::PauseTransfer(params) => {
+                handler.pause_transfer(&params.transfer_id)?;
+                to_result("ok")
+            }
+            CommandRequest::ResumeTransfer(params) => {
+                handler.resume_transfer(&params.transfer_id)?;
+                to_result("ok")
+            }
+            CommandRequest::GetHistory(_) => to_result(handler.get_history()?),
+            CommandRequest::AcceptOffer(params) => {
+                handler.accept_offer(&params.offer_id)?;
+                to_result("ok")
+            }
+            CommandRequest::RejectOffer(params) => {
+                handler.reject_offer(&params.offer_id)?;
+                to_result("ok")
+            }
+            CommandRequest::AddPeer(params) => to_result(handler.add_peer(
+                &params.address,
+                params.alias.as_deref(),
+                params.tags,
+            )?),
+            CommandRequest::RemovePeer(params) => {
+                handler.remove_peer(&params.peer)?;
+                to_result("ok")
+            }
+            CommandRequest::AliasPeer(params) => {
+                to_result(handler.alias_peer(&params.peer, params.alias.as_deref())?)
+            }
+            CommandRequest::TagPeer(params) => {
+                to_result(handler.tag_peer(&params.peer, params.tags)?)
+            }
+            CommandRequest::GetConfig(_) => to_result(handler.get_config()?),
+            CommandRequest::ReloadConfig(_) => to_result(handler.reload_config()?),
+            CommandRequest::Shutdown(_) => {
+                handler.shutdown()?;
+                to_result("Shutting down")
+            }
+            CommandRequest::Metrics(_) => to_result(handler.metrics()?),
+            CommandRequest::Subscribe(_) => {
+                if !session.is_subscribed() {
+                    session.forward(handler.subscribe()?);
+                }
+                to_result("ok")
+            }
+            CommandRequest::WebDropStatus(_) => to_result(handler.web_drop_status()?),
+            CommandRequest::ShareFile(params) => to_result(handler.share_file(&params.path)?),
+            CommandRequest::UnshareFile(params) => {
+                handler.unshare_file(&params.share_id)?;
+                to_result("ok")
+            }
+        }
     }
 
-    fn send_response(&self, mut stream: UnixStream, response: Vec<u8>) -> Result<(), IPCError> {
+    fn send_response(&self, mut stream: &UnixStream, response: Vec<u8>) -> Result<(), IPCError> {
         stream
             .write_all(&response)
             .map_err(|e| IPCError::Other(format!("Failed to write response: {}", e)))?;
@@ -280,44 +459,395 @@ impl IPCServer {
         Ok(())
     }
 
-    fn clean_connection(&self, stream: UnixStream) -> Result<(), IPCError> {
-        stream
-            .shutdown(std::net::Shutdown::Both)
-            .map_err(|e| IPCError::Other(format!("Failed to shutdown connection: {}", e)))?;
-        Ok(())
+    /// Stops accepting clients, waits for the requests in progress and
+    /// removes the socket file.
+    pub fn shutdown(mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.listener_handle.take()
+            && handle.join().is_err()
+        {
+            error!("IPC listener thread panicked");
+        }
+        self.remove_socket();
+        info!("IPC server stopped");
     }
+}
 
-    pub fn shutdown(mut self) {
-        if self.shutdown.load(Ordering::Relaxed) {
-            if let Some(handle) = self.listener_handle.take() {
-                let _ = handle.join().expect("Failed to join listener thread");
+/// Per-connection state. Responses and event notifications share one
+/// writer so their lines never interleave.
+struct Session {
+    writer: Arc<Mutex<UnixStream>>,
+    subscribed: AtomicBool,
+    closed: Arc<AtomicBool>,
+}
+
+impl Session {
+    fn new(stream: &UnixStream) -> Result<Self, IPCError> {
+        Ok(Self {
+            writer: Arc::new(Mutex::new(clone_stream(stream)?)),
+            subscribed: AtomicBool::new(false),
+            closed: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    fn is_subscribed(&self) -> bool {
+        self.subscribed.load(Ordering::Relaxed)
+    }
+
+    fn send(&self, bytes: &[u8]) -> Result<(), IPCError> {
+        write_line(&self.writer, bytes)
+    }
+
+    /// Pushes `events` to the client as notifications until either side
+    /// goes away.
+    fn forward(&self, events: Receiver<DaemonEvent>) {
+        self.subscribed.store(true, Ordering::Relaxed);
+        let writer = Arc::clone(&self.writer);
+        let closed = Arc::clone(&self.closed);
+        thread::spawn(move || {
+            while !closed.load(Ordering::Relaxed) {
+                let event = match events.recv_timeout(EVENT_POLL_INTERVAL) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                let notification = json!({
+                    "jsonrpc": JSONRPC_VERSION,
+                    "method": EVENT_METHOD,
+                    "params": event,
+                });
+                let Some(line) = encode(&notification) else {
+                    continue;
+                };
+                if write_line(&writer, &line).is_err() {
+                    break;
+                }
             }
-        }
-        println!("Shutdown complete.");
+            debug!("Event subscription ended");
+        });
     }
 }
 
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+fn write_line(writer: &Mutex<UnixStream>, bytes: &[u8]) -> Result<(), IPCError> {
+    let mut stream = writer
+        .lock()
+        .map_err(|_| IPCError::Other("Socket lock poisoned".to_string()))?;
+    stream
+        .write_all(bytes)
+        .and_then(|_| stream.flush())
+        .map_err(|e| IPCError::Other(format!("Failed to write response: {}", e)))
+}
+
+fn clone_stream(stream: &UnixStream) -> Result<UnixStream, IPCError> {
+    stream
+        .try_clone()
+        .map_err(|e| IPCError::Other(format!("Failed to clone socket: {}", e)))
+}
+
+fn invalid_request(reason: &str) -> RpcResponse {
+    RpcResponse::error(
+        Value::Null,
+        IPCError::InvalidRequest(reason.to_string()).to_rpc_error(),
+    )
+}
+
+fn to_result<T: Serialize>(value: T) -> Result<Value, IPCError> {
+    serde_json::to_value(value)
+        .map_err(|e| IPCError::Other(format!("Failed to serialize response: {}", e)))
+}
+
+/// One response line.
+fn encode<T: Serialize>(response: &T) -> Option<Vec<u8>> {
+    let mut bytes = serde_json::to_vec(response).ok()?;
+    bytes.push(b'\n');
+    Some(bytes)
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{io::Read, time::Duration};
+    use std::{os::unix::fs::PermissionsExt, time::Duration};
+
+    use lanshare_domain::models::{Peer, PeerEvent, SharedFile, TransferStatus};
 
     use super::*;
+    use crate::message::{SendFileResponse, StatusResponse, WebDropStatus};
+
+    struct MockHandler;
+
+    impl CommandHandler for MockHandler {
+        fn list_peers(&self) -> Result<Vec<Peer>, IPCError> {
+            Ok(vec![Peer::new(
+                "test1".to_string(),
+                "127.0.0.1:8080".parse().unwrap(),
+                0,
+            )])
+        }
+
+        fn send_file(
+            &self,
+            _path: &str,
+            _peer: &str,
+            _expires_in: Option<u64>,
+        ) -> Result<SendFileResponse, IPCError> {
+            Err(IPCError::PeerNotFound)
+        }
+
+        fn get_status(&self) -> Result<StatusResponse, IPCError> {
+            Ok(StatusResponse {
+                transfers: Vec::new(),
+                outbox: Vec::new(),
+                offers: Vec::new(),
+            })
+        }
+
+        fn cancel_transfer(&self, _transfer_id: &str) -> Result<(), IPCError> {
+            Ok(())
+        }
+
+        fn pause_transfer(&self, _transfer_id: &str) -> Result<(), IPCError> {
+            Err(IPCError::TransferNotFound)
+        }
+
+        fn resume_transfer(&self, _transfer_id: &str) -> Result<(), IPCError> {
+            Err(IPCError::TransferNotFound)
+        }
+
+        fn get_history(&self) -> Result<Vec<TransferStatus>, IPCError> {
+            Ok(Vec::new())
+        }
+
+        fn accept_offer(&self, _offer_id: &str) -> Result<(), IPCError> {
+            Err(IPCError::TransferNotFound)
+        }
+
+        fn reject_offer(&self, _offer_id: &str) -> Result<(), IPCError> {
+            Err(IPCError::TransferNotFound)
+        }
+
+        fn add_peer(
+            &self,
+            _address: &str,
+            _alias: Option<&str>,
+            _tags: Vec<String>,
+        ) -> Result<Peer, IPCError> {
+            Err(IPCError::InvalidJson)
+        }
+
+        fn remove_peer(&self, _peer: &str) -> Result<(), IPCError> {
+            Err(IPCError::PeerNotFound)
+        }
+
+        fn alias_peer(&self, _peer: &str, _alias: Option<&str>) -> Result<Peer, IPCError> {
+            Err(IPCError::PeerNotFound)
+        }
+
+        fn tag_peer(&self, _peer: &str, _tags: Vec<String>) -> Result<Peer, IPCError> {
+            Err(IPCError::PeerNotFound)
+        }
+
+        fn get_config(&self) -> Result<lanshare_config::Config, IPCError> {
+            Ok(lanshare_config::Config::default())
+        }
+
+        fn reload_config(&self) -> Result<lanshare_config::ReloadReport, IPCError> {
+            Ok(lanshare_config::ReloadReport::default())
+        }
+
+        fn shutdown(&self) -> Result<(), IPCError> {
+            Ok(())
+        }
+
+        fn metrics(&self) -> Result<String, IPCError> {
+            Ok(String::new())
+        }
+
+        fn subscribe(&self) -> Result<Receiver<DaemonEvent>, IPCError> {
+            let (tx, rx) = std::sync::mpsc::channel();
+            tx.send(DaemonEvent::Peer(PeerEvent::Added(Peer::new(
+                "test2".to_string(),
+                "127.0.0.1:8081".parse().unwrap(),
+                0,
+            ))))
+            .unwrap();
+            // Keep the channel open like a live daemon would.
+            std::mem::forget(tx);
+            Ok(rx)
+        }
+
+        fn web_drop_status(&self) -> Result<WebDropStatus, IPCError> {
+            Err(IPCError::InvalidConfig("web drop is disabled".to_string()))
+        }
+
+        fn share_file(&self, _path: &str) -> Result<SharedFile, IPCError> {
+            Err(IPCError::FileNotFound)
+        }
+
+        fn unshare_file(&self, _share_id: &str) -> Result<(), IPCError> {
+            Ok(())
+        }
+    }
 
     #[test]
     fn test_handle_list_peers() {
         let socket_path = PathBuf::from("/tmp/lanshare-ipc-test.sock");
         let shutdown = Arc::new(AtomicBool::new(false));
-        let mut server = IPCServer::new(socket_path.clone(), shutdown);
+        let mut server = IPCServer::new(socket_path.clone(), shutdown, Arc::new(MockHandler));
         let server_handle = thread::spawn(move || server.start().unwrap());
         thread::sleep(Duration::from_millis(50));
 
         let mut stream = UnixStream::connect(socket_path).unwrap();
-        stream.write_all(b"hello world\n").unwrap();
-        let mut response = String::new();
-        stream.read_to_string(&mut response).unwrap();
-        assert_eq!(response, "ok");
+        stream
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"method\": \"list_peers\", \"id\": 7}\n")
+            .unwrap();
+        let response = read_response(&mut BufReader::new(stream));
+        assert_eq!(response["jsonrpc"], "2.0");
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"][0]["name"], "test1");
         server_handle.join().expect("Server thread panicked");
 
         println!("Test completed successfully!");
     }
+
+    fn read_response(reader: &mut BufReader<UnixStream>) -> serde_json::Value {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn one_connection_serves_several_requests() {
+        let socket_path = PathBuf::from("/tmp/lanshare-ipc-session-test.sock");
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let mut server = IPCServer::new(socket_path.clone(), shutdown, Arc::new(MockHandler));
+        server.start().unwrap();
+        let mode = fs::metadata(&socket_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let mut stream = UnixStream::connect(&socket_path).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        stream
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"method\": \"list_peers\", \"id\": 1}\nnot json\n")
+            .unwrap();
+        stream
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"method\": \"get_status\", \"id\": \"two\"}\n")
+            .unwrap();
+
+        assert_eq!(read_response(&mut reader)["id"], 1);
+        assert_eq!(read_response(&mut reader)["error"]["code"], -32700);
+        let status = read_response(&mut reader);
+        assert_eq!(status["id"], "two");
+        assert!(status["result"]["transfers"].is_array());
+
+        // A second client is served while the first one stays connected.
+        let mut other = UnixStream::connect(&socket_path).unwrap();
+        other
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"method\": \"list_peers\", \"id\": 3}\n")
+            .unwrap();
+        assert_eq!(read_response(&mut BufReader::new(other))["id"], 3);
+
+        server.shutdown();
+    }
+
+    fn respond(line: &str) -> Option<serde_json::Value> {
+        let server = IPCServer::new(
+            PathBuf::from("/unused"),
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(MockHandler),
+        );
+        let (stream, _peer) = UnixStream::pair().unwrap();
+        let session = Session::new(&stream).unwrap();
+        server
+            .respond_to_line(line, &session)
+            .map(|bytes| serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[test]
+    fn errors_use_json_rpc_codes() {
+        let unknown = respond(r#"{"jsonrpc": "2.0", "method": "fly", "id": 1}"#).unwrap();
+        assert_eq!(unknown["error"]["code"], -32601);
+
+        let bad_params =
+            respond(r#"{"jsonrpc": "2.0", "method": "remove_peer", "params": {}, "id": 2}"#)
+                .unwrap();
+        assert_eq!(bad_params["error"]["code"], -32602);
+
+        let handler_error =
+            respond(r#"{"jsonrpc": "2.0", "method": "remove_peer", "params": ["a"], "id": 3}"#)
+                .unwrap();
+        assert_eq!(handler_error["error"]["code"], -32001);
+        assert_eq!(handler_error["error"]["data"]["code"], "PEER_NOT_FOUND");
+
+        let not_rpc = respond(r#"{"method": "list_peers", "id": 4}"#).unwrap();
+        assert_eq!(not_rpc["error"]["code"], -32600);
+        assert_eq!(not_rpc["id"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn batches_skip_notifications() {
+        let batch = respond(
+            r#"[
+                {"jsonrpc": "2.0", "method": "list_peers", "id": 1},
+                {"jsonrpc": "2.0", "method": "reload_config"},
+                {"jsonrpc": "2.0", "method": "metrics", "id": 2}
+            ]"#,
+        )
+        .unwrap();
+        let ids: Vec<_> = batch
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|response| response["id"].clone())
+            .collect();
+        assert_eq!(ids, [1, 2]);
+
+        assert!(respond(r#"{"jsonrpc": "2.0", "method": "shutdown"}"#).is_none());
+        assert_eq!(respond("[]").unwrap()["error"]["code"], -32600);
+    }
+
+    #[test]
+    fn discover_lists_every_method() {
+        let document = respond(r#"{"jsonrpc": "2.0", "method": "rpc.discover", "id": 1}"#).unwrap()
+            ["result"]
+            .take();
+        let methods = document["methods"].as_array().unwrap();
+        for method in methods {
+            let name = method["name"].as_str().unwrap();
+            // Every documented method must reach the handler, not fail as unknown.
+            let request = json!({"jsonrpc": "2.0", "method": name, "params": {}, "id": 1});
+            let response = respond(&request.to_string()).unwrap();
+            assert_ne!(response["error"]["code"], -32601, "{}", name);
+        }
+        assert!(document["components"]["schemas"]["Peer"].is_object());
+        let send_file = methods.iter().find(|m| m["name"] == "send_file").unwrap();
+        assert_eq!(send_file["params"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn subscribed_connections_receive_events() {
+        let socket_path = PathBuf::from("/tmp/lanshare-ipc-events-test.sock");
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let mut server = IPCServer::new(socket_path.clone(), shutdown, Arc::new(MockHandler));
+        server.start().unwrap();
+
+        let mut stream = UnixStream::connect(&socket_path).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        stream
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"method\": \"subscribe\", \"id\": 1}\n")
+            .unwrap();
+
+        let mut lines = [read_response(&mut reader), read_response(&mut reader)];
+        lines.sort_by_key(|line| line.get("id").is_none());
+        assert_eq!(lines[0]["result"], "ok");
+        assert_eq!(lines[1]["method"], "event");
+        assert_eq!(lines[1]["params"]["kind"], "peer");
+        assert_eq!(lines[1]["params"]["data"]["peer"]["name"], "test2");
+
+        server.shutdown();
+    }
 }
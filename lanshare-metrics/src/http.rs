@@ -0,0 +1,54 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use lanshare_domain::error::DomainError;
+use tiny_http::{Header, Response, Server};
+use tracing::{debug, info, warn};
+
+use crate::prometheus::PrometheusMetrics;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Serves `GET /metrics` on `address` until `shutdown` is set.
+pub fn serve(
+    address: SocketAddr,
+    metrics: Arc<PrometheusMetrics>,
+    shutdown: Arc<AtomicBool>,
+) -> Result<JoinHandle<()>, DomainError> {
+    let server = Server::http(address).map_err(|e| DomainError::IoError(e.to_string()))?;
+    info!(address = %address, "Serving metrics");
+
+    Ok(thread::spawn(move || {
+        while !shutdown.load(Ordering::Relaxed) {
+            let request = match server.recv_timeout(POLL_INTERVAL) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(error = %e, "Metrics endpoint failed");
+                    break;
+                }
+            };
+            debug!(method = %request.method(), url = request.url(), "Metrics request");
+
+            let response = match (request.method().as_str(), request.url()) {
+                ("GET", "/metrics") => {
+                    let header = Header::from_bytes("Content-Type", CONTENT_TYPE)
+                        .expect("static header is valid");
+                    Response::from_string(metrics.encode()).with_header(header)
+                }
+                _ => Response::from_string("Not Found").with_status_code(404),
+            };
+            if let Err(e) = request.respond(response) {
+                debug!(error = %e, "Failed to answer metrics request");
+            }
+        }
+    }))
+}
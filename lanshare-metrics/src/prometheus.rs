@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use lanshare_domain::{
+    models::{Direction, TransferOutcome},
+    ports::MetricsPort,
+};
+use prometheus_client::{
+    encoding::{EncodeLabelSet, text::encode},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        gauge::Gauge,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::Registry,
+};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct DirectionLabels {
+    direction: &'static str,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct TransferLabels {
+    direction: &'static str,
+    outcome: &'static str,
+}
+
+fn direction(direction: Direction) -> DirectionLabels {
+    DirectionLabels {
+        direction: direction.as_str(),
+    }
+}
+
+fn chunk_histogram() -> Histogram {
+    // 100µs up to ~6.5s, one bucket per 4x.
+    Histogram::new(exponential_buckets(0.0001, 4.0, 9))
+}
+
+/// Collects daemon metrics and renders them in the Prometheus text format.
+pub struct PrometheusMetrics {
+    registry: Registry,
+    bytes: Family<DirectionLabels, Counter>,
+    transfers: Family<TransferLabels, Counter>,
+    chunk_latency: Family<DirectionLabels, Histogram, fn() -> Histogram>,
+    integrity_failures: Counter,
+    active_connections: Family<DirectionLabels, Gauge>,
+    discovered_peers: Gauge,
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::with_prefix("lanshare");
+        let bytes = Family::<DirectionLabels, Counter>::default();
+        let transfers = Family::<TransferLabels, Counter>::default();
+        let chunk_latency =
+            Family::<DirectionLabels, Histogram, fn() -> Histogram>::new_with_constructor(
+                chunk_histogram,
+            );
+        let integrity_failures = Counter::default();
+        let active_connections = Family::<DirectionLabels, Gauge>::default();
+        let discovered_peers = Gauge::default();
+
+        registry.register("bytes", "File bytes transferred", bytes.clone());
+        registry.register(
+            "transfers",
+            "Finished transfers by outcome",
+            transfers.clone(),
+        );
+        registry.register(
+            "chunk_duration_seconds",
+            "Time to send or store one chunk",
+            chunk_latency.clone(),
+        );
+        registry.register(
+            "integrity_failures",
+            "Received files whose checksum did not match",
+            integrity_failures.clone(),
+        );
+        registry.register(
+            "active_connections",
+            "Open transfer connections",
+            active_connections.clone(),
+        );
+        registry.register(
+            "discovered_peers",
+            "Peers currently visible on the network",
+            discovered_peers.clone(),
+        );
+
+        Self {
+            registry,
+            bytes,
+            transfers,
+            chunk_latency,
+            integrity_failures,
+            active_connections,
+            discovered_peers,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        // Writing into a String cannot fail.
+        let _ = encode(&mut text, &self.registry);
+        text
+    }
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsPort for PrometheusMetrics {
+    fn record_bytes(&self, dir: Direction, bytes: u64) {
+        self.bytes.get_or_create(&direction(dir)).inc_by(bytes);
+    }
+
+    fn record_chunk_latency(&self, dir: Direction, latency: Duration) {
+        self.chunk_latency
+            .get_or_create(&direction(dir))
+            .observe(latency.as_secs_f64());
+    }
+
+    fn record_transfer(&self, dir: Direction, outcome: TransferOutcome) {
+        if outcome == TransferOutcome::IntegrityFailed {
+            self.integrity_failures.inc();
+        }
+        self.transfers
+            .get_or_create(&TransferLabels {
+                direction: dir.as_str(),
+                outcome: outcome.as_str(),
+            })
+            .inc();
+    }
+
+    fn connection_opened(&self, dir: Direction) {
+        self.active_connections.get_or_create(&direction(dir)).inc();
+    }
+
+    fn connection_closed(&self, dir: Direction) {
+        self.active_connections.get_or_create(&direction(dir)).dec();
+    }
+
+    fn set_discovered_peers(&self, count: usize) {
+        self.discovered_peers.set(count as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_values_show_up_in_the_text_format() {
+        let metrics = PrometheusMetrics::new();
+        metrics.record_bytes(Direction::Outbound, 4096);
+        metrics.record_transfer(Direction::Inbound, TransferOutcome::IntegrityFailed);
+        metrics.connection_opened(Direction::Inbound);
+        metrics.set_discovered_peers(3);
+
+        let text = metrics.encode();
+        assert!(text.contains("lanshare_bytes_total{direction=\"outbound\"} 4096"));
+        assert!(text.contains(
+            "lanshare_transfers_total{direction=\"inbound\",outcome=\"integrity_failed\"} 1"
+        ));
+        assert!(text.contains("lanshare_integrity_failures_total 1"));
+        assert!(text.contains("lanshare_active_connections{direction=\"inbound\"} 1"));
+        assert!(text.contains("lanshare_discovered_peers 3"));
+    }
+}
@@ -1,21 +1,32 @@
 use std::{
-    io::Write,
-    net::{TcpListener, TcpStream},
-    sync::Arc,
-    thread,
+    io::{ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
 };
 
 use lanshare_app::use_cases::receive_file::ReceiveFileUseCase;
 use lanshare_domain::{
     error::DomainError,
-    models::{FileBlock, FileManifest, Peer},
+    models::{Direction, FileBlock, FileManifest, Peer},
     ports::{NetworkConnection, NetworkPort, StoragePort},
 };
 use lanshare_proto::{
     codec::{decode_message, encode_message},
-    messages::{ErrorPayload, LanShareMessage, TransferResponsePayload},
+    messages::{ErrorPayload, INTEGRITY_FAILED, LanShareMessage, TransferResponsePayload},
 };
-use uuid::Uuid;
+use tracing::{Span, debug, error, field::Empty, info, info_span, warn};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+/// Senders only read the transfer response, which may wait for the receiving
+/// user to accept the offer.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(300);
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct TCPConnection {
     socket: TcpStream,
@@ -23,11 +34,19 @@ pub struct TCPConnection {
 
 impl NetworkConnection for TCPConnection {
     fn send(&mut self, data: &[u8]) -> Result<(), DomainError> {
-        self.socket.write_all(data)?;
-        Ok(())
+        self.socket
+            .write_all(data)
+            .map_err(|e| DomainError::ConnectionFailed(e.to_string()))
+    }
+
+    fn receive(&mut self, buf: &mut [u8]) -> Result<usize, DomainError> {
+        self.socket
+            .read(buf)
+            .map_err(|e| DomainError::ConnectionFailed(e.to_string()))
     }
 }
 
+#[derive(Default)]
 pub struct TcpNetworkAdapter {}
 
 impl TcpNetworkAdapter {
@@ -38,34 +57,87 @@ impl TcpNetworkAdapter {
 
 impl NetworkPort for TcpNetworkAdapter {
     fn connect(&self, peer: &Peer) -> Result<Box<dyn NetworkConnection>, DomainError> {
-        let socket = TcpStream::connect(peer.address())?;
+        let socket = TcpStream::connect_timeout(peer.address(), CONNECT_TIMEOUT)
+            .map_err(|e| DomainError::ConnectionFailed(e.to_string()))?;
+        socket
+            .set_read_timeout(Some(RESPONSE_TIMEOUT))
+            .and_then(|_| socket.set_write_timeout(Some(IO_TIMEOUT)))
+            .map_err(|e| DomainError::ConnectionFailed(e.to_string()))?;
         Ok(Box::new(TCPConnection { socket }))
     }
 }
 
 impl TcpNetworkAdapter {
-    pub fn start_listening<S: StoragePort + 'static>(
+    /// Binds the transfer listener; port 0 picks a free port, which the
+    /// caller reads back from `local_addr` to advertise.
+    pub fn bind(address: SocketAddr) -> Result<TcpListener, DomainError> {
+        let listener =
+            TcpListener::bind(address).map_err(|e| DomainError::IoError(e.to_string()))?;
+        if let Ok(local) = listener.local_addr() {
+            info!(address = %local, "Listening for transfers");
+        }
+        Ok(listener)
+    }
+
+    /// Accepts transfers until `shutdown` is set, then closes the open
+    /// connections and waits for their handlers. Received data is persisted
+    /// chunk by chunk, so interrupted transfers resume on the next attempt.
+    pub fn serve<S: StoragePort + 'static>(
+        listener: TcpListener,
         use_case: Arc<ReceiveFileUseCase<S>>,
+        shutdown: Arc<AtomicBool>,
     ) -> Result<(), DomainError> {
-        let listener =
-            TcpListener::bind("0.0.0.0:8080").map_err(|e| DomainError::IoError(e.to_string()))?;
-        println!("Listening on 0.0.0.0:8080...");
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| DomainError::IoError(e.to_string()))?;
+        let mut connections: Vec<(TcpStream, JoinHandle<()>)> = Vec::new();
 
-        for stream in listener.incoming() {
-            match stream {
-                Ok(stream) => {
-                    println!("New connection: {}", stream.peer_addr().unwrap());
+        while !shutdown.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, address)) => {
+                    debug!(peer = %address, "New transfer connection");
+                    let control = match stream
+                        .set_nonblocking(false)
+                        .and_then(|_| stream.try_clone())
+                    {
+                        Ok(control) => control,
+                        Err(e) => {
+                            warn!(peer = %address, error = %e, "Failed to set up connection");
+                            continue;
+                        }
+                    };
                     let use_case_clone = Arc::clone(&use_case);
 
-                    thread::spawn(move || {
-                        if let Err(e) = handle_connection(stream, use_case_clone) {
-                            eprintln!("Connection error: {:?}", e);
+                    let span = info_span!("receive", peer = %address, file = Empty, sha256 = Empty);
+                    let handle = thread::spawn(move || {
+                        let _entered = span.enter();
+                        let _active = use_case_clone.begin_transfer();
+                        use_case_clone
+                            .metrics()
+                            .connection_opened(Direction::Inbound);
+                        let result = handle_connection(stream, Arc::clone(&use_case_clone));
+                        use_case_clone.report_outcome(&result);
+                        use_case_clone
+                            .metrics()
+                            .connection_closed(Direction::Inbound);
+                        if let Err(e) = result {
+                            warn!(error = ?e, "Incoming transfer failed");
                         }
                     });
+                    connections.push((control, handle));
                 }
-                Err(e) => eprintln!("Error accepting connection: {}", e),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL_INTERVAL),
+                Err(e) => error!(error = %e, "Error accepting connection"),
             }
+            connections.retain(|(_, handle)| !handle.is_finished());
         }
+
+        drop(listener);
+        for (stream, handle) in connections {
+            let _ = stream.shutdown(Shutdown::Both);
+            let _ = handle.join();
+        }
+        info!("Transfer listener stopped");
         Ok(())
     }
 }
@@ -75,24 +147,39 @@ fn handle_connection<S: StoragePort>(
     use_case: Arc<ReceiveFileUseCase<S>>,
 ) -> Result<(), DomainError> {
     let first_msg = decode_message(&mut stream).map_err(|_| DomainError::ProtocolError)?;
-    let mut current_file_id = String::new();
+    if let LanShareMessage::StreamRequest(payload) = first_msg {
+        return receive_stream(stream, payload.name, &use_case);
+    }
+    let current_file_id;
+    let expected_size;
+    let resume_offset;
 
     if let LanShareMessage::TransferRequest(payload) = first_msg {
-        let local_file_id = Uuid::new_v4().to_string();
-        current_file_id = local_file_id.clone();
+        // Keyed by content hash so a retried transfer resumes the same partial file.
+        current_file_id = sha_to_hex(&payload.sha256);
+        expected_size = payload.size;
+        let span = Span::current();
+        span.record("file", payload.name.as_str());
+        span.record("sha256", current_file_id.as_str());
 
         let manifest = FileManifest {
-            file_id: local_file_id,
+            file_id: current_file_id.clone(),
             name: payload.name,
             size: payload.size,
             sha256: payload.sha256,
         };
 
-        let written_bytes = use_case.accept_transfer(&manifest)?;
-
+        let sender = stream
+            .peer_addr()
+            .map_err(|e| DomainError::IoError(e.to_string()))?;
+        let written_bytes = match use_case.accept_transfer(&manifest, sender) {
+            Ok(written_bytes) => Some(written_bytes),
+            Err(DomainError::TransferRejected) => None,
+            Err(e) => return Err(e),
+        };
         let response = LanShareMessage::TransferResponse(TransferResponsePayload {
-            accepted: true,
-            resume_offset: written_bytes,
+            accepted: written_bytes.is_some(),
+            resume_offset: written_bytes.unwrap_or(0),
         });
 
         let mut buffer = Vec::new();
@@ -100,6 +187,20 @@ fn handle_connection<S: StoragePort>(
         stream
             .write_all(&buffer)
             .map_err(|e| DomainError::IoError(e.to_string()))?;
+        match written_bytes {
+            Some(offset) => {
+                info!(
+                    size = expected_size,
+                    resume_offset = offset,
+                    "Accepted transfer"
+                );
+                resume_offset = offset;
+            }
+            None => {
+                info!("Rejected transfer by receive policy");
+                return Err(DomainError::TransferRejected);
+            }
+        }
     } else {
         send_error_to_peer(
             &mut stream,
@@ -108,9 +209,11 @@ fn handle_connection<S: StoragePort>(
         return Err(DomainError::ProtocolError);
     }
 
-    loop {
+    let mut received = resume_offset;
+    while received < expected_size {
         match decode_message(&mut stream) {
             Ok(LanShareMessage::DataChunk(payload)) => {
+                received = payload.offset + payload.data.len() as u64;
                 let block = FileBlock {
                     file_id: current_file_id.clone(),
                     offset: payload.offset,
@@ -118,7 +221,11 @@ fn handle_connection<S: StoragePort>(
                 };
                 use_case.process_chunk(&block)?;
             }
-            Ok(LanShareMessage::TransferRequest(_)) | Ok(LanShareMessage::TransferResponse(_)) => {
+            Ok(LanShareMessage::TransferRequest(_))
+            | Ok(LanShareMessage::TransferResponse(_))
+            | Ok(LanShareMessage::TransferComplete)
+            | Ok(LanShareMessage::StreamRequest(_))
+            | Ok(LanShareMessage::StreamEnd(_)) => {
                 send_error_to_peer(
                     &mut stream,
                     "Protocol violation: Expected DataChunk, but received another handshake message.",
@@ -126,7 +233,7 @@ fn handle_connection<S: StoragePort>(
                 return Err(DomainError::ProtocolError);
             }
             Ok(LanShareMessage::Error(err)) => {
-                eprintln!("Peer sent an error: {}", err.message);
+                warn!(message = %err.message, "Peer sent an error");
                 return Err(DomainError::ProtocolError);
             }
             Err(_) => {
@@ -135,11 +242,134 @@ fn handle_connection<S: StoragePort>(
         }
     }
 
-    use_case.finish_transfer(&current_file_id)?;
-    println!("File transfer completed: {}", current_file_id);
+    let written_bytes = use_case.written_bytes(&current_file_id)?;
+    if written_bytes < expected_size {
+        return Err(DomainError::ConnectionFailed(format!(
+            "Connection closed after {} of {} bytes, keeping partial file for resume",
+            written_bytes, expected_size
+        )));
+    }
+
+    if let Err(e) = use_case.finish_transfer(&current_file_id) {
+        if matches!(e, DomainError::IntegrityError) {
+            // Resuming would only repeat the mismatch, so the sender starts over.
+            let _ = use_case.discard_transfer(&current_file_id);
+            send_error_to_peer(&mut stream, INTEGRITY_FAILED);
+        } else {
+            send_error_to_peer(&mut stream, &format!("Could not store the file: {:?}", e));
+        }
+        return Err(e);
+    }
+    let mut buffer = Vec::new();
+    encode_message(&mut buffer, &LanShareMessage::TransferComplete)
+        .map_err(|_| DomainError::ProtocolError)?;
+    stream
+        .write_all(&buffer)
+        .map_err(|e| DomainError::IoError(e.to_string()))?;
+    info!(bytes = expected_size, "File transfer completed");
     Ok(())
 }
 
+/// Receives a stream into a new partial file, which is dropped unless the
+/// stream arrives whole: without a checksum up front there is nothing to
+/// resume it against.
+fn receive_stream<S: StoragePort>(
+    mut stream: TcpStream,
+    name: String,
+    use_case: &ReceiveFileUseCase<S>,
+) -> Result<(), DomainError> {
+    Span::current().record("file", name.as_str());
+    let sender = stream
+        .peer_addr()
+        .map_err(|e| DomainError::IoError(e.to_string()))?;
+    let file_id = match use_case.accept_stream(&name, sender) {
+        Ok(file_id) => Some(file_id),
+        Err(DomainError::TransferRejected) => None,
+        Err(e) => return Err(e),
+    };
+    let response = LanShareMessage::TransferResponse(TransferResponsePayload {
+        accepted: file_id.is_some(),
+        resume_offset: 0,
+    });
+    let mut buffer = Vec::new();
+    encode_message(&mut buffer, &response).map_err(|_| DomainError::ProtocolError)?;
+    stream
+        .write_all(&buffer)
+        .map_err(|e| DomainError::IoError(e.to_string()))?;
+    let Some(file_id) = file_id else {
+        info!("Rejected stream by receive policy");
+        return Err(DomainError::TransferRejected);
+    };
+    info!("Accepted stream");
+
+    let result = receive_stream_data(&mut stream, &file_id, use_case);
+    if result.is_err() {
+        let _ = use_case.discard_transfer(&file_id);
+    }
+    result
+}
+
+fn receive_stream_data<S: StoragePort>(
+    stream: &mut TcpStream,
+    file_id: &str,
+    use_case: &ReceiveFileUseCase<S>,
+) -> Result<(), DomainError> {
+    let mut received = 0;
+    loop {
+        match decode_message(stream) {
+            Ok(LanShareMessage::DataChunk(payload)) => {
+                if payload.offset != received {
+                    send_error_to_peer(stream, "Protocol violation: Stream chunk out of order.");
+                    return Err(DomainError::ProtocolError);
+                }
+                received += payload.data.len() as u64;
+                use_case.check_stream_size(received)?;
+                use_case.process_chunk(&FileBlock {
+                    file_id: file_id.to_string(),
+                    offset: payload.offset,
+                    data: payload.data,
+                })?;
+            }
+            Ok(LanShareMessage::StreamEnd(end)) => {
+                Span::current().record("sha256", sha_to_hex(&end.sha256).as_str());
+                if let Err(e) = use_case.finish_stream(file_id, end.size, &end.sha256) {
+                    let message = match e {
+                        DomainError::IntegrityError => INTEGRITY_FAILED.to_string(),
+                        _ => format!("Could not store the stream: {:?}", e),
+                    };
+                    send_error_to_peer(stream, &message);
+                    return Err(e);
+                }
+                let mut buffer = Vec::new();
+                encode_message(&mut buffer, &LanShareMessage::TransferComplete)
+                    .map_err(|_| DomainError::ProtocolError)?;
+                stream
+                    .write_all(&buffer)
+                    .map_err(|e| DomainError::IoError(e.to_string()))?;
+                info!(bytes = end.size, "Stream completed");
+                return Ok(());
+            }
+            Ok(LanShareMessage::Error(err)) => {
+                warn!(message = %err.message, "Peer sent an error");
+                return Err(DomainError::ProtocolError);
+            }
+            Ok(_) => {
+                send_error_to_peer(
+                    stream,
+                    "Protocol violation: Expected DataChunk or StreamEnd.",
+                );
+                return Err(DomainError::ProtocolError);
+            }
+            Err(_) => {
+                return Err(DomainError::ConnectionFailed(format!(
+                    "Stream closed after {} bytes without its end",
+                    received
+                )));
+            }
+        }
+    }
+}
+
 fn send_error_to_peer(stream: &mut TcpStream, error_msg: &str) {
     let error_payload = LanShareMessage::Error(ErrorPayload {
         message: error_msg.to_string(),
@@ -149,3 +379,7 @@ fn send_error_to_peer(stream: &mut TcpStream, error_msg: &str) {
         let _ = stream.write_all(&buffer);
     }
 }
+
+fn sha_to_hex(sha: &[u8; 32]) -> String {
+    sha.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -3,8 +3,8 @@ use std::io::{Read, Write};
 use crate::{
     error::ProtoError,
     messages::{
-        DataChunkPayload, ErrorPayload, LanShareMessage, TransferRequestPayload,
-        TransferResponsePayload,
+        DataChunkPayload, ErrorPayload, LanShareMessage, StreamEndPayload, StreamRequestPayload,
+        TransferRequestPayload, TransferResponsePayload,
     },
 };
 
@@ -15,11 +15,7 @@ pub fn encode_message<W: Write>(
     match message {
         LanShareMessage::TransferRequest(TransferRequestPayload { name, size, sha256 }) => {
             writer.write_all(b"TQ")?;
-            let mut name_buf = [0u8; 256];
-            let name_bytes = name.as_bytes();
-            let name_len = name_bytes.len().min(256);
-            name_buf[..name_len].copy_from_slice(&name_bytes[..name_len]);
-            writer.write_all(&name_buf)?;
+            write_name(writer, name)?;
             let size_buf = size.to_le_bytes();
             writer.write_all(&size_buf)?;
             writer.write_all(sha256)?;
@@ -49,20 +45,48 @@ pub fn encode_message<W: Write>(
             writer.write_all(msg_bytes)?;
             Ok(())
         }
+        LanShareMessage::TransferComplete => {
+            writer.write_all(b"TC")?;
+            Ok(())
+        }
+        LanShareMessage::StreamRequest(StreamRequestPayload { name }) => {
+            writer.write_all(b"SQ")?;
+            write_name(writer, name)?;
+            Ok(())
+        }
+        LanShareMessage::StreamEnd(StreamEndPayload { size, sha256 }) => {
+            writer.write_all(b"SE")?;
+            writer.write_all(&size.to_le_bytes())?;
+            writer.write_all(sha256)?;
+            Ok(())
+        }
     }
 }
 
+fn write_name<W: Write>(writer: &mut W, name: &str) -> Result<(), ProtoError> {
+    let mut name_buf = [0u8; 256];
+    let name_bytes = name.as_bytes();
+    let name_len = name_bytes.len().min(256);
+    name_buf[..name_len].copy_from_slice(&name_bytes[..name_len]);
+    writer.write_all(&name_buf)?;
+    Ok(())
+}
+
+fn read_name<R: Read>(reader: &mut R) -> Result<String, ProtoError> {
+    let mut name_buf = [0u8; 256];
+    reader.read_exact(&mut name_buf)?;
+    Ok(String::from_utf8_lossy(&name_buf)
+        .trim_end_matches(char::from(0))
+        .to_string())
+}
+
 pub fn decode_message<R: Read>(reader: &mut R) -> Result<LanShareMessage, ProtoError> {
     let mut buffer = [0; 2];
     reader.read_exact(&mut buffer)?;
 
     match buffer {
         [b'T', b'Q'] => {
-            let mut name_buf = [0u8; 256];
-            reader.read_exact(&mut name_buf)?;
-            let name = String::from_utf8_lossy(&name_buf)
-                .trim_end_matches(char::from(0))
-                .to_string();
+            let name = read_name(reader)?;
             let mut size_buf = [0u8; 8];
             reader.read_exact(&mut size_buf)?;
             let size = u64::from_le_bytes(size_buf);
@@ -112,6 +136,67 @@ pub fn decode_message<R: Read>(reader: &mut R) -> Result<LanShareMessage, ProtoE
 
             Ok(LanShareMessage::Error(ErrorPayload { message }))
         }
+        [b'T', b'C'] => Ok(LanShareMessage::TransferComplete),
+        [b'S', b'Q'] => {
+            let name = read_name(reader)?;
+            Ok(LanShareMessage::StreamRequest(StreamRequestPayload {
+                name,
+            }))
+        }
+        [b'S', b'E'] => {
+            let mut size_buf = [0u8; 8];
+            reader.read_exact(&mut size_buf)?;
+            let mut hash_buf = [0u8; 32];
+            reader.read_exact(&mut hash_buf)?;
+            Ok(LanShareMessage::StreamEnd(StreamEndPayload {
+                size: u64::from_le_bytes(size_buf),
+                sha256: hash_buf,
+            }))
+        }
         _ => Err(ProtoError::InvalidMessage),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_complete_round_trips() {
+        let mut buffer = Vec::new();
+        encode_message(&mut buffer, &LanShareMessage::TransferComplete).unwrap();
+        assert_eq!(buffer, b"TC");
+        assert!(matches!(
+            decode_message(&mut buffer.as_slice()).unwrap(),
+            LanShareMessage::TransferComplete
+        ));
+    }
+
+    #[test]
+    fn stream_messages_round_trip() {
+        let mut buffer = Vec::new();
+        let request = LanShareMessage::StreamRequest(StreamRequestPayload {
+            name: "backup.tar".to_string(),
+        });
+        let end = LanShareMessage::StreamEnd(StreamEndPayload {
+            size: 1 << 33,
+            sha256: [7; 32],
+        });
+        encode_message(&mut buffer, &request).unwrap();
+        encode_message(&mut buffer, &end).unwrap();
+
+        let mut reader = buffer.as_slice();
+        match decode_message(&mut reader).unwrap() {
+            LanShareMessage::StreamRequest(payload) => assert_eq!(payload.name, "backup.tar"),
+            _ => panic!("expected a stream request"),
+        }
+        match decode_message(&mut reader).unwrap() {
+            LanShareMessage::StreamEnd(payload) => {
+                assert_eq!(payload.size, 1 << 33);
+                assert_eq!(payload.sha256, [7; 32]);
+            }
+            _ => panic!("expected a stream end"),
+        }
+        assert!(reader.is_empty());
+    }
+}
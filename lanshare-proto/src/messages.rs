@@ -1,8 +1,16 @@
+pub const PROTOCOL_VERSION: u32 = 1;
+
 pub enum LanShareMessage {
     TransferRequest(TransferRequestPayload),
     TransferResponse(TransferResponsePayload),
     DataChunk(DataChunkPayload),
     Error(ErrorPayload),
+    /// Sent by the receiver once the file is verified and stored; a failure
+    /// there is reported with `Error` instead.
+    TransferComplete,
+    /// Opens a transfer whose size and checksum follow in `StreamEnd`.
+    StreamRequest(StreamRequestPayload),
+    StreamEnd(StreamEndPayload),
 }
 
 pub struct TransferRequestPayload {
@@ -22,3 +30,16 @@ pub struct DataChunkPayload {
 pub struct ErrorPayload {
     pub message: String,
 }
+
+/// `Error` message telling the sender its data did not match the checksum,
+/// so the partial file is gone and the next attempt starts over.
+pub const INTEGRITY_FAILED: &str = "Integrity check failed";
+
+pub struct StreamRequestPayload {
+    pub name: String,
+}
+
+pub struct StreamEndPayload {
+    pub size: u64,
+    pub sha256: [u8; 32],
+}
@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use lanshare_config::{ConfigError, Overrides};
+
+pub enum Invocation {
+    Run(Overrides),
+    Help,
+}
+
+pub fn parse(args: &[String]) -> Result<Invocation, ConfigError> {
+    let mut overrides = Overrides::default();
+    let mut args = args.iter().skip(1);
+
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| ConfigError::InvalidArgument(format!("{} needs a value", flag)))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Invocation::Help),
+            "--config" => overrides.config_path = Some(PathBuf::from(value()?)),
+            "--name" => overrides.set("daemon.name", value()?),
+            "--port" => overrides.set("daemon.port", value()?),
+            "--storage-dir" => overrides.set("daemon.storage_dir", value()?),
+            "--socket-path" => overrides.set("daemon.socket_path", value()?),
+            "--log-level" => overrides.set("log.level", value()?),
+            "--log-format" => overrides.set("log.format", value()?),
+            "--set" => {
+                let assignment = value()?;
+                let (key, raw) = assignment.split_once('=').ok_or_else(|| {
+                    ConfigError::InvalidArgument(format!(
+                        "expected key=value, got '{}'",
+                        assignment
+                    ))
+                })?;
+                overrides.set(key.trim(), raw);
+            }
+            unknown => {
+                return Err(ConfigError::InvalidArgument(format!(
+                    "unknown flag '{}'",
+                    unknown
+                )));
+            }
+        }
+    }
+    Ok(Invocation::Run(overrides))
+}
+
+pub fn print_usage() {
+    eprintln!("Usage: lanshare-rs [options]");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!(
+        "  --config <path>        Config file (default: $XDG_CONFIG_HOME/lanshare/config.toml)"
+    );
+    eprintln!("  --name <name>          Name shown to other peers");
+    eprintln!("  --port <port>          Transfer listener port, 0 for any free port");
+    eprintln!("  --storage-dir <path>   Directory for received files and daemon state");
+    eprintln!("  --socket-path <path>   IPC socket used by lanshare-cli");
+    eprintln!("  --log-level <filter>   Log filter, e.g. info or warn,lanshare_network=debug");
+    eprintln!("  --log-format <format>  text or json (journald is used under systemd)");
+    eprintln!("  --set <key>=<value>    Override any config key, e.g. transfer.chunk_size=65536");
+    eprintln!();
+    eprintln!(
+        "Every key can also be set with LANSHARE_<SECTION>_<KEY>, e.g. LANSHARE_DAEMON_PORT."
+    );
+}
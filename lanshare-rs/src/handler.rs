@@ -0,0 +1,386 @@
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::Duration,
+};
+
+use lanshare_app::{
+    offers::IncomingOffers, peer_resolver::resolve_peer, use_cases::send_file::SendFileUseCase,
+};
+use lanshare_config::{Config, ReloadReport};
+use lanshare_discovery::{
+    composite::CompositeDiscoveryAdapter, peer_book::PeerBookDiscoveryAdapter,
+};
+use lanshare_domain::{
+    error::DomainError,
+    models::{Peer, SharedFile, TransferStatus},
+    ports::DiscoveryPort,
+};
+use lanshare_ipc::{
+    CommandHandler, DaemonEvent, IPCError, SendFileResponse, StatusResponse, WebDropStatus,
+};
+use lanshare_metrics::prometheus::PrometheusMetrics;
+use lanshare_network::adapter::TcpNetworkAdapter;
+use lanshare_storage::adapter::LocalFileSystemAdapter;
+use lanshare_webdrop::WebDrop;
+use tracing::{error, info};
+
+use crate::{outbox::Outbox, reload::ConfigReloader, shutdown::Shutdown};
+
+pub type SendFile = SendFileUseCase<
+    Arc<LocalFileSystemAdapter>,
+    Arc<TcpNetworkAdapter>,
+    Arc<CompositeDiscoveryAdapter>,
+>;
+
+pub struct DaemonHandler {
+    discovery: Arc<CompositeDiscoveryAdapter>,
+    peer_book: Arc<PeerBookDiscoveryAdapter>,
+    send_file: Arc<SendFile>,
+    outbox: Arc<Outbox>,
+    reloader: Arc<ConfigReloader>,
+    shutdown: Shutdown,
+    metrics: Arc<PrometheusMetrics>,
+    offers: IncomingOffers,
+    web_drop: Option<Arc<WebDrop>>,
+}
+
+impl DaemonHandler {
+    pub fn new(
+        discovery: Arc<CompositeDiscoveryAdapter>,
+        peer_book: Arc<PeerBookDiscoveryAdapter>,
+        send_file: Arc<SendFile>,
+        outbox: Arc<Outbox>,
+        reloader: Arc<ConfigReloader>,
+        shutdown: Shutdown,
+        metrics: Arc<PrometheusMetrics>,
+    ) -> Self {
+        Self {
+            discovery,
+            peer_book,
+            send_file,
+            outbox,
+            reloader,
+            shutdown,
+            metrics,
+            offers: IncomingOffers::new(),
+            web_drop: None,
+        }
+    }
+
+    /// Offers held by the receive use case, for `accept` and `reject`.
+    pub fn with_offers(mut self, offers: IncomingOffers) -> Self {
+        self.offers = offers;
+        self
+    }
+
+    pub fn with_web_drop(mut self, web_drop: Arc<WebDrop>) -> Self {
+        self.web_drop = Some(web_drop);
+        self
+    }
+
+    fn web_drop(&self) -> Result<&WebDrop, IPCError> {
+        self.web_drop.as_deref().ok_or_else(|| {
+            IPCError::InvalidConfig("web drop is disabled, set web_drop.enabled".to_string())
+        })
+    }
+
+    fn default_expiry(&self) -> Duration {
+        Duration::from_secs(self.reloader.current().transfer.outbox_expiry_secs)
+    }
+
+    /// Runs a registered transfer on its own thread. An interrupted transfer
+    /// resumes from the receiver's partial file once the daemon is back and
+    /// the peer is seen again.
+    fn start_transfer(
+        &self,
+        transfer_id: String,
+        target: Peer,
+        file_path: String,
+        peer: String,
+        expires_in: Duration,
+    ) {
+        let send_file = Arc::clone(&self.send_file);
+        let outbox = Arc::clone(&self.outbox);
+        let guard = self.shutdown.begin_transfer();
+        thread::spawn(move || {
+            let _guard = guard;
+            // Failures are logged inside the transfer span.
+            if let Err(DomainError::Interrupted) = send_file.run(&transfer_id, &target, &file_path)
+            {
+                match outbox.queue(&peer, &file_path, expires_in) {
+                    Ok(_) => info!(transfer_id = %transfer_id, "Queued interrupted transfer"),
+                    Err(e) => {
+                        error!(transfer_id = %transfer_id, error = ?e, "Failed to queue transfer")
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn to_ipc_error(error: DomainError) -> IPCError {
+    match error {
+        DomainError::NotFound(_) => IPCError::FileNotFound,
+        other => IPCError::Other(format!("{:?}", other)),
+    }
+}
+
+fn to_transfer_error(error: DomainError) -> IPCError {
+    match error {
+        DomainError::NotFound(_) => IPCError::TransferNotFound,
+        DomainError::Conflict(message) => IPCError::InvalidArgument(message),
+        other => IPCError::Other(format!("{:?}", other)),
+    }
+}
+
+fn to_peer_book_error(error: DomainError) -> IPCError {
+    match error {
+        DomainError::NotFound(_) => IPCError::PeerNotFound,
+        DomainError::Conflict(message) => IPCError::InvalidArgument(message),
+        other => IPCError::Other(format!("{:?}", other)),
+    }
+}
+
+impl CommandHandler for DaemonHandler {
+    fn list_peers(&self) -> Result<Vec<Peer>, IPCError> {
+        self.discovery.discover_peers().map_err(to_ipc_error)
+    }
+
+    fn send_file(
+        &self,
+        path: &str,
+        peer: &str,
+        expires_in: Option<u64>,
+    ) -> Result<SendFileResponse, IPCError> {
+        if self.shutdown.is_requested() {
+            return Err(IPCError::Other("Daemon is shutting down".to_string()));
+        }
+        // Anything but a directory: named pipes and devices are streamed.
+        match std::fs::metadata(path) {
+            Ok(metadata) if !metadata.is_dir() => {}
+            _ => return Err(IPCError::FileNotFound),
+        }
+        let expires_in = expires_in
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| self.default_expiry());
+
+        let peers = self.discovery.discover_peers().map_err(to_ipc_error)?;
+        let online_peer = match resolve_peer(peer, &peers) {
+            Ok(target) => Some(target),
+            Err(DomainError::AmbiguousPeer(candidates)) => {
+                return Err(IPCError::AmbiguousPeer(candidates));
+            }
+            Err(_) => None,
+        };
+
+        match online_peer {
+            Some(target) => {
+                let transfer_id = self.send_file.enqueue(&target, path);
+                self.start_transfer(
+                    transfer_id.clone(),
+                    target,
+                    path.to_string(),
+                    peer.to_string(),
+                    expires_in,
+                );
+                Ok(SendFileResponse::Started { transfer_id })
+            }
+            None if self.send_file.is_stream(path) => Err(IPCError::InvalidArgument(format!(
+                "{} is offline; streams cannot wait in the outbox",
+                peer
+            ))),
+            None => {
+                let entry = self
+                    .outbox
+                    .queue(peer, path, expires_in)
+                    .map_err(to_ipc_error)?;
+                info!(
+                    peer,
+                    file = path,
+                    "Peer is offline, queued file in the outbox"
+                );
+                Ok(SendFileResponse::Queued {
+                    outbox_id: entry.id,
+                    expires_at: entry.expires_at,
+                })
+            }
+        }
+    }
+
+    fn get_status(&self) -> Result<StatusResponse, IPCError> {
+        let mut transfers = self.send_file.tracker().list();
+        transfers.retain(|transfer| !transfer.state.is_finished());
+        Ok(StatusResponse {
+            transfers,
+            outbox: self.outbox.pending().map_err(to_ipc_error)?,
+            offers: self.offers.pending(),
+        })
+    }
+
+    fn cancel_transfer(&self, transfer_id: &str) -> Result<(), IPCError> {
+        let queued = self
+            .outbox
+            .pending()
+            .map_err(to_ipc_error)?
+            .into_iter()
+            .any(|entry| entry.id == transfer_id);
+        if queued {
+            return self.outbox.remove(transfer_id).map_err(to_ipc_error);
+        }
+        self.send_file
+            .cancel(transfer_id)
+            .map_err(to_transfer_error)
+    }
+
+    fn pause_transfer(&self, transfer_id: &str) -> Result<(), IPCError> {
+        self.send_file.pause(transfer_id).map_err(to_transfer_error)
+    }
+
+    fn resume_transfer(&self, transfer_id: &str) -> Result<(), IPCError> {
+        if self.shutdown.is_requested() {
+            return Err(IPCError::Other("Daemon is shutting down".to_string()));
+        }
+        let (target, file_path) = self
+            .send_file
+            .resume(transfer_id)
+            .map_err(to_transfer_error)?;
+        let peer = target.name.clone();
+        self.start_transfer(
+            transfer_id.to_string(),
+            target,
+            file_path,
+            peer,
+            self.default_expiry(),
+        );
+        Ok(())
+    }
+
+    fn get_history(&self) -> Result<Vec<TransferStatus>, IPCError> {
+        let mut finished = self.send_file.tracker().list();
+        finished.retain(|transfer| transfer.state.is_finished());
+        finished.sort_by_key(|transfer| {
+            transfer
+                .attempts
+                .first()
+                .map(|attempt| attempt.started_at)
+                .unwrap_or(0)
+        });
+        Ok(finished)
+    }
+
+    fn accept_offer(&self, offer_id: &str) -> Result<(), IPCError> {
+        self.offers
+            .decide(offer_id, true)
+            .map_err(to_transfer_error)
+    }
+
+    fn reject_offer(&self, offer_id: &str) -> Result<(), IPCError> {
+        self.offers
+            .decide(offer_id, false)
+            .map_err(to_transfer_error)
+    }
+
+    fn add_peer(
+        &self,
+        address: &str,
+        alias: Option<&str>,
+        tags: Vec<String>,
+    ) -> Result<Peer, IPCError> {
+        let address = address
+            .parse()
+            .map_err(|_| IPCError::InvalidArgument(format!("'{}' is not an ip:port", address)))?;
+        self.peer_book
+            .add(address, alias.map(str::to_string), tags)
+            .map_err(to_peer_book_error)
+    }
+
+    fn remove_peer(&self, peer: &str) -> Result<(), IPCError> {
+        self.peer_book.remove(peer).map_err(to_peer_book_error)
+    }
+
+    fn alias_peer(&self, peer: &str, alias: Option<&str>) -> Result<Peer, IPCError> {
+        self.peer_book
+            .set_alias(peer, alias.map(str::to_string))
+            .map_err(to_peer_book_error)
+    }
+
+    fn tag_peer(&self, peer: &str, tags: Vec<String>) -> Result<Peer, IPCError> {
+        self.peer_book
+            .set_tags(peer, tags)
+            .map_err(to_peer_book_error)
+    }
+
+    fn get_config(&self) -> Result<Config, IPCError> {
+        Ok((*self.reloader.current()).clone())
+    }
+
+    fn reload_config(&self) -> Result<ReloadReport, IPCError> {
+        self.reloader
+            .reload()
+            .map_err(|e| IPCError::InvalidConfig(e.to_string()))
+    }
+
+    fn shutdown(&self) -> Result<(), IPCError> {
+        info!("Shutdown requested over IPC");
+        self.shutdown.request();
+        Ok(())
+    }
+
+    fn metrics(&self) -> Result<String, IPCError> {
+        Ok(self.metrics.encode())
+    }
+
+    fn subscribe(&self) -> Result<Receiver<DaemonEvent>, IPCError> {
+        let peers = self.discovery.subscribe().map_err(to_ipc_error)?;
+        let transfers = self.send_file.tracker().subscribe();
+        let (tx, rx) = mpsc::channel();
+        let outbox = self.outbox.subscribe();
+        forward_events(peers, tx.clone(), DaemonEvent::Peer);
+        forward_events(transfers, tx.clone(), DaemonEvent::Transfer);
+        forward_events(outbox, tx, DaemonEvent::Outbox);
+        Ok(rx)
+    }
+
+    fn web_drop_status(&self) -> Result<WebDropStatus, IPCError> {
+        let web_drop = self.web_drop()?;
+        let (pin, pin_expires_at) = web_drop.pin().map_err(to_ipc_error)?;
+        Ok(WebDropStatus {
+            listen: web_drop.listen(),
+            pin,
+            pin_expires_at,
+            shares: web_drop.shares(),
+        })
+    }
+
+    fn share_file(&self, path: &str) -> Result<SharedFile, IPCError> {
+        self.web_drop()?
+            .share(std::path::Path::new(path))
+            .map_err(to_ipc_error)
+    }
+
+    fn unshare_file(&self, share_id: &str) -> Result<(), IPCError> {
+        self.web_drop()?.unshare(share_id).map_err(|e| match e {
+            DomainError::NotFound(message) => IPCError::InvalidArgument(message),
+            other => to_ipc_error(other),
+        })
+    }
+}
+
+/// Ends once the subscriber is gone and the next event finds no receiver.
+fn forward_events<T: Send + 'static>(
+    events: Receiver<T>,
+    subscriber: Sender<DaemonEvent>,
+    wrap: fn(T) -> DaemonEvent,
+) {
+    thread::spawn(move || {
+        for event in events {
+            if subscriber.send(wrap(event)).is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,75 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, IsTerminal, stderr},
+    os::{fd::AsFd, unix::fs::MetadataExt},
+};
+
+use lanshare_config::{LogConfig, LogFormat};
+use tracing_subscriber::{EnvFilter, Registry, fmt, prelude::*, reload};
+
+/// Lets the log level change while the daemon runs.
+pub struct LogHandle {
+    filter: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogHandle {
+    pub fn set_level(&self, level: &str) -> Result<(), String> {
+        let filter = parse_filter(level)?;
+        self.filter.reload(filter).map_err(|e| e.to_string())
+    }
+}
+
+/// Installs the global subscriber: journald when running as a systemd
+/// service, otherwise text or JSON lines on stderr.
+pub fn init(config: &LogConfig) -> Result<LogHandle, String> {
+    let (filter, handle) = reload::Layer::new(parse_filter(&config.level)?);
+    let journald = if stderr_is_journal().unwrap_or(false) {
+        tracing_journald::layer().ok()
+    } else {
+        None
+    };
+    let (text, json) = match (journald.is_some(), config.format) {
+        (true, _) => (None, None),
+        (false, LogFormat::Text) => (
+            Some(
+                fmt::layer()
+                    .with_ansi(stderr().is_terminal())
+                    .with_writer(stderr),
+            ),
+            None,
+        ),
+        (false, LogFormat::Json) => (
+            None,
+            Some(
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_writer(stderr),
+            ),
+        ),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(journald)
+        .with(text)
+        .with(json)
+        .try_init()
+        .map_err(|e| e.to_string())?;
+    Ok(LogHandle { filter: handle })
+}
+
+fn parse_filter(level: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(level).map_err(|e| format!("invalid log.level '{}': {}", level, e))
+}
+
+/// systemd sets `JOURNAL_STREAM=<dev>:<inode>` for the stream it attaches.
+fn stderr_is_journal() -> io::Result<bool> {
+    let Some(stream) = env::var_os("JOURNAL_STREAM") else {
+        return Ok(false);
+    };
+    let metadata = File::from(stderr().as_fd().try_clone_to_owned()?).metadata()?;
+    Ok(stream.to_string_lossy() == format!("{}:{}", metadata.dev(), metadata.ino()))
+}
@@ -1,57 +1,462 @@
-use lanshare_app::use_cases::{receive_file::ReceiveFileUseCase, send_file::SendFileUseCase};
-use lanshare_discovery::adapter::MdnsDiscoveryAdapter;
-use lanshare_domain::{models::Peer, ports::DiscoveryPort};
-use lanshare_ipc::IPCServer;
+mod args;
+mod handler;
+mod logging;
+mod outbox;
+mod presence;
+mod reload;
+mod shutdown;
+mod systemd;
+
+use lanshare_app::{
+    rate_limit::RateLimiter,
+    retry::RetryPolicy,
+    transfers::TransferTracker,
+    use_cases::{
+        outbox::OutboxUseCase, receive_file::ReceiveFileUseCase, send_file::SendFileUseCase,
+    },
+};
+use lanshare_config::{Config, ReloadReport};
+use lanshare_discovery::{
+    adapter::MdnsDiscoveryAdapter,
+    composite::CompositeDiscoveryAdapter,
+    interfaces::InterfaceFilter,
+    peer_book::PeerBookDiscoveryAdapter,
+    udp::{UdpBeaconConfig, UdpBeaconDiscoveryAdapter},
+};
+use lanshare_domain::{
+    models::{Capability, Peer, PeerMetadata, PeerStatus},
+    ports::{DiscoveryPort, MetricsPort},
+};
+use lanshare_gateway::load_or_create_token;
+use lanshare_ipc::{AccessPolicy, IPCServer};
+use lanshare_metrics::{http, prometheus::PrometheusMetrics};
 use lanshare_network::adapter::TcpNetworkAdapter;
-use lanshare_storage::adapter::LocalFileSystemAdapter;
-use std::{process::exit, sync::Arc, thread};
+use lanshare_proto::messages::PROTOCOL_VERSION;
+use lanshare_storage::{
+    adapter::LocalFileSystemAdapter,
+    identity::{load_or_create_device_id, load_or_create_network_key},
+    outbox::JsonOutboxAdapter,
+};
+use lanshare_webdrop::WebDrop;
+use sd_notify::NotifyState;
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    process::exit,
+    sync::{Arc, atomic::AtomicBool},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    args::Invocation, handler::DaemonHandler, outbox::OutboxDelivery, presence::Presence,
+    reload::ConfigReloader, shutdown::Shutdown,
+};
+
+/// The key older releases used for every install.
+const PUBLIC_NETWORK_KEY: &str = "lanshare";
 
 fn main() {
-    let storage_path = "./lanshare_storage";
+    let args: Vec<String> = std::env::args().collect();
+    let overrides = match args::parse(&args) {
+        Ok(Invocation::Run(overrides)) => overrides,
+        Ok(Invocation::Help) => {
+            args::print_usage();
+            return;
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            args::print_usage();
+            exit(2);
+        }
+    };
+    let config = match Config::load(&overrides) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            exit(2);
+        }
+    };
+    let log_handle = match logging::init(&config.log) {
+        Ok(handle) => handle,
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            exit(2);
+        }
+    };
+    let interface_filter = match InterfaceFilter::parse(
+        &config.discovery.interfaces,
+        &config.discovery.exclude_interfaces,
+    ) {
+        Ok(filter) => filter,
+        Err(e) => {
+            error!(error = ?e, "Configuration error: discovery interfaces");
+            exit(2);
+        }
+    };
+
+    let mut signals =
+        Signals::new([SIGINT, SIGTERM, SIGHUP]).expect("Failed to install signal handlers");
+    let shutdown = Shutdown::new(signals.handle());
+
+    let storage_path = config.daemon.storage_dir.as_path();
     let storage_adapter = Arc::new(LocalFileSystemAdapter::new(storage_path).unwrap());
+    let outbox_adapter = Arc::new(JsonOutboxAdapter::new(storage_path).unwrap());
     let network_adapter = Arc::new(TcpNetworkAdapter::new());
+    let peer_book =
+        Arc::new(PeerBookDiscoveryAdapter::new(storage_path.join("peers.json")).unwrap());
+    let discovery_adapter = Arc::new(build_discovery(
+        &config,
+        interface_filter,
+        peer_book.clone(),
+    ));
+    let transfer_tracker = TransferTracker::new();
+    let metrics = Arc::new(PrometheusMetrics::new());
+    watch_peer_count(&discovery_adapter, metrics.clone());
+
+    let lookup_discovery = discovery_adapter.clone();
+    let receive_file_usecase = Arc::new(
+        ReceiveFileUseCase::new(storage_adapter.clone())
+            .with_peer_lookup(move |sender| {
+                let peers = lookup_discovery.discover_peers().ok()?;
+                peers
+                    .into_iter()
+                    .find(|peer| peer.address.ip() == sender.ip())
+            })
+            .with_metrics(metrics.clone()),
+    );
+    let upload_limiter = RateLimiter::default();
+    let send_file_usecase = Arc::new(
+        SendFileUseCase::new(
+            storage_adapter.clone(),
+            network_adapter.clone(),
+            discovery_adapter.clone(),
+            transfer_tracker.clone(),
+        )
+        .with_chunk_size(config.transfer.chunk_size)
+        .with_retry_policy(retry_policy(&config))
+        .with_rate_limiter(upload_limiter.clone())
+        .with_metrics(metrics.clone()),
+    );
+    let outbox_usecase = Arc::new(OutboxUseCase::new(outbox_adapter));
 
-    let receive_file_usecase = Arc::new(ReceiveFileUseCase::new(storage_adapter.clone()));
-    let send_file_usecase = Arc::new(SendFileUseCase::new(
-        storage_adapter.clone(),
-        network_adapter.clone(),
+    let outbox_delivery = Arc::new(OutboxDelivery::new(
+        outbox_usecase.clone(),
+        send_file_usecase.clone(),
+        shutdown.clone(),
     ));
+    match discovery_adapter.subscribe() {
+        Ok(events) => outbox_delivery.watch(events),
+        Err(e) => error!(error = ?e, "Failed to subscribe to peer events"),
+    }
+    outbox_delivery.start_sweeper(discovery_adapter.clone());
 
-    let discovery_adapter = Arc::new(MdnsDiscoveryAdapter::new().unwrap());
+    let listener = TcpNetworkAdapter::bind(SocketAddr::new(
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        config.daemon.port,
+    ))
+    .expect("Failed to bind listener");
+    let port = listener
+        .local_addr()
+        .expect("Listener has no local address")
+        .port();
 
-    // if let Err(e) = lanshare_core::run_server() {
-    //     eprintln!("application error: {e}");
-    //     exit(1);
-    // }
-    //
-    let test_peer = Peer::new(
-        "test peer".to_string(),
-        "127.0.0.1:8080".parse().unwrap(),
-        0,
+    let device_id = load_or_create_device_id(storage_path).unwrap();
+    let identity = local_identity(&config, device_id, port);
+    info!(name = %identity.display_name(), port, "Advertising presence");
+    let presence = Arc::new(Presence::new(discovery_adapter.clone(), identity));
+    presence.announce();
+    presence.watch_status(receive_file_usecase.clone(), shutdown.clone());
+
+    let reloader = Arc::new(
+        ConfigReloader::new(
+            overrides,
+            config.clone(),
+            storage_adapter.clone(),
+            receive_file_usecase.clone(),
+            upload_limiter,
+            presence,
+        )
+        .with_log_handle(log_handle),
     );
+    reloader.apply(&config);
+
+    let receive_uc_clone = receive_file_usecase.clone();
+    let listener_shutdown = shutdown.flag();
+    let transfer_listener = Arc::new(thread::spawn(move || {
+        if let Err(e) = TcpNetworkAdapter::serve(listener, receive_uc_clone, listener_shutdown) {
+            error!(error = ?e, "Network listener error");
+        }
+    }));
 
-    if let Err(e) = discovery_adapter.broadcast_presence(&test_peer) {
-        eprintln!("Failed to broadcast presence: {:?}", e);
+    let socket_path = config.daemon.socket_path.clone();
+    let mut handler = DaemonHandler::new(
+        discovery_adapter.clone(),
+        peer_book.clone(),
+        send_file_usecase.clone(),
+        outbox_usecase.clone(),
+        reloader.clone(),
+        shutdown.clone(),
+        metrics.clone(),
+    )
+    .with_offers(receive_file_usecase.offers().clone());
+    if config.web_drop.enabled {
+        let web_drop = Arc::new(WebDrop::new(
+            config.web_drop.listen,
+            Duration::from_secs(config.web_drop.pin_lifetime_secs),
+        ));
+        match lanshare_webdrop::serve(
+            web_drop.clone(),
+            receive_file_usecase.clone(),
+            shutdown.flag(),
+        ) {
+            Ok(_) => handler = handler.with_web_drop(web_drop),
+            Err(e) => {
+                error!(address = %config.web_drop.listen, error = ?e, "Failed to start web drop page")
+            }
+        }
     }
+    let handler = Arc::new(handler);
 
-    let receive_uc_clone = receive_file_usecase.clone();
+    if config.metrics.enabled
+        && let Err(e) = http::serve(config.metrics.listen, metrics, shutdown.flag())
+    {
+        error!(address = %config.metrics.listen, error = ?e, "Failed to start metrics endpoint");
+    }
+    if config.gateway.enabled {
+        start_gateway(&config, handler.clone(), shutdown.flag());
+    }
+
+    let access = match AccessPolicy::new(
+        &config.daemon.ipc_allowed_users,
+        &config.daemon.ipc_allowed_groups,
+    ) {
+        Ok(access) => access,
+        Err(e) => {
+            error!(error = %e, "Invalid IPC access policy");
+            exit(2);
+        }
+    };
+    let mut ipc_server = IPCServer::new(socket_path.clone(), shutdown.flag(), handler)
+        .with_max_clients(config.daemon.max_ipc_clients)
+        .with_access_policy(access);
+    let ipc_started = match systemd::activated_listener() {
+        Some(listener) => {
+            info!("Using socket-activated IPC socket");
+            ipc_server.start_with_listener(listener)
+        }
+        None => ipc_server.start(),
+    };
+    if let Err(e) = ipc_started {
+        error!(error = %e, "Failed to start IPC server");
+        exit(1);
+    }
+
+    let status = format!("Serving on port {}", port);
+    systemd::notify(&[NotifyState::Ready, NotifyState::Status(&status)]);
+    let watched_listener = transfer_listener.clone();
+    systemd::spawn_watchdog(move || !watched_listener.is_finished());
+    info!(
+        port,
+        socket = %socket_path.display(),
+        "LanShare Daemon is running. Ready for CLI commands!"
+    );
+
+    for signal in signals.forever() {
+        if signal == SIGHUP {
+            match reloader.reload() {
+                Ok(report) => print_reload_report(&report),
+                Err(e) => error!(
+                    error = %e,
+                    "Configuration reload failed, keeping current settings"
+                ),
+            }
+            continue;
+        }
+        info!(signal, "Received signal, shutting down");
+        break;
+    }
+
+    // Stop taking new work, let running sends record where to resume, then
+    // leave the network. Anything still running at the deadline is dropped;
+    // receivers keep their partial files either way.
+    let deadline =
+        Instant::now() + Duration::from_secs(reloader.current().daemon.shutdown_timeout_secs);
+    systemd::notify(&[
+        NotifyState::Stopping,
+        NotifyState::Status("Checkpointing transfers"),
+    ]);
+    shutdown.request();
+    send_file_usecase.interrupt();
+    receive_file_usecase.offers().reject_all();
+    if let Err(e) = discovery_adapter.withdraw_presence() {
+        error!(error = ?e, "Failed to withdraw presence");
+    }
+    let finished = shutdown.wait_idle(deadline, || transfer_listener.is_finished());
+    ipc_server.shutdown();
+    if !finished {
+        warn!("Transfers did not stop in time, exiting anyway");
+        exit(1);
+    }
+}
+
+fn print_reload_report(report: &ReloadReport) {
+    if report.applied.is_empty() && report.restart_required.is_empty() {
+        info!("Configuration reloaded, nothing changed");
+        return;
+    }
+    if !report.applied.is_empty() {
+        info!(applied = %report.applied.join(", "), "Configuration reloaded");
+    }
+    if !report.restart_required.is_empty() {
+        warn!(
+            settings = %report.restart_required.join(", "),
+            "Changed settings require a restart"
+        );
+    }
+}
+
+/// Starts the HTTP gateway. Failures are logged and leave the daemon
+/// running without the gateway, like the metrics endpoint.
+fn start_gateway(config: &Config, handler: Arc<DaemonHandler>, shutdown: Arc<AtomicBool>) {
+    let Some(token_file) = config.gateway_token_file() else {
+        error!("No config directory for the gateway token; set gateway.token_file");
+        return;
+    };
+    let token = match load_or_create_token(&token_file) {
+        Ok(token) => token,
+        Err(e) => {
+            error!(path = %token_file.display(), error = %e, "Failed to load gateway token");
+            return;
+        }
+    };
+    match lanshare_gateway::serve(config.gateway.listen, token, handler, shutdown) {
+        Ok(_) => {
+            info!(token_file = %token_file.display(), "HTTP gateway requires the token in this file")
+        }
+        Err(e) => {
+            error!(address = %config.gateway.listen, error = %e, "Failed to start HTTP gateway")
+        }
+    }
+}
+
+/// Keeps the discovered-peers gauge in step with discovery events.
+fn watch_peer_count(discovery: &Arc<CompositeDiscoveryAdapter>, metrics: Arc<PrometheusMetrics>) {
+    let events = match discovery.subscribe() {
+        Ok(events) => events,
+        Err(e) => {
+            error!(error = ?e, "Failed to subscribe to peer events");
+            return;
+        }
+    };
+    let discovery = discovery.clone();
     thread::spawn(move || {
-        if let Err(e) = TcpNetworkAdapter::start_listening(receive_uc_clone) {
-            eprintln!("Network listener error: {:?}", e);
+        let count_peers = || {
+            if let Ok(peers) = discovery.discover_peers() {
+                metrics.set_discovered_peers(peers.len());
+            }
+        };
+        count_peers();
+        for _event in events {
+            count_peers();
         }
     });
+}
 
-    println!("LanShare Daemon is running in the background.");
+fn retry_policy(config: &Config) -> RetryPolicy {
+    RetryPolicy {
+        initial_backoff: Duration::from_millis(config.transfer.initial_backoff_ms),
+        max_backoff: Duration::from_millis(config.transfer.max_backoff_ms),
+        deadline: Duration::from_secs(config.transfer.deadline_secs),
+        ..RetryPolicy::default()
+    }
+}
 
-    println!("Starting IPC Server...");
-    let socket_path = std::path::PathBuf::from("/tmp/lanshare.sock");
-    let shutdown_flag = Arc::new(std::sync::atomic::AtomicBool::new(false));
+/// Our advertised peer. The address is left unspecified so discovery
+/// announces every (allowed) LAN address with the bound port.
+fn local_identity(config: &Config, device_id: String, port: u16) -> Peer {
+    let hostname = gethostname::gethostname().to_string_lossy().into_owned();
+    let hostname = hostname.split('.').next().unwrap_or("lanshare").to_string();
+    let display_name = match config.daemon.name.trim() {
+        "" => hostname.clone(),
+        name => name.to_string(),
+    };
 
-    let mut ipc_server = IPCServer::new(socket_path, shutdown_flag);
-    ipc_server.start().expect("Failed to start IPC server");
-    println!("LanShare Daemon is running in the background. Ready for CLI commands!");
+    Peer::new(
+        hostname,
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port),
+        0,
+    )
+    .with_metadata(PeerMetadata {
+        device_id,
+        display_name,
+        platform: std::env::consts::OS.to_string(),
+        protocol_version: PROTOCOL_VERSION,
+        capabilities: vec![Capability::Streaming],
+        // No key pair to fingerprint yet.
+        fingerprint: None,
+        status: PeerStatus::Accepting,
+    })
+}
 
-    loop {
-        thread::sleep(std::time::Duration::from_secs(60));
+/// The configured UDP discovery key, or one generated for this install.
+fn network_key(config: &Config) -> Option<String> {
+    match config.discovery.network_key.as_str() {
+        "" => match load_or_create_network_key(&config.daemon.storage_dir) {
+            Ok(key) => {
+                warn!(
+                    storage = %config.daemon.storage_dir.display(),
+                    "No discovery.network_key set, using the key generated for this install; \
+                     UDP discovery only finds devices configured with the same key"
+                );
+                Some(key)
+            }
+            Err(e) => {
+                warn!(error = %e, "UDP beacon discovery unavailable");
+                None
+            }
+        },
+        PUBLIC_NETWORK_KEY => {
+            warn!(
+                "discovery.network_key is the well-known default; anyone on the network can forge beacons"
+            );
+            Some(PUBLIC_NETWORK_KEY.to_string())
+        }
+        key => Some(key.to_string()),
+    }
+}
+
+fn build_discovery(
+    config: &Config,
+    filter: InterfaceFilter,
+    peer_book: Arc<PeerBookDiscoveryAdapter>,
+) -> CompositeDiscoveryAdapter {
+    let discovery = &config.discovery;
+    let mut sources: Vec<Arc<dyn DiscoveryPort>> = vec![peer_book];
+    if discovery.mdns {
+        match MdnsDiscoveryAdapter::with_filter(filter.clone()) {
+            Ok(mdns) => sources.push(Arc::new(mdns)),
+            Err(e) => warn!(error = ?e, "mDNS discovery unavailable"),
+        }
+    }
+    if discovery.udp
+        && let Some(network_key) = network_key(config)
+    {
+        let udp_config = UdpBeaconConfig {
+            port: discovery.udp_port,
+            multicast_group: discovery.multicast_group,
+            interval: Duration::from_secs(discovery.beacon_interval_secs),
+            network_key: network_key.into_bytes(),
+            interfaces: filter,
+        };
+        match UdpBeaconDiscoveryAdapter::new(udp_config) {
+            Ok(udp) => sources.push(Arc::new(udp)),
+            Err(e) => warn!(error = ?e, "UDP beacon discovery unavailable"),
+        }
     }
+    CompositeDiscoveryAdapter::new(sources).expect("Failed to start peer discovery")
 }
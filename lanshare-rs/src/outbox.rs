@@ -0,0 +1,143 @@
+use std::{
+    sync::{Arc, mpsc::Receiver},
+    thread,
+    time::Duration,
+};
+
+use lanshare_app::{peer_resolver::matches_exactly, use_cases::outbox::OutboxUseCase};
+use lanshare_domain::{
+    error::DomainError,
+    models::{OutboxEntry, Peer, PeerEvent},
+    ports::DiscoveryPort,
+};
+use tracing::{error, info, info_span, warn};
+
+use crate::{handler::SendFile, shutdown::Shutdown};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+pub type Outbox = OutboxUseCase<Arc<lanshare_storage::outbox::JsonOutboxAdapter>>;
+
+pub struct OutboxDelivery {
+    outbox: Arc<Outbox>,
+    send_file: Arc<SendFile>,
+    shutdown: Shutdown,
+}
+
+impl OutboxDelivery {
+    pub fn new(outbox: Arc<Outbox>, send_file: Arc<SendFile>, shutdown: Shutdown) -> Self {
+        Self {
+            outbox,
+            send_file,
+            shutdown,
+        }
+    }
+
+    pub fn deliver_to(&self, peer: &Peer) {
+        if self.shutdown.is_requested() {
+            return;
+        }
+        let entries = match self
+            .outbox
+            .take_for_peer(|query| matches_exactly(peer, query))
+        {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!(error = ?e, "Failed to read outbox");
+                return;
+            }
+        };
+
+        for entry in entries {
+            info!(
+                peer = %peer.display_name(),
+                file = %entry.file_path,
+                "Peer is online, delivering queued file"
+            );
+            let outbox = Arc::clone(&self.outbox);
+            let send_file = Arc::clone(&self.send_file);
+            let peer = peer.clone();
+            let guard = self.shutdown.begin_transfer();
+            thread::spawn(move || {
+                let _guard = guard;
+                deliver_entry(&outbox, &send_file, &peer, entry)
+            });
+        }
+    }
+
+    pub fn watch(self: &Arc<Self>, events: Receiver<PeerEvent>) {
+        let delivery = Arc::clone(self);
+        thread::spawn(move || {
+            for event in events {
+                match event {
+                    PeerEvent::Added(peer) | PeerEvent::Updated(peer) => delivery.deliver_to(&peer),
+                    PeerEvent::Removed(_) => {}
+                }
+            }
+        });
+    }
+
+    pub fn start_sweeper<D: DiscoveryPort + 'static>(self: &Arc<Self>, discovery: D) {
+        let delivery = Arc::clone(self);
+        thread::spawn(move || {
+            loop {
+                match delivery.outbox.purge_expired() {
+                    Ok(expired) => {
+                        for entry in expired {
+                            warn!(
+                                peer = %entry.peer,
+                                file = %entry.file_path,
+                                "Queued file expired before the peer came online"
+                            );
+                        }
+                    }
+                    Err(e) => error!(error = ?e, "Failed to purge outbox"),
+                }
+
+                if let Ok(peers) = discovery.discover_peers() {
+                    for peer in peers {
+                        delivery.deliver_to(&peer);
+                    }
+                }
+
+                thread::sleep(SWEEP_INTERVAL);
+            }
+        });
+    }
+}
+
+fn deliver_entry(outbox: &Outbox, send_file: &SendFile, peer: &Peer, entry: OutboxEntry) {
+    let span = info_span!("outbox_delivery", outbox_id = %entry.id);
+    let _entered = span.enter();
+    let reason = match send_file.execute(peer, &entry.file_path) {
+        Ok(()) => {
+            info!(
+                peer = %peer.display_name(),
+                file = %entry.file_path,
+                "Delivered queued file"
+            );
+            outbox.delivered(entry);
+            return;
+        }
+        // Decisions of the user or the peer; retrying would overrule them.
+        // A paused delivery lives on as a transfer that `resume` picks up.
+        Err(DomainError::Paused) => "paused",
+        Err(DomainError::Cancelled) => "cancelled",
+        Err(DomainError::TransferRejected) => "rejected by the peer",
+        // Anything else, e.g. the peer going offline again mid-delivery,
+        // waits for the next sweep until the entry expires.
+        Err(e) => {
+            warn!(
+                file = %entry.file_path,
+                error = ?e,
+                "Delivery of queued file failed, requeueing it"
+            );
+            if let Err(e) = outbox.requeue(&entry) {
+                error!(file = %entry.file_path, error = ?e, "Failed to requeue queued file");
+            }
+            return;
+        }
+    };
+    info!(file = %entry.file_path, reason, "Dropped queued file");
+    outbox.dropped(entry, reason);
+}
@@ -0,0 +1,85 @@
+use std::{
+    sync::{Arc, RwLock},
+    thread,
+    time::Duration,
+};
+
+use lanshare_discovery::composite::CompositeDiscoveryAdapter;
+use lanshare_domain::{
+    models::{Peer, PeerStatus},
+    ports::DiscoveryPort,
+};
+use tracing::{debug, error};
+
+use crate::{reload::ReceiveFile, shutdown::Shutdown};
+
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What we advertise about ourselves, re-announced whenever it changes.
+pub struct Presence {
+    discovery: Arc<CompositeDiscoveryAdapter>,
+    identity: RwLock<Peer>,
+}
+
+impl Presence {
+    pub fn new(discovery: Arc<CompositeDiscoveryAdapter>, identity: Peer) -> Self {
+        Self {
+            discovery,
+            identity: RwLock::new(identity),
+        }
+    }
+
+    pub fn announce(&self) {
+        let Ok(identity) = self.identity.read() else {
+            return;
+        };
+        if let Err(e) = self.discovery.broadcast_presence(&identity) {
+            error!(error = ?e, "Failed to broadcast presence");
+        }
+    }
+
+    /// Uses `display_name`, or the hostname when it is blank.
+    pub fn rename(&self, display_name: &str) {
+        self.update(|identity| {
+            identity.metadata.display_name = match display_name.trim() {
+                "" => identity.name.clone(),
+                name => name.to_string(),
+            };
+        });
+    }
+
+    pub fn set_status(&self, status: PeerStatus) {
+        self.update(|identity| identity.metadata.status = status);
+    }
+
+    /// Keeps the advertised status in line with incoming transfers and
+    /// offers until the daemon shuts down.
+    pub fn watch_status(self: &Arc<Self>, receive_file: Arc<ReceiveFile>, shutdown: Shutdown) {
+        let presence = Arc::clone(self);
+        thread::spawn(move || {
+            while !shutdown.is_requested() {
+                presence.set_status(receive_file.status());
+                thread::sleep(STATUS_POLL_INTERVAL);
+            }
+        });
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Peer)) {
+        let Ok(mut identity) = self.identity.write() else {
+            return;
+        };
+        let previous = identity.metadata.clone();
+        change(&mut identity);
+        if identity.metadata == previous {
+            return;
+        }
+        debug!(
+            name = %identity.display_name(),
+            status = identity.metadata.status.as_str(),
+            "Re-announcing presence"
+        );
+        if let Err(e) = self.discovery.broadcast_presence(&identity) {
+            error!(error = ?e, "Failed to re-announce presence");
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use lanshare_app::{
+    rate_limit::RateLimiter, receive_policy::ReceivePolicy,
+    use_cases::receive_file::ReceiveFileUseCase,
+};
+use lanshare_config::{Config, ConfigError, Overrides, ReloadReport};
+use lanshare_storage::adapter::LocalFileSystemAdapter;
+use sd_notify::NotifyState;
+use tracing::error;
+
+use crate::{logging::LogHandle, presence::Presence, systemd};
+
+pub type ReceiveFile = ReceiveFileUseCase<Arc<LocalFileSystemAdapter>>;
+
+/// Owns the effective configuration of the running daemon and pushes the
+/// settings that can change live into the components that use them.
+pub struct ConfigReloader {
+    overrides: Overrides,
+    current: RwLock<Arc<Config>>,
+    storage: Arc<LocalFileSystemAdapter>,
+    receive_file: Arc<ReceiveFile>,
+    upload_limiter: RateLimiter,
+    presence: Arc<Presence>,
+    log: Option<LogHandle>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        overrides: Overrides,
+        config: Arc<Config>,
+        storage: Arc<LocalFileSystemAdapter>,
+        receive_file: Arc<ReceiveFile>,
+        upload_limiter: RateLimiter,
+        presence: Arc<Presence>,
+    ) -> Self {
+        Self {
+            overrides,
+            current: RwLock::new(config),
+            storage,
+            receive_file,
+            upload_limiter,
+            presence,
+            log: None,
+        }
+    }
+
+    pub fn with_log_handle(mut self, log: LogHandle) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    pub fn current(&self) -> Arc<Config> {
+        self.current
+            .read()
+            .map(|config| config.clone())
+            .unwrap_or_default()
+    }
+
+    /// Pushes the live settings of `config` into the running components.
+    pub fn apply(&self, config: &Config) {
+        if let Err(e) = self.storage.set_receive_dir(config.receive_dir()) {
+            error!(
+                directory = %config.receive_dir().display(),
+                error = %e,
+                "Failed to use receive directory"
+            );
+        }
+        if let Some(log) = &self.log
+            && let Err(e) = log.set_level(&config.log.level)
+        {
+            error!(error = %e, "Failed to change the log level");
+        }
+        self.receive_file.set_policy(ReceivePolicy {
+            auto_accept: config.receive.auto_accept,
+            offer_timeout: Duration::from_secs(config.receive.offer_timeout_secs),
+            accept_from: config.receive.accept_from.clone(),
+            max_file_size: config.receive.max_file_size,
+        });
+        self.receive_file
+            .rate_limiter()
+            .set_rate(config.limits.download_bytes_per_sec);
+        self.upload_limiter
+            .set_rate(config.limits.upload_bytes_per_sec);
+    }
+
+    /// Re-reads the configuration from the same sources as at startup.
+    pub fn reload(&self) -> Result<ReloadReport, ConfigError> {
+        systemd::notify_reloading();
+        let result = self.load_and_apply();
+        systemd::notify(&[NotifyState::Ready]);
+        result
+    }
+
+    fn load_and_apply(&self) -> Result<ReloadReport, ConfigError> {
+        let new = Config::load(&self.overrides)?;
+        let (effective, report) = self.current().reload_from(&new);
+        self.apply(&effective);
+        if report.applied.iter().any(|key| key == "daemon.name") {
+            self.presence.rename(&effective.daemon.name);
+        }
+        if let Ok(mut current) = self.current.write() {
+            *current = Arc::new(effective);
+        }
+        Ok(report)
+    }
+}
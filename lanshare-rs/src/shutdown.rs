@@ -0,0 +1,79 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use signal_hook::iterator::Handle;
+
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shared by everything that must stop when the daemon shuts down, whether
+/// that was asked for by a signal or the IPC `shutdown` command.
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    active_transfers: Arc<AtomicUsize>,
+    signals: Handle,
+}
+
+/// Held by a thread running an outgoing transfer until it has finished or
+/// recorded where to resume.
+pub struct TransferGuard {
+    active_transfers: Arc<AtomicUsize>,
+}
+
+impl Drop for TransferGuard {
+    fn drop(&mut self) {
+        self.active_transfers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Shutdown {
+    pub fn new(signals: Handle) -> Self {
+        Self {
+            requested: Arc::new(AtomicBool::new(false)),
+            active_transfers: Arc::new(AtomicUsize::new(0)),
+            signals,
+        }
+    }
+
+    /// The flag the IPC and transfer listeners poll.
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.requested.clone()
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Stops the listeners and wakes the main thread out of its signal loop.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+        self.signals.close();
+    }
+
+    pub fn begin_transfer(&self) -> TransferGuard {
+        self.active_transfers.fetch_add(1, Ordering::SeqCst);
+        TransferGuard {
+            active_transfers: self.active_transfers.clone(),
+        }
+    }
+
+    /// Waits until no outgoing transfer is running and `done` holds, giving
+    /// up at `deadline`. Returns whether everything finished in time.
+    pub fn wait_idle(&self, deadline: Instant, done: impl Fn() -> bool) -> bool {
+        loop {
+            if self.active_transfers.load(Ordering::SeqCst) == 0 && done() {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(WAIT_POLL_INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::{
+    os::{fd::FromRawFd, unix::net::UnixListener},
+    thread,
+    time::Duration,
+};
+
+use sd_notify::NotifyState;
+use tracing::{error, warn};
+
+/// The IPC socket passed in by a systemd `.socket` unit, if we were started
+/// through socket activation.
+pub fn activated_listener() -> Option<UnixListener> {
+    let fd = sd_notify::listen_fds().ok()?.next()?;
+    // SAFETY: systemd hands the descriptors from LISTEN_FDS over to us and
+    // `listen_fds` unsets the variables, so nothing else takes ownership.
+    Some(unsafe { UnixListener::from_raw_fd(fd) })
+}
+
+/// Tells the service manager about a state change; a no-op when not run by
+/// systemd.
+pub fn notify(states: &[NotifyState]) {
+    if let Err(e) = sd_notify::notify(false, states) {
+        warn!(error = %e, "Failed to notify systemd");
+    }
+}
+
+pub fn notify_reloading() {
+    match NotifyState::monotonic_usec_now() {
+        Ok(now) => notify(&[NotifyState::Reloading, now]),
+        Err(_) => notify(&[NotifyState::Reloading]),
+    }
+}
+
+/// Pings the watchdog at half the configured interval for as long as
+/// `healthy` holds, so a wedged daemon gets restarted.
+pub fn spawn_watchdog<F>(healthy: F)
+where
+    F: Fn() -> bool + Send + 'static,
+{
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(true, &mut usec) {
+        return;
+    }
+    let interval = Duration::from_micros(usec / 2);
+    thread::spawn(move || {
+        while healthy() {
+            notify(&[NotifyState::Watchdog]);
+            thread::sleep(interval);
+        }
+        error!("Daemon is unhealthy, no longer feeding the systemd watchdog");
+    });
+}
@@ -2,6 +2,7 @@ use std::{
     fs::{self, File},
     io::{self, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
+    sync::RwLock,
 };
 
 use lanshare_domain::{
@@ -9,6 +10,7 @@ use lanshare_domain::{
     models::{FileBlock, FileManifest},
     ports::StoragePort,
 };
+use tracing::{debug, warn};
 
 use crate::{
     hash::{compute_file_sha256, sha_to_hex},
@@ -18,7 +20,7 @@ use crate::{
 pub struct LocalFileSystemAdapter {
     base_dir: PathBuf,
     tmp_dir: PathBuf,
-    final_dir: PathBuf,
+    final_dir: RwLock<PathBuf>,
 }
 
 impl LocalFileSystemAdapter {
@@ -33,9 +35,26 @@ impl LocalFileSystemAdapter {
         Ok(Self {
             base_dir: base_path,
             tmp_dir: temp_path,
-            final_dir: final_path,
+            final_dir: RwLock::new(final_path),
         })
     }
+
+    /// Moves future completed transfers to `dir`, creating it if needed.
+    pub fn set_receive_dir(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        if let Ok(mut final_dir) = self.final_dir.write() {
+            *final_dir = dir;
+        }
+        Ok(())
+    }
+
+    pub fn receive_dir(&self) -> PathBuf {
+        self.final_dir
+            .read()
+            .map(|dir| dir.clone())
+            .unwrap_or_else(|_| self.base_dir.join("final"))
+    }
 }
 
 impl StoragePort for LocalFileSystemAdapter {
@@ -63,6 +82,12 @@ impl StoragePort for LocalFileSystemAdapter {
     }
 
     fn prepare_for_receive(&self, manifest: &FileManifest) -> Result<(), DomainError> {
+        let meta_path = self.tmp_dir.join(format!("{}.meta", manifest.file_id));
+        let part_path = self.tmp_dir.join(format!("{}.part", manifest.file_id));
+        if meta_path.exists() && part_path.exists() {
+            return Ok(());
+        }
+
         let meta = TransactionMeta {
             id: manifest.file_id.clone(),
             filename: manifest.name.clone(),
@@ -71,11 +96,9 @@ impl StoragePort for LocalFileSystemAdapter {
             total_size: manifest.size,
         };
 
-        let meta_path = self.tmp_dir.join(format!("{}.meta", manifest.file_id));
         let meta_json = serde_json::to_string_pretty(&meta)?;
         fs::write(meta_path, meta_json)?;
 
-        let part_path = self.tmp_dir.join(format!("{}.part", manifest.file_id));
         File::create(part_path)?;
         Ok(())
     }
@@ -120,7 +143,8 @@ impl StoragePort for LocalFileSystemAdapter {
         if meta_path.exists() {
             let json = fs::read_to_string(&meta_path)?;
             let mut meta: TransactionMeta = serde_json::from_str(&json).unwrap();
-            meta.written_bytes += block.data.len() as u64;
+            let block_end = block.offset + block.data.len() as u64;
+            meta.written_bytes = meta.written_bytes.max(block_end);
             fs::write(meta_path, serde_json::to_string(&meta).unwrap())?;
         }
         Ok(())
@@ -135,11 +159,22 @@ impl StoragePort for LocalFileSystemAdapter {
 
         let actual_sha = compute_file_sha256(&part_path)?;
         if sha_to_hex(&actual_sha) != meta.expected_sha {
+            warn!(
+                file = %meta.filename,
+                expected = %meta.expected_sha,
+                actual = %sha_to_hex(&actual_sha),
+                "Received file does not match its checksum"
+            );
             return Err(DomainError::IntegrityError);
         }
 
-        let final_path = self.final_dir.join(meta.filename);
-        fs::rename(part_path, final_path)?;
+        let final_path = self.receive_dir().join(meta.filename);
+        if fs::rename(&part_path, &final_path).is_err() {
+            // The receive directory may live on another filesystem.
+            fs::copy(&part_path, &final_path)?;
+            fs::remove_file(&part_path)?;
+        }
+        debug!(path = %final_path.display(), "Stored received file");
         let _ = fs::remove_file(meta_path);
         Ok(())
     }
@@ -152,4 +187,24 @@ impl StoragePort for LocalFileSystemAdapter {
         let _ = fs::remove_file(meta_path);
         Ok(())
     }
+
+    fn is_stream(&self, file_path: &str) -> Result<bool, DomainError> {
+        let file_type = fs::metadata(file_path)?.file_type();
+        Ok(!file_type.is_file() && !file_type.is_dir())
+    }
+
+    fn open_stream(&self, file_path: &str) -> Result<Box<dyn Read + Send>, DomainError> {
+        // Opening a named pipe blocks until something writes to it.
+        Ok(Box::new(File::open(file_path)?))
+    }
+
+    fn seal_stream(&self, file_id: &str, size: u64, sha256: &[u8; 32]) -> Result<(), DomainError> {
+        let meta_path = self.tmp_dir.join(format!("{}.meta", file_id));
+        let json = fs::read_to_string(&meta_path)?;
+        let mut meta: TransactionMeta = serde_json::from_str(&json)?;
+        meta.expected_sha = sha_to_hex(sha256);
+        meta.total_size = size;
+        fs::write(meta_path, serde_json::to_string(&meta)?)?;
+        Ok(())
+    }
 }
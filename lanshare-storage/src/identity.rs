@@ -0,0 +1,46 @@
+use std::{
+    fs,
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+};
+
+const DEVICE_ID_FILE: &str = "device_id";
+const NETWORK_KEY_FILE: &str = "network_key";
+
+pub fn load_or_create_device_id(base_dir: impl AsRef<Path>) -> io::Result<String> {
+    let path = base_dir.as_ref().join(DEVICE_ID_FILE);
+    if let Ok(existing) = fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if !existing.is_empty() {
+            return Ok(existing.to_string());
+        }
+    }
+
+    fs::create_dir_all(base_dir.as_ref())?;
+    let device_id = uuid::Uuid::new_v4().to_string();
+    fs::write(&path, &device_id)?;
+    Ok(device_id)
+}
+
+/// A random UDP discovery key for this install, readable only by its owner.
+pub fn load_or_create_network_key(base_dir: impl AsRef<Path>) -> io::Result<String> {
+    let path = base_dir.as_ref().join(NETWORK_KEY_FILE);
+    if let Ok(existing) = fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if !existing.is_empty() {
+            return Ok(existing.to_string());
+        }
+    }
+
+    fs::create_dir_all(base_dir.as_ref())?;
+    let key = uuid::Uuid::new_v4().simple().to_string();
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)?
+        .write_all(key.as_bytes())?;
+    Ok(key)
+}
@@ -1,3 +1,5 @@
 pub mod adapter;
+pub mod identity;
+pub mod outbox;
 pub mod transaction;
-mod hash;
\ No newline at end of file
+mod hash;
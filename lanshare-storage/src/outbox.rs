@@ -0,0 +1,83 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use lanshare_domain::{error::DomainError, models::OutboxEntry, ports::OutboxPort};
+
+pub struct JsonOutboxAdapter {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JsonOutboxAdapter {
+    pub fn new(base_dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        fs::create_dir_all(base_dir.as_ref())?;
+        Ok(Self {
+            path: base_dir.as_ref().join("outbox.json"),
+            lock: Mutex::new(()),
+        })
+    }
+
+    fn load(&self) -> Result<Vec<OutboxEntry>, DomainError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let json = fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    fn save(&self, entries: &[OutboxEntry]) -> Result<(), DomainError> {
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(entries)?)?;
+        fs::rename(tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl OutboxPort for JsonOutboxAdapter {
+    fn enqueue(&self, entry: &OutboxEntry) -> Result<(), DomainError> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        let mut entries = self.load()?;
+        entries.retain(|existing| existing.id != entry.id);
+        entries.push(entry.clone());
+        self.save(&entries)
+    }
+
+    fn pending(&self) -> Result<Vec<OutboxEntry>, DomainError> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        self.load()
+    }
+
+    fn remove(&self, entry_id: &str) -> Result<(), DomainError> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        let mut entries = self.load()?;
+        entries.retain(|entry| entry.id != entry_id);
+        self.save(&entries)
+    }
+
+    fn take(
+        &self,
+        matches: &dyn Fn(&OutboxEntry) -> bool,
+    ) -> Result<Vec<OutboxEntry>, DomainError> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| DomainError::IoError("Lock failed".into()))?;
+        let (taken, kept): (Vec<_>, Vec<_>) = self.load()?.into_iter().partition(|e| matches(e));
+        if !taken.is_empty() {
+            self.save(&kept)?;
+        }
+        Ok(taken)
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    net::{IpAddr, Ipv4Addr},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use lanshare_app::use_cases::receive_file::ReceiveFileUseCase;
+use lanshare_domain::{
+    error::DomainError,
+    models::{FileBlock, FileManifest},
+    ports::StoragePort,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response, ResponseBox, Server};
+use tracing::{debug, info, info_span, warn};
+
+use crate::state::{SignInError, WebDrop};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Requests served at once; more are turned away until one finishes.
+const MAX_CONCURRENT_REQUESTS: usize = 32;
+const MAX_JSON_SIZE: u64 = 16 * 1024;
+/// The page uploads 1 MiB chunks; anything much larger is refused.
+const MAX_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+const SESSION_COOKIE: &str = "lanshare_drop";
+const PAGE: &str = include_str!("page.html");
+
+#[derive(Deserialize)]
+struct SignIn {
+    pin: String,
+}
+
+#[derive(Deserialize)]
+struct NewUpload {
+    name: String,
+    size: u64,
+    /// Hex SHA-256 the page computed before uploading.
+    sha256: String,
+}
+
+struct WebDropServer<S: StoragePort> {
+    web_drop: Arc<WebDrop>,
+    receive: Arc<ReceiveFileUseCase<S>>,
+    /// Sizes of the uploads a browser has announced, by file ID.
+    uploads: Mutex<HashMap<String, u64>>,
+}
+
+/// Serves the web drop page until `shutdown` is set. Uploads go through
+/// `receive` like transfers from peers: the receive policy, the size limit
+/// and the checksum all apply.
+pub fn serve<S: StoragePort + 'static>(
+    web_drop: Arc<WebDrop>,
+    receive: Arc<ReceiveFileUseCase<S>>,
+    shutdown: Arc<AtomicBool>,
+) -> Result<JoinHandle<()>, DomainError> {
+    let address = web_drop.listen();
+    let server = Server::http(address).map_err(|e| DomainError::IoError(e.to_string()))?;
+    info!(address = %address, "Serving web drop page");
+
+    let drop_server = Arc::new(WebDropServer {
+        web_drop,
+        receive,
+        uploads: Mutex::new(HashMap::new()),
+    });
+    Ok(thread::spawn(move || {
+        let mut requests: Vec<JoinHandle<()>> = Vec::new();
+        while !shutdown.load(Ordering::Relaxed) {
+            let request = match server.recv_timeout(POLL_INTERVAL) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(error = %e, "Web drop server failed");
+                    break;
+                }
+            };
+            requests.retain(|handle| !handle.is_finished());
+            if requests.len() >= MAX_CONCURRENT_REQUESTS {
+                warn!(
+                    max_requests = MAX_CONCURRENT_REQUESTS,
+                    "Too many web drop requests"
+                );
+                let _ = request.respond(error_response(503, "Too many requests, try again"));
+                continue;
+            }
+            // Uploads and downloads can take a while; serve each on its own.
+            let drop_server = drop_server.clone();
+            let span = info_span!(
+                "web_drop_request",
+                method = %request.method(),
+                path = path_of(&request),
+                client = ?request.remote_addr(),
+            );
+            requests.push(thread::spawn(move || {
+                let _entered = span.enter();
+                drop_server.handle(request);
+            }));
+        }
+    }))
+}
+
+impl<S: StoragePort> WebDropServer<S> {
+    fn handle(&self, mut request: Request) {
+        let path = path_of(&request).to_string();
+        let method = request.method().clone();
+        let response = match (&method, path.as_str()) {
+            (Method::Get, "/") => {
+                let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8")
+                    .expect("static header is valid");
+                Response::from_string(PAGE).with_header(header).boxed()
+            }
+            (Method::Post, "/api/session") => self.sign_in(&mut request),
+            _ if !self.is_signed_in(&request) => error_response(401, "Enter the PIN"),
+            (Method::Get, "/api/shares") => {
+                let shares: Vec<Value> = self
+                    .web_drop
+                    .shares()
+                    .into_iter()
+                    .map(|shared| json!({ "id": shared.id, "name": shared.name, "size": shared.size }))
+                    .collect();
+                json_response(200, &json!(shares))
+            }
+            (Method::Get, path) if path.starts_with("/files/") => {
+                self.download(&path["/files/".len()..])
+            }
+            (Method::Post, "/api/uploads") => self.start_upload(&mut request),
+            (Method::Put, path) if path.starts_with("/api/uploads/") => {
+                let file_id = path["/api/uploads/".len()..].to_string();
+                self.write_chunk(&mut request, &file_id)
+            }
+            (Method::Post, path)
+                if path.starts_with("/api/uploads/") && path.ends_with("/complete") =>
+            {
+                self.finish_upload(&path["/api/uploads/".len()..path.len() - "/complete".len()])
+            }
+            _ => error_response(404, "Not found"),
+        };
+        if let Err(e) = request.respond(response) {
+            debug!(error = %e, "Failed to answer web drop request");
+        }
+    }
+
+    fn sign_in(&self, request: &mut Request) -> ResponseBox {
+        let Some(attempt) = read_json::<SignIn>(request, MAX_JSON_SIZE) else {
+            return error_response(400, "Expected {\"pin\": \"...\"}");
+        };
+        let source = request
+            .remote_addr()
+            .map(|address| address.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let token = match self.web_drop.sign_in(&attempt.pin, source) {
+            Ok(token) => token,
+            Err(SignInError::WrongPin) => {
+                warn!("Wrong web drop PIN");
+                return error_response(401, "Wrong or expired PIN");
+            }
+            Err(SignInError::LockedOut(remaining)) => {
+                let seconds = remaining.as_secs().max(1);
+                let header = Header::from_bytes("Retry-After", seconds.to_string())
+                    .expect("a number is a valid header");
+                return error_response(
+                    429,
+                    &format!("Too many wrong PINs, try again in {} seconds", seconds),
+                )
+                .with_header(header);
+            }
+        };
+        let cookie = format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Strict",
+            SESSION_COOKIE, token
+        );
+        let header = Header::from_bytes("Set-Cookie", cookie).expect("token is a valid header");
+        Response::empty(204).with_header(header).boxed()
+    }
+
+    fn is_signed_in(&self, request: &Request) -> bool {
+        request
+            .headers()
+            .iter()
+            .filter(|header| header.field.equiv("Cookie"))
+            .flat_map(|header| header.value.as_str().split(';'))
+            .filter_map(|cookie| {
+                cookie
+                    .trim()
+                    .strip_prefix(SESSION_COOKIE)?
+                    .strip_prefix('=')
+            })
+            .any(|token| self.web_drop.is_signed_in(token))
+    }
+
+    fn download(&self, id: &str) -> ResponseBox {
+        let Some(shared) = self.web_drop.shared(id) else {
+            return error_response(404, "This file is no longer shared");
+        };
+        let file = match File::open(&shared.path) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!(file = %shared.path, error = %e, "Failed to open shared file");
+                return error_response(404, "This file is no longer available");
+            }
+        };
+        info!(file = %shared.path, "Web drop download");
+        let disposition = format!(
+            "attachment; filename*=UTF-8''{}",
+            percent_encode(&shared.name)
+        );
+        Response::from_file(file)
+            .with_header(
+                Header::from_bytes("Content-Type", "application/octet-stream")
+                    .expect("static header is valid"),
+            )
+            .with_header(
+                Header::from_bytes("Content-Disposition", disposition)
+                    .expect("encoded name is a valid header"),
+            )
+            .boxed()
+    }
+
+    fn start_upload(&self, request: &mut Request) -> ResponseBox {
+        let Some(upload) = read_json::<NewUpload>(request, MAX_JSON_SIZE) else {
+            return error_response(400, "Expected name, size and sha256");
+        };
+        let Some(name) = file_name(&upload.name) else {
+            return error_response(400, "Invalid file name");
+        };
+        let Some(sha256) = parse_sha256(&upload.sha256) else {
+            return error_response(400, "Invalid sha256");
+        };
+        let Some(sender) = request.remote_addr().copied() else {
+            return error_response(400, "Unknown client address");
+        };
+
+        // Keyed by content hash like peer transfers, so a retry resumes.
+        let manifest = FileManifest {
+            file_id: upload.sha256.to_ascii_lowercase(),
+            name,
+            size: upload.size,
+            sha256,
+        };
+        match self.receive.accept_transfer(&manifest, sender) {
+            Ok(offset) => {
+                info!(file = %manifest.name, size = manifest.size, offset, "Web drop upload accepted");
+                if let Ok(mut uploads) = self.uploads.lock() {
+                    uploads.insert(manifest.file_id.clone(), manifest.size);
+                }
+                json_response(
+                    200,
+                    &json!({ "upload_id": manifest.file_id, "offset": offset }),
+                )
+            }
+            Err(DomainError::TransferRejected) => {
+                info!(file = %manifest.name, "Web drop upload rejected by receive policy");
+                self.receive
+                    .report_outcome(&Err(DomainError::TransferRejected));
+                error_response(403, "This device does not accept the file")
+            }
+            Err(e) => internal_error(e),
+        }
+    }
+
+    fn write_chunk(&self, request: &mut Request, file_id: &str) -> ResponseBox {
+        let Some(size) = self.upload_size(file_id) else {
+            return error_response(404, "Unknown upload");
+        };
+        let offset = request
+            .url()
+            .split_once('?')
+            .and_then(|(_, query)| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("offset="))
+            })
+            .and_then(|offset| offset.parse::<u64>().ok());
+        let Some(offset) = offset else {
+            return error_response(400, "Missing offset");
+        };
+
+        let mut data = Vec::new();
+        if let Err(e) = request
+            .as_reader()
+            .take(MAX_CHUNK_SIZE + 1)
+            .read_to_end(&mut data)
+        {
+            debug!(error = %e, "Web drop upload interrupted");
+            return error_response(400, "Upload interrupted");
+        }
+        if data.len() as u64 > MAX_CHUNK_SIZE {
+            return error_response(413, "Chunk too large");
+        }
+        if offset + data.len() as u64 > size {
+            return error_response(400, "Chunk beyond the announced size");
+        }
+
+        let block = FileBlock {
+            file_id: file_id.to_string(),
+            offset,
+            data,
+        };
+        match self.receive.process_chunk(&block) {
+            Ok(()) => Response::empty(204).boxed(),
+            Err(e) => internal_error(e),
+        }
+    }
+
+    fn finish_upload(&self, file_id: &str) -> ResponseBox {
+        let Some(size) = self.upload_size(file_id) else {
+            return error_response(404, "Unknown upload");
+        };
+        let written = match self.receive.written_bytes(file_id) {
+            Ok(written) => written,
+            Err(e) => return internal_error(e),
+        };
+        if written < size {
+            return error_response(
+                409,
+                &format!("Upload incomplete: {} of {} bytes", written, size),
+            );
+        }
+
+        let result = self.receive.finish_transfer(file_id);
+        self.receive.report_outcome(&result);
+        if let Ok(mut uploads) = self.uploads.lock() {
+            uploads.remove(file_id);
+        }
+        match result {
+            Ok(()) => {
+                info!(bytes = size, "Web drop upload completed");
+                Response::empty(204).boxed()
+            }
+            Err(DomainError::IntegrityError) => {
+                let _ = self.receive.discard_transfer(file_id);
+                error_response(422, "The file arrived damaged, please upload it again")
+            }
+            Err(e) => internal_error(e),
+        }
+    }
+
+    fn upload_size(&self, file_id: &str) -> Option<u64> {
+        self.uploads.lock().ok()?.get(file_id).copied()
+    }
+}
+
+fn path_of(request: &Request) -> &str {
+    request.url().split('?').next().unwrap_or_default()
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(request: &mut Request, limit: u64) -> Option<T> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(limit)
+        .read_to_end(&mut body)
+        .ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+/// Browsers send paths on some platforms; only the last component is kept.
+fn file_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    if name.is_empty() || name == "." || name == ".." || name.contains('\0') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+fn parse_sha256(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 {
+        return None;
+    }
+    let mut sha = [0u8; 32];
+    for (byte, pair) in sha.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(sha)
+}
+
+/// RFC 5987 encoding for the download file name.
+fn percent_encode(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn json_response(status: u16, body: &Value) -> ResponseBox {
+    let header =
+        Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header)
+        .boxed()
+}
+
+fn error_response(status: u16, message: &str) -> ResponseBox {
+    json_response(status, &json!({ "error": message }))
+}
+
+fn internal_error(error: DomainError) -> ResponseBox {
+    warn!(error = ?error, "Web drop request failed");
+    error_response(500, "Something went wrong on the receiving device")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uploaded_names_cannot_leave_the_receive_directory() {
+        assert_eq!(file_name("photo.jpg").as_deref(), Some("photo.jpg"));
+        assert_eq!(file_name("../../.bashrc").as_deref(), Some(".bashrc"));
+        assert_eq!(
+            file_name("C:\\Users\\me\\cv.pdf").as_deref(),
+            Some("cv.pdf")
+        );
+        assert_eq!(file_name(".."), None);
+        assert_eq!(file_name("dir/"), None);
+
+        assert!(parse_sha256(&"ab".repeat(32)).is_some());
+        assert!(parse_sha256("../../etc/passwd").is_none());
+        assert!(parse_sha256(&"zz".repeat(32)).is_none());
+    }
+}
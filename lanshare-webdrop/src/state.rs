@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lanshare_app::time::unix_now;
+use lanshare_domain::{error::DomainError, models::SharedFile};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Wrong guesses allowed from one address before it is locked out.
+const MAX_PIN_ATTEMPTS: u32 = 5;
+/// The first lockout; each further one doubles, up to `MAX_LOCKOUT`.
+const LOCKOUT: Duration = Duration::from_secs(30);
+const MAX_LOCKOUT: Duration = Duration::from_secs(60 * 60);
+/// An address that stops guessing is forgotten after this long.
+const FAILURE_MEMORY: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long a browser stays signed in after entering the PIN.
+const SESSION_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+struct Pin {
+    value: String,
+    expires_at: u64,
+}
+
+/// Wrong PINs from one address.
+struct Failures {
+    attempts: u32,
+    lockouts: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+pub(crate) enum SignInError {
+    WrongPin,
+    /// Too many wrong PINs from this address; retry after the duration.
+    LockedOut(Duration),
+}
+
+/// What the web drop page allows right now: the PIN, the signed-in
+/// browsers and the files offered for download.
+pub struct WebDrop {
+    listen: SocketAddr,
+    pin_lifetime: Duration,
+    pin: Mutex<Pin>,
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+    sessions: Mutex<HashMap<String, Instant>>,
+    shares: Mutex<Vec<SharedFile>>,
+}
+
+impl WebDrop {
+    pub fn new(listen: SocketAddr, pin_lifetime: Duration) -> Self {
+        Self {
+            listen,
+            pin_lifetime,
+            pin: Mutex::new(new_pin(pin_lifetime)),
+            failures: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(HashMap::new()),
+            shares: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn listen(&self) -> SocketAddr {
+        self.listen
+    }
+
+    /// The current PIN and the unix time it stops working; an expired one is
+    /// replaced first.
+    pub fn pin(&self) -> Result<(String, u64), DomainError> {
+        let mut pin = self.pin.lock().map_err(|_| lock_failed())?;
+        if pin.expires_at <= unix_now() {
+            *pin = new_pin(self.pin_lifetime);
+        }
+        Ok((pin.value.clone(), pin.expires_at))
+    }
+
+    /// Trades the PIN for a session token. Wrong guesses lock out the
+    /// address they came from rather than replacing the owner's PIN.
+    pub(crate) fn sign_in(&self, attempt: &str, source: IpAddr) -> Result<String, SignInError> {
+        let now = Instant::now();
+        let mut failures = self.failures.lock().map_err(|_| SignInError::WrongPin)?;
+        failures.retain(|_, failed| {
+            failed.locked_until.is_some_and(|until| until > now)
+                || now.duration_since(failed.last_failure) < FAILURE_MEMORY
+        });
+        if let Some(until) = failures
+            .get(&source)
+            .and_then(|failed| failed.locked_until)
+            .filter(|until| *until > now)
+        {
+            return Err(SignInError::LockedOut(until - now));
+        }
+
+        let correct = {
+            let mut pin = self.pin.lock().map_err(|_| SignInError::WrongPin)?;
+            if pin.expires_at <= unix_now() {
+                *pin = new_pin(self.pin_lifetime);
+                return Err(SignInError::WrongPin);
+            }
+            pin.value == attempt.trim()
+        };
+        if !correct {
+            let failed = failures.entry(source).or_insert(Failures {
+                attempts: 0,
+                lockouts: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+            failed.attempts += 1;
+            failed.last_failure = now;
+            if failed.attempts >= MAX_PIN_ATTEMPTS {
+                let lockout = LOCKOUT
+                    .saturating_mul(1 << failed.lockouts.min(16))
+                    .min(MAX_LOCKOUT);
+                failed.attempts = 0;
+                failed.lockouts += 1;
+                failed.locked_until = Some(now + lockout);
+                warn!(
+                    client = %source,
+                    seconds = lockout.as_secs(),
+                    "Too many wrong web drop PINs, locking the client out"
+                );
+            }
+            return Err(SignInError::WrongPin);
+        }
+        failures.remove(&source);
+        drop(failures);
+
+        let token = Uuid::new_v4().simple().to_string();
+        let mut sessions = self.sessions.lock().map_err(|_| SignInError::WrongPin)?;
+        sessions.retain(|_, expires| *expires > Instant::now());
+        sessions.insert(token.clone(), Instant::now() + SESSION_LIFETIME);
+        Ok(token)
+    }
+
+    pub(crate) fn is_signed_in(&self, token: &str) -> bool {
+        self.sessions.lock().is_ok_and(|sessions| {
+            sessions
+                .get(token)
+                .is_some_and(|expires| *expires > Instant::now())
+        })
+    }
+
+    pub fn share(&self, path: &Path) -> Result<SharedFile, DomainError> {
+        let path = fs::canonicalize(path)?;
+        let metadata = fs::metadata(&path)?;
+        if !metadata.is_file() {
+            return Err(DomainError::NotFound(format!(
+                "{} is not a file",
+                path.display()
+            )));
+        }
+        let shared = SharedFile {
+            id: Uuid::new_v4().simple().to_string(),
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: path.to_string_lossy().into_owned(),
+            size: metadata.len(),
+        };
+        info!(file = %shared.path, "Sharing file on the web drop page");
+        self.shares
+            .lock()
+            .map_err(|_| lock_failed())?
+            .push(shared.clone());
+        Ok(shared)
+    }
+
+    pub fn unshare(&self, id: &str) -> Result<(), DomainError> {
+        let mut shares = self.shares.lock().map_err(|_| lock_failed())?;
+        let before = shares.len();
+        shares.retain(|shared| shared.id != id);
+        if shares.len() == before {
+            return Err(DomainError::NotFound(format!("No shared file {}", id)));
+        }
+        Ok(())
+    }
+
+    pub fn shares(&self) -> Vec<SharedFile> {
+        self.shares
+            .lock()
+            .map(|shares| shares.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn shared(&self, id: &str) -> Option<SharedFile> {
+        self.shares
+            .lock()
+            .ok()?
+            .iter()
+            .find(|shared| shared.id == id)
+            .cloned()
+    }
+}
+
+fn lock_failed() -> DomainError {
+    DomainError::IoError("Lock failed".into())
+}
+
+fn new_pin(lifetime: Duration) -> Pin {
+    Pin {
+        value: format!("{:06}", Uuid::new_v4().as_u128() % 1_000_000),
+        expires_at: unix_now() + lifetime.as_secs(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_wrong_pins_lock_out_only_their_address() {
+        let web_drop = WebDrop::new("127.0.0.1:0".parse().unwrap(), Duration::from_secs(60));
+        let guesser: IpAddr = "10.0.0.66".parse().unwrap();
+        let owner: IpAddr = "10.0.0.2".parse().unwrap();
+        let (pin, _) = web_drop.pin().unwrap();
+        let token = web_drop.sign_in(&pin, owner).ok().unwrap();
+        assert!(web_drop.is_signed_in(&token));
+        assert!(!web_drop.is_signed_in("guess"));
+
+        let wrong = if pin == "000000" { "000001" } else { "000000" };
+        for _ in 0..MAX_PIN_ATTEMPTS {
+            assert!(matches!(
+                web_drop.sign_in(wrong, guesser),
+                Err(SignInError::WrongPin)
+            ));
+        }
+        assert!(matches!(
+            web_drop.sign_in(&pin, guesser),
+            Err(SignInError::LockedOut(_))
+        ));
+
+        assert_eq!(web_drop.pin().unwrap().0, pin);
+        assert!(web_drop.sign_in(&pin, owner).is_ok());
+        assert!(web_drop.is_signed_in(&token));
+    }
+}